@@ -1,16 +1,33 @@
-use crate::pkg::character::dao::FileCharacterDao;
 use crate::pkg::character::service::CharacterServiceLocalImpl;
-use crate::pkg::dialog::dao::FileDialogDao;
+use crate::pkg::comment::dao::SqliteCommentDao;
+use crate::pkg::comment::service::CommentServiceLocalImpl;
+use crate::pkg::dialog::history::SqliteDialogHistoryDao;
 use crate::pkg::dialog::service::DialogServiceLocalImpl;
 use crate::pkg::gitter::service::GitService;
+use crate::pkg::localization::dao::FileLocalizationDao;
+use crate::pkg::localization::service::LocalizationServiceLocalImpl;
 use crate::pkg::meta::dao::FileMetaDao;
 use crate::pkg::meta::service::MetaServiceLocalImpl;
+use crate::pkg::migration::service::MigrationServiceLocalImpl;
+use crate::pkg::migration::steps::NdjsonStorageMigration;
 use crate::pkg::project::service::ProjectServiceLocaleImpl;
-use crate::pkg::variables::dao::FileVariableDao;
+use crate::pkg::search::index::SearchIndex;
+use crate::pkg::search::service::SearchServiceLocalImpl;
+use crate::pkg::serializer::service::SerializerServiceLocalImpl;
+use crate::pkg::spreadsheet::service::SpreadsheetServiceLocalImpl;
+use crate::pkg::stats::service::StatsServiceLocalImpl;
+use crate::pkg::storage::dao::{StorageCharacterDao, StorageDialogDao, StorageVariableDao};
+use crate::pkg::storage::service::StorageServiceLocalImpl;
+use crate::pkg::translation::service::TranslationServiceLocalImpl;
+use crate::pkg::user::dao::SqliteUserDao;
+use crate::pkg::user::service::UserServiceLocalImpl;
 use crate::pkg::variables::service::VariableServiceLocalImpl;
+use crate::pkg::voice::service::VoiceServiceLocalImpl;
+use crate::pkg::watcher::service::WatcherServiceLocalImpl;
 use crate::shared::types::interfaces::{FSUploader, Shared};
 use crate::shared::{
-    config::{ODConfig, ODConfigLocal},
+    config::{ODConfig, ODConfigLocal, DATABASE_FILE},
+    database::Database,
     state::AppState,
 };
 use anyhow::Result;
@@ -18,15 +35,104 @@ use std::sync::Arc;
 
 pub fn init_local_app_state() -> Result<AppState> {
     let config = ODConfigLocal::init()?;
+    let database = Arc::new(Database::new(config.get_root_dir().join(DATABASE_FILE)));
     let uploader = FSUploader {};
     let uploader_ref = Arc::new(uploader);
     let shared_conf = Shared::new(config);
-    let character_dao = Arc::new(FileCharacterDao::new(shared_conf.clone()));
-    let var_dao = Arc::new(FileVariableDao::new(shared_conf.clone()));
-    let dialog_dao = Arc::new(FileDialogDao::new(shared_conf.clone()));
+    let character_dao = Arc::new(StorageCharacterDao::new(shared_conf.clone()));
+    let var_dao = Arc::new(StorageVariableDao::new(shared_conf.clone()));
+    let dialog_dao = Arc::new(StorageDialogDao::new(shared_conf.clone()));
     let meta_dao = Arc::new(FileMetaDao::new(shared_conf.clone()));
+    let localization_dao = Arc::new(FileLocalizationDao::new(shared_conf.clone()));
+    let user_dao = Arc::new(SqliteUserDao::new(database.clone()));
+    let comment_dao = Arc::new(SqliteCommentDao::new(database.clone()));
+    let search_index = Arc::new(SearchIndex::new());
     let meta_srv = MetaServiceLocalImpl::new(shared_conf.clone(), meta_dao.clone());
     let git_srv = GitService::new(shared_conf.clone());
+    let dialog_srv = Arc::new(DialogServiceLocalImpl::new(
+        shared_conf.clone(),
+        dialog_dao.clone(),
+        character_dao.clone(),
+        meta_srv,
+        search_index.clone(),
+        Arc::new(SqliteDialogHistoryDao::new(database.clone())),
+        var_dao.clone(),
+        GitService::new(shared_conf.clone()),
+    ));
+    let var_srv = Arc::new(VariableServiceLocalImpl::new(
+        shared_conf.clone(),
+        var_dao.clone(),
+        character_dao.clone(),
+        dialog_dao.clone(),
+        meta_dao.clone(),
+        dialog_srv.clone(),
+        search_index.clone(),
+        GitService::new(shared_conf.clone()),
+    ));
+    let spreadsheet_srv = SpreadsheetServiceLocalImpl::new(
+        shared_conf.clone(),
+        dialog_srv.clone(),
+        character_dao.clone(),
+    );
+    let localization_srv = Arc::new(LocalizationServiceLocalImpl::new(
+        shared_conf.clone(),
+        localization_dao.clone(),
+        dialog_srv.clone(),
+        character_dao.clone(),
+        GitService::new(shared_conf.clone()),
+    ));
+    let translation_srv = TranslationServiceLocalImpl::new(
+        shared_conf.clone(),
+        localization_dao.clone(),
+        dialog_srv.clone(),
+        character_dao.clone(),
+        GitService::new(shared_conf.clone()),
+    );
+    let stats_srv = StatsServiceLocalImpl::new(
+        shared_conf.clone(),
+        localization_srv.clone(),
+        dialog_srv.clone(),
+        character_dao.clone(),
+        var_dao.clone(),
+    );
+    let voice_srv = VoiceServiceLocalImpl::new(
+        shared_conf.clone(),
+        localization_dao.clone(),
+        dialog_dao.clone(),
+        dialog_srv.clone(),
+        uploader_ref.clone(),
+        GitService::new(shared_conf.clone()),
+    );
+    let serializer_srv = Arc::new(SerializerServiceLocalImpl::new(
+        shared_conf.clone(),
+        dialog_dao.clone(),
+        character_dao.clone(),
+        var_dao.clone(),
+        meta_dao.clone(),
+        localization_dao.clone(),
+    ));
+    let search_srv = SearchServiceLocalImpl::new(
+        shared_conf.clone(),
+        search_index.clone(),
+        dialog_srv.clone(),
+        dialog_dao.clone(),
+        character_dao.clone(),
+        var_dao.clone(),
+        GitService::new(shared_conf.clone()),
+    );
+    let watcher_srv = Arc::new(WatcherServiceLocalImpl::new(
+        shared_conf.clone(),
+        dialog_srv.clone(),
+        dialog_dao.clone(),
+        character_dao.clone(),
+        var_dao.clone(),
+        search_index.clone(),
+    ));
+    let migration_srv = MigrationServiceLocalImpl::new(
+        shared_conf.clone(),
+        GitService::new(shared_conf.clone()),
+        vec![Arc::new(NdjsonStorageMigration::new(serializer_srv.clone()))],
+    );
 
     Ok(AppState {
         project_service: ProjectServiceLocaleImpl::new(shared_conf.clone()),
@@ -34,20 +140,33 @@ pub fn init_local_app_state() -> Result<AppState> {
             shared_conf.clone(),
             character_dao.clone(),
             uploader_ref,
+            search_index.clone(),
+            dialog_srv.clone(),
+            dialog_dao.clone(),
+            meta_dao.clone(),
+            var_dao.clone(),
+            var_srv.clone(),
+            GitService::new(shared_conf.clone()),
         ),
-        dialog_service: DialogServiceLocalImpl::new(
+        dialog_service: dialog_srv,
+        var_service: var_srv,
+        git_service: git_srv,
+        spreadsheet_service: spreadsheet_srv,
+        localization_service: localization_srv,
+        translation_service: translation_srv,
+        stats_service: stats_srv,
+        voice_service: voice_srv,
+        serializer_service: serializer_srv,
+        migration_service: migration_srv,
+        storage_service: StorageServiceLocalImpl::new(
             shared_conf.clone(),
             dialog_dao.clone(),
             character_dao.clone(),
-            meta_srv,
-        ),
-        var_service: VariableServiceLocalImpl::new(
-            shared_conf.clone(),
             var_dao.clone(),
-            character_dao.clone(),
-            dialog_dao.clone(),
-            meta_dao.clone(),
         ),
-        git_service: git_srv,
+        user_service: UserServiceLocalImpl::new(shared_conf.clone(), user_dao),
+        comment_service: CommentServiceLocalImpl::new(shared_conf.clone(), comment_dao),
+        search_service: search_srv,
+        watcher_service: watcher_srv,
     })
 }
@@ -24,6 +24,20 @@ pub fn run() {
             pkg::character::persist_metadata,
             pkg::character::get_character_by_id,
             pkg::character::delete_character,
+            pkg::character::get_character_references,
+            pkg::character::set_character_tags,
+            pkg::character::create_character_group,
+            pkg::character::rename_character_group,
+            pkg::character::delete_character_group,
+            pkg::character::add_character_to_group,
+            pkg::character::remove_character_from_group,
+            pkg::character::create_character_attribute,
+            pkg::character::rename_character_attribute,
+            pkg::character::delete_character_attribute,
+            pkg::character::create_character_relationship,
+            pkg::character::change_character_relationship,
+            pkg::character::delete_character_relationship,
+            pkg::character::get_relationship_graph,
 
             pkg::dialog::create_dialog,
             pkg::dialog::get_dialog_by_id,
@@ -32,13 +46,83 @@ pub fn run() {
             pkg::dialog::save_dialog_content,
             pkg::dialog::save_dialog_metadata,
             pkg::dialog::delete_dialog,
+            pkg::dialog::get_dialog_references,
+            pkg::dialog::get_story_graph,
+            pkg::dialog::get_dialogs_featuring_character,
+            pkg::dialog::undo_dialog,
+            pkg::dialog::redo_dialog,
+            pkg::dialog::get_dialog_history,
+            pkg::dialog::create_dialog_group,
+            pkg::dialog::rename_dialog_group,
+            pkg::dialog::move_dialog_group,
+            pkg::dialog::delete_dialog_group,
+            pkg::dialog::assign_dialog_to_group,
 
             pkg::variables::load_variables,
             pkg::variables::persist_variables,
+            pkg::variables::check_variable_states,
             pkg::variables::delete_variable,
+            pkg::variables::get_variable_usages,
 
             pkg::gitter::get_logs,
             pkg::gitter::commit,
+            pkg::gitter::get_snapshots_enabled,
+            pkg::gitter::set_snapshots_enabled,
+            pkg::gitter::take_snapshot,
+            pkg::gitter::get_snapshots,
+            pkg::gitter::recover_snapshot,
+
+            pkg::spreadsheet::export_dialog_lines,
+            pkg::spreadsheet::export_character_sheet,
+            pkg::spreadsheet::import_dialog_lines,
+
+            pkg::localization::get_locale_settings,
+            pkg::localization::save_locale_settings,
+            pkg::localization::get_localized_dialog,
+            pkg::localization::save_localized_line,
+            pkg::localization::get_localized_characters,
+            pkg::localization::save_localized_character_name,
+            pkg::localization::get_translation_progress,
+
+            pkg::translation::export_translations,
+            pkg::translation::import_translations,
+
+            pkg::stats::get_project_stats,
+
+            pkg::voice::get_dialog_audio,
+            pkg::voice::attach_line_audio,
+            pkg::voice::detach_line_audio,
+            pkg::voice::get_missing_audio,
+
+            pkg::serializer::migrate_storage_format,
+
+            pkg::migration::open_project,
+
+            pkg::storage::get_storage_backend,
+            pkg::storage::convert_project_storage,
+
+            pkg::user::get_user_profile,
+            pkg::user::get_user_profiles,
+            pkg::user::get_recent_projects,
+            pkg::user::record_recent_project,
+            pkg::user::get_ui_state,
+            pkg::user::save_ui_state,
+
+            pkg::comment::add_comment,
+            pkg::comment::reply_to_comment,
+            pkg::comment::edit_comment,
+            pkg::comment::delete_comment,
+            pkg::comment::resolve_comment,
+            pkg::comment::unresolve_comment,
+            pkg::comment::get_open_dialog_comments,
+            pkg::comment::get_open_project_comments,
+
+            pkg::search::search,
+            pkg::search::preview_replace,
+            pkg::search::apply_replace,
+
+            pkg::watcher::watch_project,
+            pkg::watcher::unwatch_project,
 
         ])
         .run(tauri::generate_context!())
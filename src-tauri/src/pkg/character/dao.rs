@@ -1,7 +1,6 @@
 use std::{
     collections::HashSet,
-    fs::{self, File},
-    io::BufWriter,
+    fs,
     path::PathBuf,
     str::FromStr,
 };
@@ -9,11 +8,17 @@ use std::{
 use anyhow::{bail, Context, Result};
 use uuid::Uuid;
 
+use crate::pkg::serializer::{
+    ndjson::{read_ndjson, write_ndjson},
+    protobuf::{read_protobuf, write_protobuf},
+};
 use crate::shared::{
     config::{ODConfig, CHAR_DIRNAME},
+    own_writes,
     types::{
         character::{Character, CharacterMetadata},
         interfaces::Shared,
+        proto,
     },
 };
 
@@ -21,6 +26,12 @@ pub struct FileCharacterDao<C: ODConfig> {
     config: Shared<C>,
 }
 
+/// Stores characters as protobuf in `<id>.pb` files, descriptions
+/// and metadata are kept in the same files as the json storage.
+pub struct ProtoCharacterDao<C: ODConfig> {
+    json: FileCharacterDao<C>,
+}
+
 pub trait CharacterDao<C: ODConfig> {
     fn persist_character(&self, project_id: &str, character: &Character) -> Result<()>; // persistence
     // d'une seule entité
@@ -41,39 +52,29 @@ impl<C: ODConfig> CharacterDao<C> for FileCharacterDao<C> {
         match char.get_description_link() {
             Some(d) => {
                 let desc_file_name = self.get_desc_file_name(project_id, &d)?;
-                fs::remove_file(desc_file_name)
+                own_writes::remove_file(&desc_file_name)
                     .context("could not delete description file of character to be deleted")?;
             }
             None => {}
         }
         let char_path = self.get_char_path(project_id, char_id)?;
-        fs::remove_file(char_path).context("could not delete file of character")
+        own_writes::remove_file(&char_path).context("could not delete file of character")
     }
 
     fn save_metadata(&self, project_id: &str, metadata: CharacterMetadata) -> Result<()> {
-        Ok(self
-            .get_char_dir(project_id)
-            .map(|cp| File::create(cp.join("meta.json")))
-            .context("error creating the metadata file")?
-            .map(|file| BufWriter::new(file))
-            .map(|writer| serde_json::to_writer(writer, &metadata))
-            .context("could not serialize character to write into file")??)
+        write_ndjson(&self.get_char_dir(project_id)?.join("meta.json"), &metadata)
+            .context("could not serialize character metadata to write into file")
     }
 
     fn get_meta_file(&self, project_id: &str) -> Result<CharacterMetadata> {
-        Ok(fs::read(self.get_char_dir(project_id)?.join("meta.json"))
-            .context("could not read character file")
-            .map(|b| serde_json::from_slice(&b))
-            .context("could not deserialize file into character metadata.")??)
+        read_ndjson(&self.get_char_dir(project_id)?.join("meta.json"))
+            .context("could not deserialize file into character metadata.")
     }
     fn persist_character(&self, project_id: &str, character: &Character) -> Result<()> {
-        Ok(self
-            .get_char_path(project_id, character.get_id())
-            .map(|cp| File::create(cp))
-            .context("error creating the character file")?
-            .map(|file| BufWriter::new(file))
-            .map(|writer| serde_json::to_writer(writer, &character))
-            .context("could not serialize character to write into file")??)
+        let content = serde_json::to_vec(character)
+            .context("could not serialize character to write into file")?;
+        own_writes::write(&self.get_char_path(project_id, character.get_id())?, content)
+            .context("error writing the character file")
     }
 
     fn get_character(&self, project_id: &str, char_id: &Uuid) -> Result<Character> {
@@ -98,7 +99,7 @@ impl<C: ODConfig> CharacterDao<C> for FileCharacterDao<C> {
     fn persist_description(&self, project_id: &str, desc_id: &Uuid, desc: &str) -> Result<()> {
         Ok(self
             .get_desc_file_name(project_id, desc_id)
-            .map(|dfs| fs::write(dfs, desc))
+            .map(|dfs| own_writes::write(&dfs, desc))
             .context("could not write description to file")??)
     }
 
@@ -153,3 +154,76 @@ impl<C: ODConfig> FileCharacterDao<C> {
             .join(CHAR_DIRNAME))
     }
 }
+
+impl<C: ODConfig> CharacterDao<C> for ProtoCharacterDao<C> {
+    fn delete_character_by_id(&self, project_id: &str, char_id: &Uuid) -> Result<()> {
+        let char = self.get_character(project_id, char_id)?;
+        if let Some(d) = char.get_description_link() {
+            own_writes::remove_file(&self.json.get_desc_file_name(project_id, &d)?)
+                .context("could not delete description file of character to be deleted")?;
+        }
+        own_writes::remove_file(&self.get_char_path(project_id, char_id)?)
+            .context("could not delete file of character")
+    }
+
+    fn save_metadata(&self, project_id: &str, metadata: CharacterMetadata) -> Result<()> {
+        self.json.save_metadata(project_id, metadata)
+    }
+
+    fn get_meta_file(&self, project_id: &str) -> Result<CharacterMetadata> {
+        self.json.get_meta_file(project_id)
+    }
+
+    fn persist_character(&self, project_id: &str, character: &Character) -> Result<()> {
+        write_protobuf::<proto::Character, Character>(
+            &self.get_char_path(project_id, character.get_id())?,
+            character,
+        )
+        .context("could not encode character to write into file")
+    }
+
+    fn get_character(&self, project_id: &str, char_id: &Uuid) -> Result<Character> {
+        let mut character: Character =
+            read_protobuf::<proto::Character, Character>(&self.get_char_path(project_id, char_id)?)
+                .context("could not decode file into character.")?;
+        if let Some(dl) = character.get_description_link() {
+            let file = fs::read_to_string(self.json.get_desc_file_name(project_id, &dl)?)?;
+            character.set_description(&file);
+        }
+        Ok(character)
+    }
+
+    fn persist_description(&self, project_id: &str, desc_id: &Uuid, desc: &str) -> Result<()> {
+        self.json.persist_description(project_id, desc_id, desc)
+    }
+
+    fn enforce_character_existence(&self, project_id: &str, char_id: &Uuid) -> Result<()> {
+        if fs::exists(self.get_char_path(project_id, char_id)?)? {
+            return Ok(());
+        }
+        bail!("char {char_id} did not exist")
+    }
+
+    fn get_character_identifiers(&self, project_id: &str) -> Result<HashSet<Uuid>> {
+        fs::read_dir(self.json.get_char_dir(project_id)?)?
+            .filter_map(|entry| entry.ok())
+            .filter(|path| path.path().extension().is_some_and(|e| e == "pb"))
+            .filter_map(|e| e.path().file_stem()?.to_str().map(String::from))
+            .map(|name| {
+                Uuid::from_str(&name).context(format!("could not create uuid from string {name}"))
+            })
+            .collect::<Result<HashSet<Uuid>>>()
+    }
+}
+
+impl<C: ODConfig> ProtoCharacterDao<C> {
+    pub fn new(config: Shared<C>) -> Self {
+        ProtoCharacterDao {
+            json: FileCharacterDao::new(config),
+        }
+    }
+
+    pub fn get_char_path(&self, project_id: &str, char_id: &Uuid) -> Result<PathBuf> {
+        Ok(self.json.get_char_dir(project_id)?.join(format!("{char_id}.pb")))
+    }
+}
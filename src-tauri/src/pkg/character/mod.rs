@@ -1,4 +1,8 @@
-use crate::shared::types::character::{Character, CharacterForm, CharacterMetadata, ImageField};
+use crate::shared::types::character::{
+    AttributeKind, Character, CharacterAttribute, CharacterDeletionMode, CharacterFilter,
+    CharacterForm, CharacterGroup, CharacterMetadata, CharacterReferences, CharacterRelationship,
+    ImageField, RelationshipForm, RelationshipGraph,
+};
 use od_macros::tauri_command;
 use uuid::Uuid;
 
@@ -15,7 +19,7 @@ pub fn change_character(project_id: &str, char_form: CharacterForm) -> Character
 pub fn upload_image(project_id: &str, char_id: &str, path: &str, field: ImageField) -> () {}
 
 #[tauri_command(character_service)]
-pub fn get_all_characters(project_id: &str) -> CharacterMetadata {}
+pub fn get_all_characters(project_id: &str, filter: Option<CharacterFilter>) -> CharacterMetadata {}
 
 #[tauri_command(character_service)]
 pub fn persist_metadata(project_id: &str, metadata: CharacterMetadata) {}
@@ -24,4 +28,70 @@ pub fn persist_metadata(project_id: &str, metadata: CharacterMetadata) {}
 pub fn get_character_by_id(project_id: &str, character_id: Uuid) -> Character {}
 
 #[tauri_command(character_service)]
-pub fn delete_character(project_id: &str, character_id:Uuid) {}
+pub fn delete_character(
+    project_id: &str,
+    character_id: Uuid,
+    mode: CharacterDeletionMode,
+) -> CharacterReferences {
+}
+
+#[tauri_command(character_service)]
+pub fn get_character_references(project_id: &str, character_id: Uuid) -> CharacterReferences {}
+
+#[tauri_command(character_service)]
+pub fn set_character_tags(project_id: &str, character_id: Uuid, tags: Vec<String>) -> Character {}
+
+#[tauri_command(character_service)]
+pub fn create_character_group(project_id: &str, name: &str) -> CharacterGroup {}
+
+#[tauri_command(character_service)]
+pub fn rename_character_group(project_id: &str, group_id: Uuid, name: &str) {}
+
+#[tauri_command(character_service)]
+pub fn delete_character_group(project_id: &str, group_id: Uuid) {}
+
+#[tauri_command(character_service)]
+pub fn add_character_to_group(project_id: &str, character_id: Uuid, group_id: Uuid) -> Character {}
+
+#[tauri_command(character_service)]
+pub fn remove_character_from_group(
+    project_id: &str,
+    character_id: Uuid,
+    group_id: Uuid,
+) -> Character {
+}
+
+#[tauri_command(character_service)]
+pub fn create_character_attribute(
+    project_id: &str,
+    name: &str,
+    kind: AttributeKind,
+) -> CharacterAttribute {
+}
+
+#[tauri_command(character_service)]
+pub fn rename_character_attribute(project_id: &str, attribute_id: Uuid, name: &str) {}
+
+#[tauri_command(character_service)]
+pub fn delete_character_attribute(project_id: &str, attribute_id: Uuid) {}
+
+#[tauri_command(character_service)]
+pub fn create_character_relationship(
+    project_id: &str,
+    form: RelationshipForm,
+) -> CharacterRelationship {
+}
+
+#[tauri_command(character_service)]
+pub fn change_character_relationship(
+    project_id: &str,
+    relationship_id: Uuid,
+    form: RelationshipForm,
+) -> CharacterRelationship {
+}
+
+#[tauri_command(character_service)]
+pub fn delete_character_relationship(project_id: &str, relationship_id: Uuid) {}
+
+#[tauri_command(character_service)]
+pub fn get_relationship_graph(project_id: &str, character_id: Option<Uuid>) -> RelationshipGraph {}
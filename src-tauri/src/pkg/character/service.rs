@@ -1,31 +1,82 @@
-use std::{str::FromStr, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::Arc,
+};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use uuid::Uuid;
 
 use crate::{
-    pkg::character::{dao::CharacterDao},
+    pkg::{
+        character::dao::CharacterDao,
+        dialog::{dao::DialogDao, service::DialogServiceLocalImpl},
+        gitter::service::GitService,
+        meta::dao::MetaDao,
+        search::index::SearchIndex,
+        variables::{dao::VariableDao, service::VariableServiceLocalImpl},
+    },
     shared::{
         config::ODConfig,
         types::{
-            character::{Character, CharacterForm, CharacterMetadata, ImageField, SimpleCharacter},
+            character::{
+                AttributeKind, Character, CharacterAttribute, CharacterDeletionMode,
+                CharacterFilter, CharacterForm, CharacterGroup, CharacterMetadata,
+                CharacterReferences, CharacterRelationship, ImageField, RelationshipForm,
+                RelationshipGraph, RelationshipStrength, SimpleCharacter,
+            },
+            dialog::NodeReference,
             interfaces::{Shared, Uploader},
+            variables::VariableStore,
         },
     },
 };
 
-pub struct CharacterServiceLocalImpl<C: ODConfig, D: CharacterDao<C>> {
+pub struct CharacterServiceLocalImpl<
+    C: ODConfig,
+    D: CharacterDao<C>,
+    DD: DialogDao<C>,
+    MD: MetaDao<C>,
+    VD: VariableDao<C>,
+> {
     config: Shared<C>,
     dao: Arc<D>,
     uploader: Arc<dyn Uploader>,
+    search_index: Arc<SearchIndex>,
+    dialog_srv: Arc<DialogServiceLocalImpl<C, DD, D, MD>>,
+    dialog_dao: Arc<DD>,
+    meta_dao: Arc<MD>,
+    var_dao: Arc<VD>,
+    var_srv: Arc<VariableServiceLocalImpl<C, VD, D, DD, MD>>,
+    git_srv: GitService<C>,
 }
 
-impl<C: ODConfig, D: CharacterDao<C>> CharacterServiceLocalImpl<C, D> {
-    pub fn new(config: Shared<C>, dao: Arc<D>, uploader: Arc<dyn Uploader>) -> Self {
+impl<C: ODConfig, D: CharacterDao<C>, DD: DialogDao<C>, MD: MetaDao<C>, VD: VariableDao<C>>
+    CharacterServiceLocalImpl<C, D, DD, MD, VD>
+{
+    pub fn new(
+        config: Shared<C>,
+        dao: Arc<D>,
+        uploader: Arc<dyn Uploader>,
+        search_index: Arc<SearchIndex>,
+        dialog_srv: Arc<DialogServiceLocalImpl<C, DD, D, MD>>,
+        dialog_dao: Arc<DD>,
+        meta_dao: Arc<MD>,
+        var_dao: Arc<VD>,
+        var_srv: Arc<VariableServiceLocalImpl<C, VD, D, DD, MD>>,
+        git_srv: GitService<C>,
+    ) -> Self {
         CharacterServiceLocalImpl {
             config,
             uploader,
             dao,
+            search_index,
+            dialog_srv,
+            dialog_dao,
+            meta_dao,
+            var_dao,
+            var_srv,
+            git_srv,
         }
     }
 
@@ -41,21 +92,233 @@ impl<C: ODConfig, D: CharacterDao<C>> CharacterServiceLocalImpl<C, D> {
         metadata.persist_character(simple_char);
         self.dao.persist_character(project_id, &character)?;
         self.dao.save_metadata(project_id, metadata)?;
+        self.search_index.mark_character_stale(project_id, character.get_id())?;
+        self.git_srv.snapshot_after_save(project_id);
         Ok(character)
     }
 
-    pub fn delete_character(&self, project_id: &str, character_id:Uuid) -> Result<()> {
+    /// Deletes the character, handling the references to it according to the mode.
+    /// Returns the references found before the deletion.
+    pub fn delete_character(
+        &self,
+        project_id: &str,
+        character_id: Uuid,
+        mode: CharacterDeletionMode,
+    ) -> Result<CharacterReferences> {
         let mut metadata = self.dao.get_meta_file(project_id)?;
+        metadata.get_character_by_id(&character_id)?;
+        let refs = self.get_character_references(project_id, character_id)?;
+        match mode {
+            CharacterDeletionMode::Block if !refs.is_empty() => bail!(
+                "character {character_id} is still referenced by {} dialogs, {} nodes, {} variables, {} characters and {} relationships",
+                refs.main_character_of.len(),
+                refs.speaking_nodes.len(),
+                refs.character_variables.len() + refs.global_character_variables.len(),
+                refs.attributes_of.len(),
+                refs.relationships.len()
+            ),
+            CharacterDeletionMode::Block => (),
+            CharacterDeletionMode::Reassign(to) => {
+                self.reassign_references(project_id, &character_id, to, &refs)?;
+                metadata.replace_relationship_character(&character_id, Some(to));
+            }
+            CharacterDeletionMode::Cascade => {
+                self.cascade_references(project_id, &character_id, &refs)?;
+                metadata.replace_relationship_character(&character_id, None);
+            }
+        }
         metadata.delete_character_by_id(&character_id)?;
         self.dao.delete_character_by_id(project_id, &character_id)?;
-        self.dao.save_metadata(project_id, metadata)
+        self.search_index.mark_character_stale(project_id, &character_id)?;
+        self.dao.save_metadata(project_id, metadata)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(refs)
+    }
+
+    /// Lists the dialogs, nodes, variables, character attributes
+    /// and relationships pointing to the character.
+    pub fn get_character_references(
+        &self,
+        project_id: &str,
+        character_id: Uuid,
+    ) -> Result<CharacterReferences> {
+        let mut refs = CharacterReferences::default();
+        let mut dialog_ids: Vec<Uuid> = self
+            .dialog_dao
+            .get_dialog_identifiers(project_id)?
+            .into_iter()
+            .collect();
+        dialog_ids.sort();
+        for dialog_id in dialog_ids {
+            let dialog = self.dialog_dao.get_dialog_by_id(project_id, &dialog_id)?;
+            if *dialog.get_main_character() == character_id {
+                refs.main_character_of.push(dialog_id);
+            }
+            for node_id in dialog.get_speaking_nodes(&character_id) {
+                refs.speaking_nodes.push(NodeReference { dialog_id, node_id });
+            }
+        }
+        let vars = self.var_dao.load_variables(project_id)?;
+        refs.character_variables = vars.get_character_variables(&character_id);
+        refs.global_character_variables = vars.get_global_character_variables(&character_id);
+        let mut character_ids: Vec<Uuid> = self
+            .dao
+            .get_character_identifiers(project_id)?
+            .into_iter()
+            .filter(|id| *id != character_id)
+            .collect();
+        character_ids.sort();
+        for id in character_ids {
+            if self.dao.get_character(project_id, &id)?.references_character(&character_id) {
+                refs.attributes_of.push(id);
+            }
+        }
+        refs.relationships = self
+            .dao
+            .get_meta_file(project_id)?
+            .get_relationships(Some(&character_id))
+            .into_iter()
+            .map(|r| *r.get_id())
+            .collect();
+        Ok(refs)
+    }
+
+    /// Points, or without a replacement removes, the attributes
+    /// of the characters referencing the character.
+    fn replace_attribute_references(
+        &self,
+        project_id: &str,
+        character_id: &Uuid,
+        by: Option<Uuid>,
+        refs: &CharacterReferences,
+    ) -> Result<()> {
+        for id in &refs.attributes_of {
+            let mut character = self.dao.get_character(project_id, id)?;
+            character.replace_character_reference(character_id, by);
+            self.dao.persist_character(project_id, &character)?;
+            self.search_index.mark_character_stale(project_id, id)?;
+        }
+        Ok(())
+    }
+
+    fn reassign_references(
+        &self,
+        project_id: &str,
+        character_id: &Uuid,
+        to: Uuid,
+        refs: &CharacterReferences,
+    ) -> Result<()> {
+        if to == *character_id {
+            bail!("character {character_id} cannot be reassigned to itself")
+        }
+        self.dao.enforce_character_existence(project_id, &to)?;
+        let vars = if refs.has_variables() {
+            let mut vars = self.var_dao.load_variables(project_id)?;
+            vars.reassign_character(character_id, &to);
+            Some(vars)
+        } else {
+            None
+        };
+        self.replace_in_dialogs(project_id, character_id, Some(to), &refs.get_dialog_ids(), vars)?;
+        self.replace_attribute_references(project_id, character_id, Some(to), refs)
+    }
 
+    /// Replaces the character in the dialogs, then saves the variables through
+    /// the variable service. Every dialog is checked before any is written, and
+    /// the dialogs already written are restored if a later save fails.
+    fn replace_in_dialogs(
+        &self,
+        project_id: &str,
+        character_id: &Uuid,
+        by: Option<Uuid>,
+        dialog_ids: &[Uuid],
+        vars: Option<VariableStore>,
+    ) -> Result<()> {
+        let mut dialogs = Vec::with_capacity(dialog_ids.len());
+        for dialog_id in dialog_ids {
+            let mut dialog = self.dialog_srv.get_dialog_by_id(project_id, *dialog_id)?;
+            dialog.replace_character(character_id, by)?;
+            self.dialog_srv.enforce_dialog_coherence(project_id, &dialog)?;
+            dialogs.push(dialog);
+        }
+        let mut originals = vec![];
+        let mut saved = Ok(());
+        for dialog in dialogs {
+            saved = self
+                .dialog_srv
+                .get_dialog_by_id(project_id, dialog.get_id())
+                .and_then(|original| {
+                    originals.push(original);
+                    self.dialog_srv.save_dialog(project_id, dialog)
+                });
+            if saved.is_err() {
+                break;
+            }
+        }
+        if let Some(vars) = vars {
+            saved = saved.and_then(|_| self.var_srv.persist_variables(project_id, vars, None));
+        }
+        saved.map_err(|e| self.dialog_srv.restore_dialogs(project_id, originals, e))
+    }
+
+    /// Fails before any change if character variables are still used by conditions
+    /// of dialogs which are not deleted along with the character, or if the deleted
+    /// dialogs are linked by, or have variables used in, the remaining dialogs.
+    fn cascade_references(
+        &self,
+        project_id: &str,
+        character_id: &Uuid,
+        refs: &CharacterReferences,
+    ) -> Result<()> {
+        let mut deleted_phylums: HashSet<Uuid> = HashSet::new();
+        for dialog_id in &refs.main_character_of {
+            let dialog = self.dialog_dao.get_dialog_by_id(project_id, dialog_id)?;
+            deleted_phylums.extend(dialog.get_phylums_map().into_keys());
+        }
+        let fks = self.meta_dao.get_var_to_phylum_map(project_id)?;
+        for var_id in &refs.character_variables {
+            if fks
+                .get_phylums(var_id)
+                .iter()
+                .any(|p| !deleted_phylums.contains(p))
+            {
+                bail!("character variable {var_id} is still used in conditions of other dialogs")
+            }
+        }
+
+        if !refs.main_character_of.is_empty() {
+            self.dialog_srv
+                .delete_dialogs(project_id, &refs.main_character_of)?;
+        }
+        let dialog_ids: Vec<Uuid> = refs
+            .get_dialog_ids()
+            .into_iter()
+            .filter(|id| !refs.main_character_of.contains(id))
+            .collect();
+        let vars = if refs.has_variables() {
+            let mut vars = self.var_dao.load_variables(project_id)?;
+            vars.remove_character(character_id);
+            Some(vars)
+        } else {
+            None
+        };
+        self.replace_in_dialogs(project_id, character_id, None, &dialog_ids, vars)?;
+        if refs.has_variables() {
+            let mut fks = self.meta_dao.get_var_to_phylum_map(project_id)?;
+            for var_id in &refs.character_variables {
+                fks.data.remove(var_id);
+            }
+            self.meta_dao.save_var_to_phylum(project_id, fks)?;
+        }
+        self.replace_attribute_references(project_id, character_id, None, refs)
     }
 
     pub fn persist_metadata(&self, project_id: &str, metadata: CharacterMetadata) -> Result<()> {
         let old = self.dao.get_meta_file(project_id)?;
         old.enforce_characters_unchanged(&metadata)?;
-        self.dao.save_metadata(project_id, metadata)
+        self.dao.save_metadata(project_id, metadata)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
     }
 
     pub fn change_character(
@@ -65,6 +328,11 @@ impl<C: ODConfig, D: CharacterDao<C>> CharacterServiceLocalImpl<C, D> {
     ) -> Result<Character> {
         let char_uuid = Uuid::from_str(char_form.id)?;
         let mut character = self.dao.get_character(project_id, &char_uuid)?;
+        if let Some(attributes) = &char_form.attributes {
+            self.dao
+                .get_meta_file(project_id)?
+                .validate_attribute_values(attributes)?;
+        }
         match &char_form.description {
             Some(desc) => self.persist_description(project_id, &mut character, &desc)?,
             None => (),
@@ -73,6 +341,8 @@ impl<C: ODConfig, D: CharacterDao<C>> CharacterServiceLocalImpl<C, D> {
         character.change_from_form(&char_form);
         self.update_metadata(project_id, &char_uuid, &character)?;
         self.dao.persist_character(project_id, &character)?;
+        self.search_index.mark_character_stale(project_id, &char_uuid)?;
+        self.git_srv.snapshot_after_save(project_id);
         Ok(character)
     }
 
@@ -115,14 +385,274 @@ impl<C: ODConfig, D: CharacterDao<C>> CharacterServiceLocalImpl<C, D> {
             }
             ImageField::Artwork => {}
             ImageField::Background => {}
+            ImageField::Attribute(id) => {
+                let metadata = self.dao.get_meta_file(project_id)?;
+                if *metadata.get_attribute(&id)?.get_kind() != AttributeKind::Image {
+                    bail!("attribute {id} is not an image")
+                }
+            }
         }
         character.upload_image(from, &project_path, self.uploader.clone(), field)?;
-        self.dao.persist_character(project_id, &character)
+        self.dao.persist_character(project_id, &character)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
+    }
+
+
+    pub fn get_all_characters(
+        &self,
+        project_id: &str,
+        filter: Option<CharacterFilter>,
+    ) -> Result<CharacterMetadata> {
+        let mut metadata = self.dao.get_meta_file(project_id)?;
+        if let Some(filter) = filter {
+            metadata.retain_matching(&filter);
+        }
+        Ok(metadata)
+    }
+
+    pub fn set_character_tags(
+        &self,
+        project_id: &str,
+        character_id: Uuid,
+        tags: Vec<String>,
+    ) -> Result<Character> {
+        let mut character = self.dao.get_character(project_id, &character_id)?;
+        character.set_tags(tags);
+        self.dao.persist_character(project_id, &character)?;
+        self.update_metadata(project_id, &character_id, &character)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(character)
+    }
+
+    pub fn create_character_group(&self, project_id: &str, name: &str) -> Result<CharacterGroup> {
+        let mut metadata = self.dao.get_meta_file(project_id)?;
+        let group = metadata.create_group(name)?;
+        self.dao.save_metadata(project_id, metadata)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(group)
+    }
+
+    pub fn rename_character_group(&self, project_id: &str, group_id: Uuid, name: &str) -> Result<()> {
+        let mut metadata = self.dao.get_meta_file(project_id)?;
+        metadata.rename_group(&group_id, name)?;
+        self.dao.save_metadata(project_id, metadata)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
+    }
+
+    /// Refuses to delete a group targeted by global character variables.
+    /// The metadata is saved last, and the members are restored if a save fails.
+    pub fn delete_character_group(&self, project_id: &str, group_id: Uuid) -> Result<()> {
+        let mut metadata = self.dao.get_meta_file(project_id)?;
+        let targets = self.var_dao.load_variables(project_id)?.get_group_targets(&group_id);
+        if !targets.is_empty() {
+            bail!("group {group_id} is still targeted by {} variables", targets.len())
+        }
+        let members = metadata
+            .get_group_members()
+            .remove(&group_id)
+            .unwrap_or_default();
+        metadata.delete_group(&group_id)?;
+        let mut characters = vec![];
+        for character_id in members {
+            characters.push(self.dao.get_character(project_id, &character_id)?);
+        }
+
+        let mut originals: Vec<Character> = vec![];
+        let mut saved = Ok(());
+        for original in characters {
+            let mut character = original.clone();
+            character.remove_group(&group_id);
+            originals.push(original);
+            saved = self.dao.persist_character(project_id, &character);
+            if saved.is_err() {
+                break;
+            }
+        }
+        let saved = saved.and_then(|_| self.dao.save_metadata(project_id, metadata));
+        if let Err(e) = saved {
+            return Err(self.restore_characters(project_id, originals, e));
+        }
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
+    }
+
+    /// Saves back the characters as they were before a change spanning several
+    /// of them failed. Every character is attempted, and the error of the change
+    /// is returned along with the ones of the restoration.
+    fn restore_characters(
+        &self,
+        project_id: &str,
+        originals: Vec<Character>,
+        error: anyhow::Error,
+    ) -> anyhow::Error {
+        let failures: Vec<String> = originals
+            .iter()
+            .filter_map(|original| {
+                self.dao
+                    .persist_character(project_id, original)
+                    .err()
+                    .map(|e| format!("character {} could not be restored: {e}", original.get_id()))
+            })
+            .collect();
+        if failures.is_empty() {
+            return error;
+        }
+        anyhow!("{error}; {}", failures.join("; "))
+    }
+
+    pub fn add_character_to_group(
+        &self,
+        project_id: &str,
+        character_id: Uuid,
+        group_id: Uuid,
+    ) -> Result<Character> {
+        self.dao
+            .get_meta_file(project_id)?
+            .enforce_group_existence(&group_id)?;
+        let mut character = self.dao.get_character(project_id, &character_id)?;
+        character.add_group(group_id);
+        self.save_membership(project_id, &character_id, &character)?;
+        Ok(character)
+    }
+
+    pub fn remove_character_from_group(
+        &self,
+        project_id: &str,
+        character_id: Uuid,
+        group_id: Uuid,
+    ) -> Result<Character> {
+        let mut character = self.dao.get_character(project_id, &character_id)?;
+        character.remove_group(&group_id);
+        self.save_membership(project_id, &character_id, &character)?;
+        Ok(character)
     }
 
+    /// Saves the groups of the character, and gives it a state in the
+    /// variables targeting its new groups.
+    fn save_membership(&self, project_id: &str, character_id: &Uuid, character: &Character) -> Result<()> {
+        self.update_metadata(project_id, character_id, character)?;
+        self.dao.persist_character(project_id, character)?;
+        let members = self.dao.get_meta_file(project_id)?.get_group_members();
+        let mut vars = self.var_dao.load_variables(project_id)?;
+        vars.sync_group_targets(&members)?;
+        self.var_dao.persist_variables(project_id, &vars)?;
+        self.search_index.mark_variables_stale(project_id)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
+    }
+
+    pub fn create_character_attribute(
+        &self,
+        project_id: &str,
+        name: &str,
+        kind: AttributeKind,
+    ) -> Result<CharacterAttribute> {
+        let mut metadata = self.dao.get_meta_file(project_id)?;
+        let attribute = metadata.create_attribute(name, kind)?;
+        self.dao.save_metadata(project_id, metadata)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(attribute)
+    }
 
-    pub fn get_all_characters(&self, project_id: &str) -> Result<CharacterMetadata> {
-        self.dao.get_meta_file(project_id)
+    pub fn rename_character_attribute(
+        &self,
+        project_id: &str,
+        attribute_id: Uuid,
+        name: &str,
+    ) -> Result<()> {
+        let mut metadata = self.dao.get_meta_file(project_id)?;
+        metadata.rename_attribute(&attribute_id, name)?;
+        self.dao.save_metadata(project_id, metadata)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
+    }
+
+    /// Removes the attribute from the schema and from every character.
+    pub fn delete_character_attribute(&self, project_id: &str, attribute_id: Uuid) -> Result<()> {
+        let mut metadata = self.dao.get_meta_file(project_id)?;
+        metadata.delete_attribute(&attribute_id)?;
+        for character_id in self.dao.get_character_identifiers(project_id)? {
+            let mut character = self.dao.get_character(project_id, &character_id)?;
+            character.remove_attribute(&attribute_id);
+            self.dao.persist_character(project_id, &character)?;
+        }
+        self.dao.save_metadata(project_id, metadata)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
+    }
+
+    pub fn create_character_relationship(
+        &self,
+        project_id: &str,
+        form: RelationshipForm,
+    ) -> Result<CharacterRelationship> {
+        self.enforce_strength_coherence(project_id, &form)?;
+        let mut metadata = self.dao.get_meta_file(project_id)?;
+        let relationship = metadata.create_relationship(form)?;
+        self.dao.save_metadata(project_id, metadata)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(relationship)
+    }
+
+    pub fn change_character_relationship(
+        &self,
+        project_id: &str,
+        relationship_id: Uuid,
+        form: RelationshipForm,
+    ) -> Result<CharacterRelationship> {
+        self.enforce_strength_coherence(project_id, &form)?;
+        let mut metadata = self.dao.get_meta_file(project_id)?;
+        let relationship = metadata.change_relationship(&relationship_id, form)?;
+        self.dao.save_metadata(project_id, metadata)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(relationship)
+    }
+
+    pub fn delete_character_relationship(&self, project_id: &str, relationship_id: Uuid) -> Result<()> {
+        let mut metadata = self.dao.get_meta_file(project_id)?;
+        metadata.delete_relationship(&relationship_id)?;
+        self.dao.save_metadata(project_id, metadata)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
+    }
+
+    /// Relationships of the character, or of the whole cast. Strengths backed by
+    /// a variable holding no state for the character are left out.
+    pub fn get_relationship_graph(
+        &self,
+        project_id: &str,
+        character_id: Option<Uuid>,
+    ) -> Result<RelationshipGraph> {
+        let metadata = self.dao.get_meta_file(project_id)?;
+        let vars = self.var_dao.load_variables(project_id)?;
+        let mut graph = RelationshipGraph {
+            relationships: vec![],
+            variable_strengths: HashMap::new(),
+        };
+        for relationship in metadata.get_relationships(character_id.as_ref()) {
+            if let Some(RelationshipStrength::Variable(var_id)) = relationship.get_strength() {
+                if let Ok(state) = vars.get_character_state(&var_id, relationship.get_from()) {
+                    graph
+                        .variable_strengths
+                        .insert(*relationship.get_id(), String::from(state));
+                }
+            }
+            graph.relationships.push(relationship.clone());
+        }
+        Ok(graph)
+    }
+
+    /// A strength backed by a variable needs a global character
+    /// variable holding a state for the first character.
+    fn enforce_strength_coherence(&self, project_id: &str, form: &RelationshipForm) -> Result<()> {
+        if let Some(RelationshipStrength::Variable(var_id)) = form.strength {
+            self.var_dao
+                .load_variables(project_id)?
+                .get_character_state(&var_id, &form.from)?;
+        }
+        Ok(())
     }
 
     pub fn get_character_by_id(&self, project_id: &str, character_id:Uuid) -> Result<Character> {
@@ -0,0 +1,132 @@
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::{Context, Result};
+use rusqlite::{params, types::Type, OptionalExtension, Row};
+use uuid::Uuid;
+
+use crate::shared::{
+    database::{get_opt_uuid, get_uuid, Database},
+    types::comment::{Comment, CommentTarget},
+};
+
+const COLUMNS: &str = "id, project_id, target, target_id, dialog_id, parent_id, author, email, \
+    content, created_at, updated_at, resolved_at, resolved_by";
+
+pub struct SqliteCommentDao {
+    db: Arc<Database>,
+}
+
+pub trait CommentDao {
+    fn persist_comment(&self, comment: &Comment) -> Result<()>;
+    fn get_comment(&self, comment_id: &Uuid) -> Result<Option<Comment>>;
+    fn get_comments_by_project(&self, project_id: &Uuid) -> Result<Vec<Comment>>;
+    fn get_comments_by_dialog(&self, project_id: &Uuid, dialog_id: &Uuid) -> Result<Vec<Comment>>;
+    fn delete_comment(&self, comment_id: &Uuid) -> Result<()>;
+}
+
+impl SqliteCommentDao {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+}
+
+impl CommentDao for SqliteCommentDao {
+    fn persist_comment(&self, comment: &Comment) -> Result<()> {
+        self.db.with_connection(|conn| {
+            conn.execute(
+                &format!(
+                    "INSERT INTO comments ({COLUMNS}) \
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13) \
+                    ON CONFLICT(id) DO UPDATE SET content = ?9, updated_at = ?11, \
+                    resolved_at = ?12, resolved_by = ?13"
+                ),
+                params![
+                    comment.id.to_string(),
+                    comment.project_id.to_string(),
+                    comment.target.as_str(),
+                    comment.target_id.to_string(),
+                    comment.dialog_id.map(|d| d.to_string()),
+                    comment.parent_id.map(|p| p.to_string()),
+                    comment.author,
+                    comment.email,
+                    comment.content,
+                    comment.created_at,
+                    comment.updated_at,
+                    comment.resolved_at,
+                    comment.resolved_by,
+                ],
+            )
+            .context("could not save comment")?;
+            Ok(())
+        })
+    }
+
+    fn get_comment(&self, comment_id: &Uuid) -> Result<Option<Comment>> {
+        self.db.with_connection(|conn| {
+            conn.query_row(
+                &format!("SELECT {COLUMNS} FROM comments WHERE id = ?1"),
+                params![comment_id.to_string()],
+                comment_from_row,
+            )
+            .optional()
+            .context("could not read comment")
+        })
+    }
+
+    fn get_comments_by_project(&self, project_id: &Uuid) -> Result<Vec<Comment>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {COLUMNS} FROM comments WHERE project_id = ?1 ORDER BY created_at"
+            ))?;
+            let rows = stmt.query_map(params![project_id.to_string()], comment_from_row)?;
+            rows.collect::<rusqlite::Result<Vec<Comment>>>()
+                .context("could not read comments of project")
+        })
+    }
+
+    fn get_comments_by_dialog(&self, project_id: &Uuid, dialog_id: &Uuid) -> Result<Vec<Comment>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {COLUMNS} FROM comments WHERE project_id = ?1 AND dialog_id = ?2 \
+                ORDER BY created_at"
+            ))?;
+            let rows = stmt.query_map(
+                params![project_id.to_string(), dialog_id.to_string()],
+                comment_from_row,
+            )?;
+            rows.collect::<rusqlite::Result<Vec<Comment>>>()
+                .context("could not read comments of dialog")
+        })
+    }
+
+    fn delete_comment(&self, comment_id: &Uuid) -> Result<()> {
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "DELETE FROM comments WHERE id = ?1",
+                params![comment_id.to_string()],
+            )
+            .context("could not delete comment")?;
+            Ok(())
+        })
+    }
+}
+
+fn comment_from_row(row: &Row) -> rusqlite::Result<Comment> {
+    let target: String = row.get(2)?;
+    Ok(Comment {
+        id: get_uuid(row, 0)?,
+        project_id: get_uuid(row, 1)?,
+        target: CommentTarget::from_str(&target)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, Type::Text, e.into()))?,
+        target_id: get_uuid(row, 3)?,
+        dialog_id: get_opt_uuid(row, 4)?,
+        parent_id: get_opt_uuid(row, 5)?,
+        author: row.get(6)?,
+        email: row.get(7)?,
+        content: row.get(8)?,
+        created_at: row.get(9)?,
+        updated_at: row.get(10)?,
+        resolved_at: row.get(11)?,
+        resolved_by: row.get(12)?,
+    })
+}
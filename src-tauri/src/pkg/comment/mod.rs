@@ -0,0 +1,38 @@
+use od_macros::tauri_command;
+use uuid::Uuid;
+
+use crate::shared::types::comment::{Comment, CommentTarget, CommentThread};
+
+pub mod dao;
+pub mod service;
+
+#[tauri_command(comment_service)]
+pub fn add_comment(
+    project_id: &str,
+    target: CommentTarget,
+    target_id: Uuid,
+    dialog_id: Option<Uuid>,
+    content: &str,
+) -> Comment {
+}
+
+#[tauri_command(comment_service)]
+pub fn reply_to_comment(project_id: &str, comment_id: Uuid, content: &str) -> Comment {}
+
+#[tauri_command(comment_service)]
+pub fn edit_comment(project_id: &str, comment_id: Uuid, content: &str) -> Comment {}
+
+#[tauri_command(comment_service)]
+pub fn delete_comment(project_id: &str, comment_id: Uuid) -> () {}
+
+#[tauri_command(comment_service)]
+pub fn resolve_comment(project_id: &str, comment_id: Uuid) -> Comment {}
+
+#[tauri_command(comment_service)]
+pub fn unresolve_comment(project_id: &str, comment_id: Uuid) -> Comment {}
+
+#[tauri_command(comment_service)]
+pub fn get_open_dialog_comments(project_id: &str, dialog_id: Uuid) -> Vec<CommentThread> {}
+
+#[tauri_command(comment_service)]
+pub fn get_open_project_comments(project_id: &str) -> Vec<CommentThread> {}
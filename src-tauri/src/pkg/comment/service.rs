@@ -0,0 +1,204 @@
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::{
+    pkg::comment::dao::CommentDao,
+    shared::{
+        config::ODConfig,
+        types::{
+            comment::{Comment, CommentTarget, CommentThread},
+            interfaces::Shared,
+        },
+    },
+};
+
+pub struct CommentServiceLocalImpl<C: ODConfig, CD: CommentDao> {
+    config: Shared<C>,
+    dao: Arc<CD>,
+}
+
+impl<C: ODConfig, CD: CommentDao> CommentServiceLocalImpl<C, CD> {
+    pub fn new(config: Shared<C>, dao: Arc<CD>) -> Self {
+        Self { config, dao }
+    }
+
+    /// Starts a thread on an entity. Nodes and choices only exist
+    /// inside a dialog, so the dialog must be given for them.
+    pub fn add_comment(
+        &self,
+        project_id: &str,
+        target: CommentTarget,
+        target_id: Uuid,
+        dialog_id: Option<Uuid>,
+        content: &str,
+    ) -> Result<Comment> {
+        let dialog_id = match target {
+            CommentTarget::Dialog => Some(target_id),
+            CommentTarget::Node | CommentTarget::Choice if dialog_id.is_none() => {
+                bail!("the dialog of the commented node must be given")
+            }
+            _ => dialog_id,
+        };
+        let comment = self.new_comment(project_id, target, target_id, dialog_id, None, content)?;
+        self.dao.persist_comment(&comment)?;
+        Ok(comment)
+    }
+
+    pub fn reply_to_comment(
+        &self,
+        project_id: &str,
+        comment_id: Uuid,
+        content: &str,
+    ) -> Result<Comment> {
+        let parent = self.get_comment(project_id, &comment_id)?;
+        let thread_id = parent.parent_id.unwrap_or(parent.id);
+        let reply = self.new_comment(
+            project_id,
+            parent.target,
+            parent.target_id,
+            parent.dialog_id,
+            Some(thread_id),
+            content,
+        )?;
+        self.dao.persist_comment(&reply)?;
+        Ok(reply)
+    }
+
+    pub fn edit_comment(
+        &self,
+        project_id: &str,
+        comment_id: Uuid,
+        content: &str,
+    ) -> Result<Comment> {
+        let mut comment = self.get_comment(project_id, &comment_id)?;
+        if comment.author != *self.config.lock()?.get_user() {
+            bail!("only the author of a comment can edit it")
+        }
+        comment.content = validate_content(content)?;
+        comment.updated_at = Some(Utc::now());
+        self.dao.persist_comment(&comment)?;
+        Ok(comment)
+    }
+
+    /// Deletes a comment, and its answers when it starts a thread.
+    pub fn delete_comment(&self, project_id: &str, comment_id: Uuid) -> Result<()> {
+        let comment = self.get_comment(project_id, &comment_id)?;
+        self.dao.delete_comment(&comment.id)
+    }
+
+    pub fn resolve_comment(&self, project_id: &str, comment_id: Uuid) -> Result<Comment> {
+        let mut comment = self.get_thread_start(project_id, &comment_id)?;
+        if comment.is_resolved() {
+            return Ok(comment);
+        }
+        comment.resolved_at = Some(Utc::now());
+        comment.resolved_by = Some(self.config.lock()?.get_user().clone());
+        self.dao.persist_comment(&comment)?;
+        Ok(comment)
+    }
+
+    pub fn unresolve_comment(&self, project_id: &str, comment_id: Uuid) -> Result<Comment> {
+        let mut comment = self.get_thread_start(project_id, &comment_id)?;
+        comment.resolved_at = None;
+        comment.resolved_by = None;
+        self.dao.persist_comment(&comment)?;
+        Ok(comment)
+    }
+
+    pub fn get_open_dialog_comments(
+        &self,
+        project_id: &str,
+        dialog_id: Uuid,
+    ) -> Result<Vec<CommentThread>> {
+        let comments = self
+            .dao
+            .get_comments_by_dialog(&Uuid::from_str(project_id)?, &dialog_id)?;
+        Ok(into_open_threads(comments))
+    }
+
+    pub fn get_open_project_comments(&self, project_id: &str) -> Result<Vec<CommentThread>> {
+        let comments = self
+            .dao
+            .get_comments_by_project(&Uuid::from_str(project_id)?)?;
+        Ok(into_open_threads(comments))
+    }
+
+    fn new_comment(
+        &self,
+        project_id: &str,
+        target: CommentTarget,
+        target_id: Uuid,
+        dialog_id: Option<Uuid>,
+        parent_id: Option<Uuid>,
+        content: &str,
+    ) -> Result<Comment> {
+        let config = self.config.lock()?;
+        config.get_project(project_id)?;
+        Ok(Comment {
+            id: Uuid::new_v4(),
+            project_id: Uuid::from_str(project_id)?,
+            target,
+            target_id,
+            dialog_id,
+            parent_id,
+            author: config.get_user().clone(),
+            email: config.get_email().cloned(),
+            content: validate_content(content)?,
+            created_at: Utc::now(),
+            updated_at: None,
+            resolved_at: None,
+            resolved_by: None,
+        })
+    }
+
+    fn get_comment(&self, project_id: &str, comment_id: &Uuid) -> Result<Comment> {
+        let comment = self
+            .dao
+            .get_comment(comment_id)?
+            .ok_or(anyhow!("comment {comment_id} does not exist"))?;
+        if comment.project_id != Uuid::from_str(project_id)? {
+            bail!("comment {comment_id} does not belong to project {project_id}")
+        }
+        Ok(comment)
+    }
+
+    /// Resolution applies to whole threads : it is stored on their first comment.
+    fn get_thread_start(&self, project_id: &str, comment_id: &Uuid) -> Result<Comment> {
+        let comment = self.get_comment(project_id, comment_id)?;
+        match comment.parent_id {
+            Some(parent_id) => self.get_comment(project_id, &parent_id),
+            None => Ok(comment),
+        }
+    }
+}
+
+fn validate_content(content: &str) -> Result<String> {
+    let trimmed = content.trim();
+    if trimmed.is_empty() {
+        bail!("a comment cannot be empty")
+    }
+    Ok(String::from(trimmed))
+}
+
+/// Groups comments, sorted by creation date, into the threads that are not resolved.
+fn into_open_threads(comments: Vec<Comment>) -> Vec<CommentThread> {
+    let mut replies: HashMap<Uuid, Vec<Comment>> = HashMap::new();
+    let mut starts = vec![];
+    for comment in comments {
+        match comment.parent_id {
+            Some(parent_id) => replies.entry(parent_id).or_default().push(comment),
+            None => starts.push(comment),
+        }
+    }
+    starts
+        .into_iter()
+        .filter(|c| !c.is_resolved())
+        .map(|comment| CommentThread {
+            replies: replies.remove(&comment.id).unwrap_or_default(),
+            comment,
+        })
+        .collect()
+}
@@ -1,19 +1,20 @@
-use std::{
-    collections::HashSet,
-    fs::{self, File},
-    io::BufWriter,
-    path::PathBuf,
-    str::FromStr,
-};
+use std::{collections::HashSet, fs, path::PathBuf, str::FromStr};
 
 use anyhow::{Context, Result};
 use uuid::Uuid;
 
+use crate::pkg::serializer::{
+    ndjson::{read_ndjson, write_ndjson},
+    protobuf::{read_protobuf, write_protobuf},
+};
 use crate::shared::{
     config::{ODConfig, DIALOG_DIRNAME},
+    own_writes,
     types::{
         dialog::{Dialog, DialogMetadata},
         interfaces::Shared,
+        proto,
+        voice::VoiceOverTable,
     },
 };
 
@@ -21,6 +22,12 @@ pub struct FileDialogDao<C: ODConfig> {
     config: Shared<C>,
 }
 
+/// Stores the structure of dialogs as protobuf, everything
+/// else is kept in the same files as the json storage.
+pub struct ProtoDialogDao<C: ODConfig> {
+    json: FileDialogDao<C>,
+}
+
 pub trait DialogDao<C: ODConfig> {
     fn persist_dialog(&self, project_id: &str, dialog: Dialog) -> Result<()>;
     fn persist_metadata(&self, project_id: &str, metadata: &DialogMetadata) -> Result<()>;
@@ -38,6 +45,13 @@ pub trait DialogDao<C: ODConfig> {
     ) -> Result<()>;
     fn get_dialog_identifiers(&self, project_id: &str) -> Result<HashSet<Uuid>>;
     fn delete_dialog_by_id(&self, project_id: &str, dialog_id: &Uuid) -> Result<()>;
+    fn get_voice_over(&self, project_id: &str, dialog_id: &Uuid) -> Result<VoiceOverTable>;
+    fn persist_voice_over(
+        &self,
+        project_id: &str,
+        dialog_id: &Uuid,
+        table: &VoiceOverTable,
+    ) -> Result<()>;
 }
 
 impl<C: ODConfig> FileDialogDao<C> {
@@ -48,24 +62,20 @@ impl<C: ODConfig> FileDialogDao<C> {
 
 impl<C: ODConfig> DialogDao<C> for FileDialogDao<C> {
     fn delete_dialog_by_id(&self, project_id: &str, dialog_id: &Uuid) -> Result<()> {
-        fs::remove_dir_all(self.get_dialog_dir_id(project_id, dialog_id)?)
+        own_writes::remove_dir_all(&self.get_dialog_dir_id(project_id, dialog_id)?)
             .context("deleting the directory of the dialog did not work")
     }
 
     fn persist_dialog(&self, project_id: &str, dialog: Dialog) -> Result<()> {
         let dir_path = self.get_dialog_dir_id(project_id, &dialog.get_id())?;
         if !dir_path.is_dir() {
-            fs::create_dir(&dir_path).context("could not create character directory")?;
+            own_writes::create_dir(&dir_path).context("could not create character directory")?;
         }
-        let file_path = dir_path.join("meta.json");
-        let file = File::create(file_path).context("could not create or open dialog file")?;
-        let writer = BufWriter::new(file);
-        serde_json::to_writer(writer, &dialog).context("failed to deserialize dialog")
+        write_ndjson(&dir_path.join("meta.json"), &dialog).context("failed to serialize dialog")
     }
 
     fn get_metadata(&self, project_id: &str) -> Result<DialogMetadata> {
-        let file = fs::read(self.get_metadata_file(project_id)?)?;
-        serde_json::from_slice(&file).context("could not deserialize metadata")
+        read_ndjson(&self.get_metadata_file(project_id)?).context("could not deserialize metadata")
     }
 
     fn create_metadata(&self, project_id: &str) -> Result<DialogMetadata> {
@@ -75,23 +85,18 @@ impl<C: ODConfig> DialogDao<C> for FileDialogDao<C> {
     }
 
     fn persist_metadata(&self, project_id: &str, metadata: &DialogMetadata) -> Result<()> {
-        let file = File::create(self.get_metadata_file(project_id)?)
-            .context("could not create or open metadata file")?;
-        let writer = BufWriter::new(file);
-        serde_json::to_writer(writer, &metadata).context("failed to deserialize dialog")?;
-        Ok(())
+        write_ndjson(&self.get_metadata_file(project_id)?, metadata)
+            .context("failed to serialize dialog metadata")
     }
 
     fn get_dialog_by_id(&self, project_id: &str, dialog_id: &Uuid) -> Result<Dialog> {
         let path = self.get_dialog_meta_file(project_id, dialog_id)?;
-        let file = fs::read(path).context("could not read dialog file")?;
-        Ok(serde_json::from_slice(&file).context("could not deserialize dialog")?)
+        read_ndjson(&path).context("could not deserialize dialog")
     }
 
     fn get_dialog_metadata(&self, project_id: &str) -> Result<DialogMetadata> {
         let path = self.get_metadata_file(project_id)?;
-        let file = fs::read(path).context("could not read metadata file")?;
-        Ok(serde_json::from_slice(&file).context("could not deserialize metadata")?)
+        read_ndjson(&path).context("could not deserialize metadata")
     }
 
     fn persist_dialog_content(
@@ -102,7 +107,7 @@ impl<C: ODConfig> DialogDao<C> for FileDialogDao<C> {
         content: &str,
     ) -> Result<()> {
         let path = self.get_dialog_content_node_file(project_id, dialog_id, node_id)?;
-        fs::write(path, content).context("could not write dialog content to file")?;
+        own_writes::write(&path, content).context("could not write dialog content to file")?;
         Ok(())
     }
 
@@ -122,6 +127,24 @@ impl<C: ODConfig> DialogDao<C> for FileDialogDao<C> {
             })
             .collect::<Result<HashSet<Uuid>>>()
     }
+
+    fn get_voice_over(&self, project_id: &str, dialog_id: &Uuid) -> Result<VoiceOverTable> {
+        let path = self.get_dialog_audio_file(project_id, dialog_id)?;
+        if !path.is_file() {
+            return Ok(VoiceOverTable::new());
+        }
+        read_ndjson(&path).context("could not deserialize audio file of dialog")
+    }
+
+    fn persist_voice_over(
+        &self,
+        project_id: &str,
+        dialog_id: &Uuid,
+        table: &VoiceOverTable,
+    ) -> Result<()> {
+        write_ndjson(&self.get_dialog_audio_file(project_id, dialog_id)?, table)
+            .context("failed to serialize audio of dialog")
+    }
 }
 
 impl<C: ODConfig> FileDialogDao<C> {
@@ -149,6 +172,12 @@ impl<C: ODConfig> FileDialogDao<C> {
             .join("meta.json"))
     }
 
+    pub fn get_dialog_audio_file(&self, project_id: &str, dialog_id: &Uuid) -> Result<PathBuf> {
+        Ok(self
+            .get_dialog_dir_id(project_id, dialog_id)?
+            .join("audio.json"))
+    }
+
     pub fn get_dialog_content_node_file(
         &self,
         project_id: &str,
@@ -160,3 +189,89 @@ impl<C: ODConfig> FileDialogDao<C> {
             .join(format!("{node_id}.txt")))
     }
 }
+
+impl<C: ODConfig> ProtoDialogDao<C> {
+    pub fn new(config: Shared<C>) -> Self {
+        ProtoDialogDao {
+            json: FileDialogDao::new(config),
+        }
+    }
+
+    pub fn get_dialog_proto_file(&self, project_id: &str, dialog_id: &Uuid) -> Result<PathBuf> {
+        Ok(self
+            .json
+            .get_dialog_dir_id(project_id, dialog_id)?
+            .join("dialog.pb"))
+    }
+}
+
+impl<C: ODConfig> DialogDao<C> for ProtoDialogDao<C> {
+    fn persist_dialog(&self, project_id: &str, dialog: Dialog) -> Result<()> {
+        let dir_path = self.json.get_dialog_dir_id(project_id, &dialog.get_id())?;
+        if !dir_path.is_dir() {
+            own_writes::create_dir(&dir_path).context("could not create dialog directory")?;
+        }
+        write_protobuf::<proto::Dialog, Dialog>(
+            &self.get_dialog_proto_file(project_id, &dialog.get_id())?,
+            &dialog,
+        )
+        .context("failed to encode dialog")
+    }
+
+    fn persist_metadata(&self, project_id: &str, metadata: &DialogMetadata) -> Result<()> {
+        self.json.persist_metadata(project_id, metadata)
+    }
+
+    fn get_metadata(&self, project_id: &str) -> Result<DialogMetadata> {
+        self.json.get_metadata(project_id)
+    }
+
+    fn create_metadata(&self, project_id: &str) -> Result<DialogMetadata> {
+        self.json.create_metadata(project_id)
+    }
+
+    fn get_dialog_by_id(&self, project_id: &str, dialog_id: &Uuid) -> Result<Dialog> {
+        read_protobuf::<proto::Dialog, Dialog>(&self.get_dialog_proto_file(project_id, dialog_id)?)
+            .context("could not decode dialog")
+    }
+
+    fn get_dialog_metadata(&self, project_id: &str) -> Result<DialogMetadata> {
+        self.json.get_dialog_metadata(project_id)
+    }
+
+    fn get_content(&self, project_id: &str, dialog_id: &Uuid, node_id: &Uuid) -> Result<String> {
+        self.json.get_content(project_id, dialog_id, node_id)
+    }
+
+    fn persist_dialog_content(
+        &self,
+        project_id: &str,
+        dialog_id: &Uuid,
+        node_id: &Uuid,
+        content: &str,
+    ) -> Result<()> {
+        self.json
+            .persist_dialog_content(project_id, dialog_id, node_id, content)
+    }
+
+    fn get_dialog_identifiers(&self, project_id: &str) -> Result<HashSet<Uuid>> {
+        self.json.get_dialog_identifiers(project_id)
+    }
+
+    fn delete_dialog_by_id(&self, project_id: &str, dialog_id: &Uuid) -> Result<()> {
+        self.json.delete_dialog_by_id(project_id, dialog_id)
+    }
+
+    fn get_voice_over(&self, project_id: &str, dialog_id: &Uuid) -> Result<VoiceOverTable> {
+        self.json.get_voice_over(project_id, dialog_id)
+    }
+
+    fn persist_voice_over(
+        &self,
+        project_id: &str,
+        dialog_id: &Uuid,
+        table: &VoiceOverTable,
+    ) -> Result<()> {
+        self.json.persist_voice_over(project_id, dialog_id, table)
+    }
+}
@@ -0,0 +1,257 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension, Transaction};
+use uuid::Uuid;
+
+use crate::shared::{database::Database, types::dialog::DialogHistoryState};
+
+/// Saved versions of dialogs, with the position of the current one.
+/// Versions after the current one can be redone until a new version is saved.
+pub trait DialogHistoryDao: Send + Sync {
+    /// Saves a version after the current one, dropping the versions that could
+    /// be redone and the oldest ones beyond the limit. Does nothing if the
+    /// content is the one of the current version.
+    fn push_version(
+        &self,
+        project_id: &str,
+        dialog_id: &Uuid,
+        content: &str,
+        limit: u32,
+    ) -> Result<()>;
+    /// Moves the current version by the offset, and returns its content.
+    /// Returns None, without moving, if there is no version there.
+    fn move_cursor(
+        &self,
+        project_id: &str,
+        dialog_id: &Uuid,
+        offset: i64,
+    ) -> Result<Option<String>>;
+    fn get_history_state(&self, project_id: &str, dialog_id: &Uuid) -> Result<DialogHistoryState>;
+    fn clear_history(&self, project_id: &str, dialog_id: &Uuid) -> Result<()>;
+}
+
+pub struct SqliteDialogHistoryDao {
+    db: Arc<Database>,
+}
+
+impl SqliteDialogHistoryDao {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+}
+
+impl DialogHistoryDao for SqliteDialogHistoryDao {
+    fn push_version(
+        &self,
+        project_id: &str,
+        dialog_id: &Uuid,
+        content: &str,
+        limit: u32,
+    ) -> Result<()> {
+        let dialog_id = dialog_id.to_string();
+        self.db.with_connection(|conn| {
+            let tx = conn.transaction()?;
+            let position = match get_cursor(&tx, project_id, &dialog_id)? {
+                Some(cursor) => {
+                    if get_version(&tx, project_id, &dialog_id, cursor)?.as_deref() == Some(content)
+                    {
+                        return Ok(());
+                    }
+                    tx.execute(
+                        "DELETE FROM dialog_versions \
+                        WHERE project_id = ?1 AND dialog_id = ?2 AND position > ?3",
+                        params![project_id, dialog_id, cursor],
+                    )?;
+                    cursor + 1
+                }
+                None => 0,
+            };
+            tx.execute(
+                "INSERT INTO dialog_versions (project_id, dialog_id, position, content, saved_at) \
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![project_id, dialog_id, position, content, Utc::now()],
+            )?;
+            set_cursor(&tx, project_id, &dialog_id, position)?;
+            tx.execute(
+                "DELETE FROM dialog_versions \
+                WHERE project_id = ?1 AND dialog_id = ?2 AND position <= ?3",
+                params![project_id, dialog_id, position - i64::from(limit)],
+            )?;
+            tx.commit().context("could not save dialog version")
+        })
+    }
+
+    fn move_cursor(
+        &self,
+        project_id: &str,
+        dialog_id: &Uuid,
+        offset: i64,
+    ) -> Result<Option<String>> {
+        let dialog_id = dialog_id.to_string();
+        self.db.with_connection(|conn| {
+            let tx = conn.transaction()?;
+            let cursor = match get_cursor(&tx, project_id, &dialog_id)? {
+                Some(c) => c,
+                None => return Ok(None),
+            };
+            let content = match get_version(&tx, project_id, &dialog_id, cursor + offset)? {
+                Some(c) => c,
+                None => return Ok(None),
+            };
+            set_cursor(&tx, project_id, &dialog_id, cursor + offset)?;
+            tx.commit().context("could not move in dialog history")?;
+            Ok(Some(content))
+        })
+    }
+
+    fn get_history_state(&self, project_id: &str, dialog_id: &Uuid) -> Result<DialogHistoryState> {
+        let dialog_id = dialog_id.to_string();
+        self.db.with_connection(|conn| {
+            let tx = conn.transaction()?;
+            let cursor = match get_cursor(&tx, project_id, &dialog_id)? {
+                Some(c) => c,
+                None => {
+                    return Ok(DialogHistoryState {
+                        can_undo: false,
+                        can_redo: false,
+                    })
+                }
+            };
+            let (min, max): (i64, i64) = tx.query_row(
+                "SELECT MIN(position), MAX(position) FROM dialog_versions \
+                WHERE project_id = ?1 AND dialog_id = ?2",
+                params![project_id, dialog_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            Ok(DialogHistoryState {
+                can_undo: cursor > min,
+                can_redo: cursor < max,
+            })
+        })
+    }
+
+    fn clear_history(&self, project_id: &str, dialog_id: &Uuid) -> Result<()> {
+        let dialog_id = dialog_id.to_string();
+        self.db.with_connection(|conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "DELETE FROM dialog_versions WHERE project_id = ?1 AND dialog_id = ?2",
+                params![project_id, dialog_id],
+            )?;
+            tx.execute(
+                "DELETE FROM dialog_history WHERE project_id = ?1 AND dialog_id = ?2",
+                params![project_id, dialog_id],
+            )?;
+            tx.commit().context("could not clear dialog history")
+        })
+    }
+}
+
+fn get_cursor(tx: &Transaction, project_id: &str, dialog_id: &str) -> Result<Option<i64>> {
+    tx.query_row(
+        "SELECT cursor FROM dialog_history WHERE project_id = ?1 AND dialog_id = ?2",
+        params![project_id, dialog_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .context("could not read dialog history")
+}
+
+fn set_cursor(tx: &Transaction, project_id: &str, dialog_id: &str, cursor: i64) -> Result<()> {
+    tx.execute(
+        "INSERT INTO dialog_history (project_id, dialog_id, cursor) VALUES (?1, ?2, ?3) \
+        ON CONFLICT(project_id, dialog_id) DO UPDATE SET cursor = ?3",
+        params![project_id, dialog_id, cursor],
+    )?;
+    Ok(())
+}
+
+fn get_version(
+    tx: &Transaction,
+    project_id: &str,
+    dialog_id: &str,
+    position: i64,
+) -> Result<Option<String>> {
+    tx.query_row(
+        "SELECT content FROM dialog_versions \
+        WHERE project_id = ?1 AND dialog_id = ?2 AND position = ?3",
+        params![project_id, dialog_id, position],
+        |row| row.get(0),
+    )
+    .optional()
+    .context("could not read dialog version")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn dao() -> SqliteDialogHistoryDao {
+        SqliteDialogHistoryDao::new(Arc::new(Database::new(PathBuf::from(":memory:"))))
+    }
+
+    #[test]
+    fn cursor_moves_through_saved_versions() {
+        let dao = dao();
+        let dialog_id = Uuid::new_v4();
+        for content in ["a", "b", "c"] {
+            dao.push_version("p", &dialog_id, content, 10).unwrap();
+        }
+        assert_eq!(dao.move_cursor("p", &dialog_id, -1).unwrap().as_deref(), Some("b"));
+        assert_eq!(dao.move_cursor("p", &dialog_id, -1).unwrap().as_deref(), Some("a"));
+        assert_eq!(dao.move_cursor("p", &dialog_id, -1).unwrap(), None);
+        let state = dao.get_history_state("p", &dialog_id).unwrap();
+        assert!(!state.can_undo && state.can_redo);
+        assert_eq!(dao.move_cursor("p", &dialog_id, 1).unwrap().as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn saving_after_undo_drops_the_versions_to_redo() {
+        let dao = dao();
+        let dialog_id = Uuid::new_v4();
+        for content in ["a", "b", "c"] {
+            dao.push_version("p", &dialog_id, content, 10).unwrap();
+        }
+        dao.move_cursor("p", &dialog_id, -2).unwrap();
+        dao.push_version("p", &dialog_id, "d", 10).unwrap();
+        assert!(!dao.get_history_state("p", &dialog_id).unwrap().can_redo);
+        assert_eq!(dao.move_cursor("p", &dialog_id, -1).unwrap().as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn same_content_as_the_current_version_is_not_recorded() {
+        let dao = dao();
+        let dialog_id = Uuid::new_v4();
+        dao.push_version("p", &dialog_id, "a", 10).unwrap();
+        dao.push_version("p", &dialog_id, "a", 10).unwrap();
+        assert!(!dao.get_history_state("p", &dialog_id).unwrap().can_undo);
+    }
+
+    #[test]
+    fn oldest_versions_beyond_the_limit_are_dropped() {
+        let dao = dao();
+        let dialog_id = Uuid::new_v4();
+        for content in ["a", "b", "c", "d"] {
+            dao.push_version("p", &dialog_id, content, 2).unwrap();
+        }
+        assert_eq!(dao.move_cursor("p", &dialog_id, -1).unwrap().as_deref(), Some("c"));
+        assert_eq!(dao.move_cursor("p", &dialog_id, -1).unwrap(), None);
+    }
+
+    #[test]
+    fn histories_are_kept_per_dialog_and_cleared() {
+        let dao = dao();
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        dao.push_version("p", &first, "a", 10).unwrap();
+        dao.push_version("p", &first, "b", 10).unwrap();
+        dao.push_version("p", &second, "x", 10).unwrap();
+        assert!(!dao.get_history_state("p", &second).unwrap().can_undo);
+        dao.clear_history("p", &first).unwrap();
+        assert!(!dao.get_history_state("p", &first).unwrap().can_undo);
+        assert_eq!(dao.move_cursor("p", &first, -1).unwrap(), None);
+    }
+}
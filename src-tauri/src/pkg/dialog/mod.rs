@@ -1,8 +1,12 @@
-use crate::shared::types::dialog::{Dialog, DialogCreationForm, DialogMetadata};
+use crate::shared::types::dialog::{
+    Dialog, DialogCreationForm, DialogGroup, DialogHistoryState, DialogMetadata, SimpleDialog,
+    StoryGraph, StoryLink,
+};
 use od_macros::tauri_command;
 use uuid::Uuid;
 
 pub mod dao;
+pub mod history;
 pub mod service;
 
 #[tauri_command(dialog_service)]
@@ -25,3 +29,42 @@ pub fn save_dialog_metadata(project_id: &str, dialog_metadata: DialogMetadata) {
 
 #[tauri_command(dialog_service)]
 pub fn delete_dialog(project_id: &str, dialog_id: Uuid) {}
+
+#[tauri_command(dialog_service)]
+pub fn get_dialog_references(project_id: &str, dialog_id: Uuid) -> Vec<StoryLink> {}
+
+#[tauri_command(dialog_service)]
+pub fn get_dialogs_featuring_character(project_id: &str, character_id: Uuid) -> Vec<SimpleDialog> {}
+
+#[tauri_command(dialog_service)]
+pub fn get_story_graph(project_id: &str) -> StoryGraph {}
+
+#[tauri_command(dialog_service)]
+pub fn undo_dialog(project_id: &str, dialog_id: Uuid) -> Dialog {}
+
+#[tauri_command(dialog_service)]
+pub fn redo_dialog(project_id: &str, dialog_id: Uuid) -> Dialog {}
+
+#[tauri_command(dialog_service)]
+pub fn get_dialog_history(project_id: &str, dialog_id: Uuid) -> DialogHistoryState {}
+
+#[tauri_command(dialog_service)]
+pub fn create_dialog_group(project_id: &str, name: &str, parent_id: Option<Uuid>) -> DialogGroup {}
+
+#[tauri_command(dialog_service)]
+pub fn rename_dialog_group(project_id: &str, group_id: Uuid, name: &str) {}
+
+#[tauri_command(dialog_service)]
+pub fn move_dialog_group(project_id: &str, group_id: Uuid, parent_id: Option<Uuid>, order: usize) {}
+
+#[tauri_command(dialog_service)]
+pub fn delete_dialog_group(project_id: &str, group_id: Uuid) {}
+
+#[tauri_command(dialog_service)]
+pub fn assign_dialog_to_group(
+    project_id: &str,
+    dialog_id: Uuid,
+    group_id: Option<Uuid>,
+    order: usize,
+) {
+}
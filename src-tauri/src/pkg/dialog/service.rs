@@ -1,21 +1,27 @@
-use std::{str::FromStr, sync::Arc};
+use std::{collections::HashSet, str::FromStr, sync::Arc};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use uuid::Uuid;
 
 use crate::{
     pkg::{
         character::dao::CharacterDao,
-        dialog::dao::DialogDao,
+        dialog::{dao::DialogDao, history::DialogHistoryDao},
+        gitter::service::GitService,
         meta::{dao::MetaDao, service::MetaServiceLocalImpl},
+        search::index::SearchIndex,
+        serializer::ndjson::{from_ndjson, to_ndjson},
+        variables::dao::VariableDao,
     },
     shared::{
         config::ODConfig,
         types::{
             dialog::{
-                Dialog, DialogContent, DialogCreationForm, DialogMetadata, NodeData, SimpleDialog,
+                Dialog, DialogContent, DialogCreationForm, DialogGroup, DialogHistoryState,
+                DialogMetadata,
+                NodeData, PhylumDiff, SimpleDialog, StoryGraph, StoryLink,
             },
-            interfaces::Shared,
+            interfaces::{LineDelimited, Shared},
         },
     },
 };
@@ -30,8 +36,15 @@ pub struct DialogServiceLocalImpl<
     dialog_dao: Arc<DD>,
     char_dao: Arc<CD>,
     meta_srv: MetaServiceLocalImpl<C, MD>,
+    search_index: Arc<SearchIndex>,
+    history_dao: Arc<dyn DialogHistoryDao>,
+    var_dao: Arc<dyn VariableDao<C> + Send + Sync>,
+    git_srv: GitService<C>,
 }
 
+/// Number of saved versions kept for each dialog.
+const HISTORY_LIMIT: u32 = 50;
+
 impl<C: ODConfig, DD: DialogDao<C>, CD: CharacterDao<C>, MD: MetaDao<C>>
     DialogServiceLocalImpl<C, DD, CD, MD>
 {
@@ -40,12 +53,20 @@ impl<C: ODConfig, DD: DialogDao<C>, CD: CharacterDao<C>, MD: MetaDao<C>>
         dialog_dao: Arc<DD>,
         char_dao: Arc<CD>,
         meta_srv: MetaServiceLocalImpl<C, MD>,
+        search_index: Arc<SearchIndex>,
+        history_dao: Arc<dyn DialogHistoryDao>,
+        var_dao: Arc<dyn VariableDao<C> + Send + Sync>,
+        git_srv: GitService<C>,
     ) -> Self {
         DialogServiceLocalImpl {
             config,
             dialog_dao,
             char_dao,
             meta_srv,
+            search_index,
+            history_dao,
+            var_dao,
+            git_srv,
         }
     }
 
@@ -59,21 +80,158 @@ impl<C: ODConfig, DD: DialogDao<C>, CD: CharacterDao<C>, MD: MetaDao<C>>
             .or_else(|_| self.dialog_dao.create_metadata(project_id))?;
         let order = form.order;
         let new_dialog = Dialog::from_dialog_creation_form(form)?;
+        let dialog_id = new_dialog.get_id();
         metadata.data.insert(
             new_dialog.get_id(),
             SimpleDialog::from_dialog(&new_dialog, order),
         );
         self.dialog_dao.persist_dialog(project_id, new_dialog)?;
+        self.search_index.mark_dialog_stale(project_id, &dialog_id)?;
 
         //TODO: should retry and delete the dialog if it fails.
-        self.dialog_dao.persist_metadata(project_id, &metadata)
+        self.dialog_dao.persist_metadata(project_id, &metadata)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
     }
 
     pub fn get_dialog_by_id(&self, project_id: &str, dialog_id: Uuid) -> Result<Dialog> {
         let mut dialog = self.dialog_dao.get_dialog_by_id(project_id, &dialog_id)?;
+        self.load_content(project_id, &mut dialog)?;
+        Ok(dialog)
+    }
+
+    /// Loads a dialog and checks that what it references exists in the project.
+    pub fn validate_dialog(&self, project_id: &str, dialog_id: Uuid) -> Result<Dialog> {
+        let dialog = self.get_dialog_by_id(project_id, dialog_id)?;
+        self.enforce_dialog_coherence(project_id, &dialog)?;
+        self.char_dao
+            .enforce_character_existence(project_id, dialog.get_main_character())?;
+        Ok(dialog)
+    }
+
+    /// Checks what saving the dialog requires : the variables, dialogs
+    /// and speakers it references must exist.
+    pub fn enforce_dialog_coherence(&self, project_id: &str, dialog: &Dialog) -> Result<()> {
+        let fks = self.meta_srv.get_var_to_phylum(project_id)?;
+        let vars = self.meta_srv.get_all_variable_hashet(&fks)?;
+        dialog.enforce_links_coherence(vars)?;
+        self.enforce_story_links(project_id, dialog)?;
+        self.enforce_speakers_existence(project_id, dialog)
+    }
+
+    /// Dialogs whose main character is the character, or in which it speaks.
+    pub fn get_dialogs_featuring_character(
+        &self,
+        project_id: &str,
+        character_id: Uuid,
+    ) -> Result<Vec<SimpleDialog>> {
+        let metadata = self.dialog_dao.get_metadata(project_id)?;
+        let mut res = vec![];
+        for (dialog_id, simple_dialog) in metadata.data {
+            let dialog = self.dialog_dao.get_dialog_by_id(project_id, &dialog_id)?;
+            if dialog.features_character(&character_id) {
+                res.push(simple_dialog);
+            }
+        }
+        res.sort_by_key(|d| d.get_order());
+        Ok(res)
+    }
+
+    fn enforce_speakers_existence(&self, project_id: &str, dialog: &Dialog) -> Result<()> {
+        for character_id in dialog.get_speakers() {
+            self.char_dao
+                .enforce_character_existence(project_id, &character_id)
+                .map_err(|e| anyhow!("speaker {character_id} of the dialog is invalid : {e}"))?;
+        }
+        Ok(())
+    }
+
+    /// Links between the dialogs of the project, with the links whose
+    /// target dialog or node does not exist anymore.
+    pub fn get_story_graph(&self, project_id: &str) -> Result<StoryGraph> {
+        let metadata = self.dialog_dao.get_metadata(project_id)?;
+        let mut dialogs: Vec<Uuid> = metadata.data.keys().copied().collect();
+        dialogs.sort();
+        let mut loaded = Vec::with_capacity(dialogs.len());
+        for dialog_id in &dialogs {
+            loaded.push(self.dialog_dao.get_dialog_by_id(project_id, dialog_id)?);
+        }
+        let mut links = vec![];
+        let mut broken_links = vec![];
+        for link in loaded.iter().flat_map(|d| d.get_story_links()) {
+            let target = loaded.iter().find(|d| d.get_id() == link.target_dialog_id);
+            let exists = match (target, link.target_node_id) {
+                (Some(d), Some(node_id)) => d.has_node(&node_id),
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+            if exists {
+                links.push(link);
+            } else {
+                broken_links.push(link);
+            }
+        }
+        Ok(StoryGraph {
+            dialogs,
+            links,
+            broken_links,
+        })
+    }
+
+    /// Links of other dialogs which would be broken by the deletion of the dialog.
+    pub fn get_dialog_references(&self, project_id: &str, dialog_id: Uuid) -> Result<Vec<StoryLink>> {
+        let graph = self.get_story_graph(project_id)?;
+        Ok(graph
+            .links
+            .into_iter()
+            .filter(|l| l.target_dialog_id == dialog_id && l.dialog_id != dialog_id)
+            .collect())
+    }
+
+    /// Checks that the dialogs, and nodes, the dialog links to exist.
+    fn enforce_story_links(&self, project_id: &str, dialog: &Dialog) -> Result<()> {
+        let links = dialog.get_story_links();
+        if links.is_empty() {
+            return Ok(());
+        }
+        let metadata = self.dialog_dao.get_metadata(project_id)?;
+        for link in links {
+            if link.target_dialog_id != dialog.get_id()
+                && !metadata.data.contains_key(&link.target_dialog_id)
+            {
+                bail!(
+                    "node {} links to dialog {} which does not exist",
+                    link.node_id,
+                    link.target_dialog_id
+                )
+            }
+            let Some(target_node_id) = link.target_node_id else {
+                continue;
+            };
+            let exists = if link.target_dialog_id == dialog.get_id() {
+                dialog.has_node(&target_node_id)
+            } else {
+                self.dialog_dao
+                    .get_dialog_by_id(project_id, &link.target_dialog_id)?
+                    .has_node(&target_node_id)
+            };
+            if !exists {
+                bail!(
+                    "node {} links to node {target_node_id} which does not exist in dialog {}",
+                    link.node_id,
+                    link.target_dialog_id
+                )
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads the content of the dialog nodes which only link to it.
+    fn load_content(&self, project_id: &str, dialog: &mut Dialog) -> Result<()> {
+        let dialog_id = dialog.get_id();
         for (uuid, node) in dialog.get_nodes() {
             if let NodeData::Dialog(dialog) = node.get_data() {
-                if dialog.content_link.is_some() {
+                if dialog.content.is_none() && dialog.content_link.is_some() {
                     let content = self
                         .dialog_dao
                         .get_content(project_id, &dialog_id, uuid)?;
@@ -81,7 +239,7 @@ impl<C: ODConfig, DD: DialogDao<C>, CD: CharacterDao<C>, MD: MetaDao<C>>
                 }
             };
         }
-        Ok(dialog)
+        Ok(())
     }
 
     pub fn get_dialog_metadata(&self, project_id: &str) -> Result<DialogMetadata> {
@@ -91,13 +249,97 @@ impl<C: ODConfig, DD: DialogDao<C>, CD: CharacterDao<C>, MD: MetaDao<C>>
         }
     }
 
-    pub fn save_dialog(&self, project_id: &str, mut dialog: Dialog) -> Result<()> {
+    pub fn save_dialog(&self, project_id: &str, dialog: Dialog) -> Result<()> {
+        self.persist_dialog(project_id, dialog, true)
+    }
+
+    /// Saves back the dialogs as they were before a change spanning several of
+    /// them failed. Every dialog is attempted, and the error of the change is
+    /// returned along with the ones of the restoration.
+    pub fn restore_dialogs(
+        &self,
+        project_id: &str,
+        originals: Vec<Dialog>,
+        error: anyhow::Error,
+    ) -> anyhow::Error {
+        let failures: Vec<String> = originals
+            .into_iter()
+            .filter_map(|original| {
+                let dialog_id = original.get_id();
+                self.save_dialog(project_id, original)
+                    .err()
+                    .map(|e| format!("dialog {dialog_id} could not be restored: {e}"))
+            })
+            .collect();
+        if failures.is_empty() {
+            return error;
+        }
+        anyhow!("{error}; {}", failures.join("; "))
+    }
+
+    /// Restores the version saved before the current one.
+    pub fn undo_dialog(&self, project_id: &str, dialog_id: Uuid) -> Result<Dialog> {
+        self.restore_version(project_id, dialog_id, -1)
+            .and_then(|d| d.ok_or(anyhow!("nothing to undo")))
+    }
+
+    /// Restores the version saved after the current one, if it was undone.
+    pub fn redo_dialog(&self, project_id: &str, dialog_id: Uuid) -> Result<Dialog> {
+        self.restore_version(project_id, dialog_id, 1)
+            .and_then(|d| d.ok_or(anyhow!("nothing to redo")))
+    }
+
+    pub fn get_dialog_history(
+        &self,
+        project_id: &str,
+        dialog_id: Uuid,
+    ) -> Result<DialogHistoryState> {
+        self.history_dao.get_history_state(project_id, &dialog_id)
+    }
+
+    fn restore_version(
+        &self,
+        project_id: &str,
+        dialog_id: Uuid,
+        offset: i64,
+    ) -> Result<Option<Dialog>> {
+        let content = match self.history_dao.move_cursor(project_id, &dialog_id, offset)? {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+        let restored = from_ndjson::<Dialog>(content.as_bytes()).and_then(|dialog| {
+            // The states a version needs may have been removed or renamed since.
+            self.var_dao
+                .load_variables(project_id)?
+                .enforce_necessary_states(&dialog.get_variable_usages())?;
+            self.persist_dialog(project_id, dialog, false)?;
+            self.get_dialog_by_id(project_id, dialog_id)
+        });
+        match restored {
+            Ok(dialog) => Ok(Some(dialog)),
+            Err(e) => {
+                self.history_dao.move_cursor(project_id, &dialog_id, -offset)?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Saves the dialog, recording it in its history unless it is restored from it.
+    /// Versions are only recorded once the dialog is saved.
+    fn persist_dialog(&self, project_id: &str, mut dialog: Dialog, record: bool) -> Result<()> {
+        self.enforce_dialog_coherence(project_id, &dialog)?;
         let mut fks = self.meta_srv.get_var_to_phylum(project_id)?;
-        let vars = self.meta_srv.get_all_variable_hashet(&fks)?;
-        dialog.enforce_links_coherence(vars)?;
+        dialog.sync_characters();
         let prev_dialog = self
             .dialog_dao
             .get_dialog_by_id(project_id, &dialog.get_id())?;
+        let mut line_ids = self.meta_srv.get_line_id_registry(project_id)?;
+        dialog.assign_line_ids(&prev_dialog, &mut line_ids);
+        let versions = if record {
+            self.get_versions_to_record(project_id, &mut dialog)?
+        } else {
+            vec![]
+        };
         let diffs = dialog.get_diffs(&prev_dialog);
         fks.mutate_to_match_diffs(diffs)?;
 
@@ -106,7 +348,7 @@ impl<C: ODConfig, DD: DialogDao<C>, CD: CharacterDao<C>, MD: MetaDao<C>>
             .data
             .get(&dialog.get_id())
             .ok_or(anyhow!("dialog did not exist"))?;
-        let simple_dialog = SimpleDialog::from_dialog(&dialog, prev.get_order());
+        let simple_dialog = SimpleDialog::from_saved_dialog(&dialog, prev);
         metadata.data.insert(dialog.get_id(), simple_dialog);
         let mut collector: Vec<DialogContent> = vec![];
         let dialog_id = &dialog.get_id();
@@ -122,12 +364,21 @@ impl<C: ODConfig, DD: DialogDao<C>, CD: CharacterDao<C>, MD: MetaDao<C>>
         }
 
         self.dialog_dao.persist_dialog(project_id, dialog)?;
+        self.search_index.mark_dialog_stale(project_id, dialog_id)?;
         self.meta_srv.save_var_to_phylum_fk(project_id, fks)?;
-        self.dialog_dao.persist_metadata(project_id, &metadata)
+        self.meta_srv.save_line_id_registry(project_id, line_ids)?;
+        self.dialog_dao.persist_metadata(project_id, &metadata)?;
+        for version in versions {
+            self.history_dao
+                .push_version(project_id, dialog_id, &version, HISTORY_LIMIT)?;
+        }
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
     }
 
     /// Used only to change metadata, and not add / supress a dialog.
-    /// Throws if the old metadata and the new do not contain the same dialogs
+    /// Throws if the old metadata and the new do not contain the same dialogs,
+    /// or if the groups reference unknown groups or are nested in themselves.
     pub fn save_dialog_metadata(
         &self,
         project_id: &str,
@@ -135,16 +386,144 @@ impl<C: ODConfig, DD: DialogDao<C>, CD: CharacterDao<C>, MD: MetaDao<C>>
     ) -> Result<()> {
         let old = self.dialog_dao.get_metadata(project_id)?;
         dialog_metadata.enforce_metadata_contains_same_dialogs(old)?;
+        dialog_metadata.enforce_groups_coherence()?;
         self.dialog_dao
             .persist_metadata(project_id, &dialog_metadata)?;
+        self.git_srv.snapshot_after_save(project_id);
         Ok(())
     }
 
+    /// Deletes the dialog along with its variables, and removes its phylums
+    /// from the variables they use. Refuses if a variable of the dialog is
+    /// still used by conditions of other dialogs, or if other dialogs link to it.
     pub fn delete_dialog(&self, project_id: &str, dialog_id: Uuid) -> Result<()> {
+        self.delete_dialogs(project_id, &[dialog_id])
+    }
+
+    /// Deletes several dialogs as `delete_dialog` does. Links and conditions
+    /// between the deleted dialogs do not prevent the deletion, the ones from
+    /// other dialogs are all checked before any change.
+    pub fn delete_dialogs(&self, project_id: &str, dialog_ids: &[Uuid]) -> Result<()> {
+        let deleted: HashSet<Uuid> = dialog_ids.iter().copied().collect();
+        let graph = self.get_story_graph(project_id)?;
+        let references: Vec<&StoryLink> = graph
+            .links
+            .iter()
+            .filter(|l| deleted.contains(&l.target_dialog_id) && !deleted.contains(&l.dialog_id))
+            .collect();
+        if let Some(link) = references.first() {
+            bail!(
+                "dialog {} is linked by {} node(s) of other dialogs, such as node {} of dialog {}",
+                link.target_dialog_id,
+                references.len(),
+                link.node_id,
+                link.dialog_id
+            )
+        }
+        let mut metadata = self.dialog_dao.get_metadata(project_id)?;
+        let mut dialogs = Vec::with_capacity(deleted.len());
+        for dialog_id in &deleted {
+            metadata.delete_dialog_by_id(dialog_id)?;
+            dialogs.push(self.dialog_dao.get_dialog_by_id(project_id, dialog_id)?);
+        }
+        let mut fks = self.meta_srv.get_var_to_phylum(project_id)?;
+        fks.mutate_to_match_diffs(PhylumDiff {
+            added: vec![],
+            deleted: dialogs
+                .iter()
+                .flat_map(|d| d.get_phylums_map().into_values())
+                .collect(),
+        })?;
+        let mut vars = self.var_dao.load_variables(project_id)?;
+        let dialog_vars: Vec<Uuid> = deleted
+            .iter()
+            .flat_map(|id| vars.get_dialog_variables(id))
+            .collect();
+        for var_id in &dialog_vars {
+            if !fks.get_phylums(var_id).is_empty() {
+                bail!("variable {var_id} of the dialog is still used in conditions of other dialogs")
+            }
+        }
+
+        if !dialog_vars.is_empty() {
+            for var_id in &dialog_vars {
+                vars.delete_var_with_id(var_id);
+                fks.data.remove(var_id);
+            }
+            self.var_dao.persist_variables(project_id, &vars)?;
+            self.search_index.mark_variables_stale(project_id)?;
+        }
+        self.meta_srv.save_var_to_phylum_fk(project_id, fks)?;
+        self.dialog_dao.persist_metadata(project_id, &metadata)?;
+        // The directories go last, so that a failure before leaves no
+        // variable or foreign key pointing to a dialog which is gone.
+        for dialog_id in &deleted {
+            self.history_dao.clear_history(project_id, dialog_id)?;
+            self.dialog_dao.delete_dialog_by_id(project_id, dialog_id)?;
+            self.search_index.mark_dialog_stale(project_id, dialog_id)?;
+        }
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
+    }
+
+    pub fn create_dialog_group(
+        &self,
+        project_id: &str,
+        name: &str,
+        parent_id: Option<Uuid>,
+    ) -> Result<DialogGroup> {
+        let mut metadata = self.dialog_dao.get_metadata(project_id)?;
+        let group = metadata.create_group(name, parent_id)?;
+        self.dialog_dao.persist_metadata(project_id, &metadata)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(group)
+    }
+
+    pub fn rename_dialog_group(&self, project_id: &str, group_id: Uuid, name: &str) -> Result<()> {
+        let mut metadata = self.dialog_dao.get_metadata(project_id)?;
+        metadata.rename_group(&group_id, name)?;
+        self.dialog_dao.persist_metadata(project_id, &metadata)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
+    }
+
+    /// Moves a group into another one, or to the top level when the parent is None.
+    pub fn move_dialog_group(
+        &self,
+        project_id: &str,
+        group_id: Uuid,
+        parent_id: Option<Uuid>,
+        order: usize,
+    ) -> Result<()> {
         let mut metadata = self.dialog_dao.get_metadata(project_id)?;
-        metadata.delete_dialog_by_id(&dialog_id)?;
-        self.dialog_dao.delete_dialog_by_id(project_id, &dialog_id)?;
-        self.dialog_dao.persist_metadata(project_id, &metadata)
+        metadata.move_group(&group_id, parent_id, order)?;
+        self.dialog_dao.persist_metadata(project_id, &metadata)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
+    }
+
+    /// The dialogs and sub groups of a deleted group are moved to its parent.
+    pub fn delete_dialog_group(&self, project_id: &str, group_id: Uuid) -> Result<()> {
+        let mut metadata = self.dialog_dao.get_metadata(project_id)?;
+        metadata.delete_group(&group_id)?;
+        self.dialog_dao.persist_metadata(project_id, &metadata)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
+    }
+
+    /// Moves a dialog into a group, or out of any group when the group is None.
+    pub fn assign_dialog_to_group(
+        &self,
+        project_id: &str,
+        dialog_id: Uuid,
+        group_id: Option<Uuid>,
+        order: usize,
+    ) -> Result<()> {
+        let mut metadata = self.dialog_dao.get_metadata(project_id)?;
+        metadata.assign_dialog(&dialog_id, group_id, order)?;
+        self.dialog_dao.persist_metadata(project_id, &metadata)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
     }
 
     pub fn save_dialog_content(
@@ -155,6 +534,25 @@ impl<C: ODConfig, DD: DialogDao<C>, CD: CharacterDao<C>, MD: MetaDao<C>>
         content: &str,
     ) -> Result<()> {
         self.dialog_dao
-            .persist_dialog_content(project_id, &dialog_id, &node_id, content)
+            .persist_dialog_content(project_id, &dialog_id, &node_id, content)?;
+        self.search_index.mark_dialog_stale(project_id, &dialog_id)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
+    }
+
+    /// Versions to record once the dialog about to be saved is. The first time,
+    /// the version on disk comes before it, so that this first save can be undone.
+    fn get_versions_to_record(&self, project_id: &str, dialog: &mut Dialog) -> Result<Vec<String>> {
+        let dialog_id = dialog.get_id();
+        let state = self.history_dao.get_history_state(project_id, &dialog_id)?;
+        let mut versions = vec![];
+        if !state.can_undo && !state.can_redo {
+            let current = self.get_dialog_by_id(project_id, dialog_id)?;
+            versions.push(to_ndjson(&current, Dialog::COLLECTIONS)?);
+        }
+        self.load_content(project_id, dialog)?;
+        versions.push(to_ndjson(&*dialog, Dialog::COLLECTIONS)?);
+        Ok(versions)
     }
+
 }
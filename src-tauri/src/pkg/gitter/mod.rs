@@ -1,7 +1,7 @@
 use od_macros::tauri_command;
 use uuid::Uuid;
 
-use crate::shared::types::gitter::CommitGraph;
+use crate::shared::types::gitter::{CommitGraph, CommitInfo};
 
 pub mod service;
 
@@ -10,3 +10,18 @@ pub fn get_logs(project_id: Uuid) -> CommitGraph {}
 
 #[tauri_command(git_service)]
 pub fn commit(project_id: Uuid, message: &str) {}
+
+#[tauri_command(git_service)]
+pub fn get_snapshots_enabled(project_id: Uuid) -> bool {}
+
+#[tauri_command(git_service)]
+pub fn set_snapshots_enabled(project_id: Uuid, enabled: bool) {}
+
+#[tauri_command(git_service)]
+pub fn take_snapshot(project_id: Uuid) -> Option<CommitInfo> {}
+
+#[tauri_command(git_service)]
+pub fn get_snapshots(project_id: Uuid) -> Vec<CommitInfo> {}
+
+#[tauri_command(git_service)]
+pub fn recover_snapshot(project_id: Uuid, snapshot_id: &str) {}
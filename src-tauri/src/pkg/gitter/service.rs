@@ -1,7 +1,7 @@
 use std::path::PathBuf;
 
-use anyhow::{Context, Result};
-use git2::Repository;
+use anyhow::{anyhow, Context, Result};
+use git2::{build::CheckoutBuilder, Commit, Oid, Repository, Signature};
 use uuid::Uuid;
 
 use crate::{
@@ -14,6 +14,12 @@ use crate::{
     },
 };
 
+/// Hidden reference on which autosaves are recorded until the user commits.
+/// Being outside of refs/heads, it is not shown as a branch.
+const SNAPSHOT_REF: &str = "refs/open-dialog/snapshots";
+/// Key of the repository configuration enabling snapshots.
+const SNAPSHOT_CONFIG_KEY: &str = "opendialog.snapshots";
+
 pub struct GitService<C: ODConfig> {
     config: Shared<C>,
 }
@@ -30,6 +36,127 @@ impl<C: ODConfig> GitService<C> {
         let tree = repo.find_tree(tree_oid)?;
         let parent = repo.head()?.peel_to_commit()?;
         repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &[&parent])?;
+        self.clear_snapshots(&repo)
+    }
+
+    pub fn get_snapshots_enabled(&self, project_id: Uuid) -> Result<bool> {
+        let repo = self.open_repository(&project_id)?;
+        let enabled = repo.config()?.get_bool(SNAPSHOT_CONFIG_KEY).unwrap_or(false);
+        Ok(enabled)
+    }
+
+    /// Snapshots are a setting of the local repository, not of the project.
+    pub fn set_snapshots_enabled(&self, project_id: Uuid, enabled: bool) -> Result<()> {
+        let repo = self.open_repository(&project_id)?;
+        repo.config()?.set_bool(SNAPSHOT_CONFIG_KEY, enabled)?;
+        Ok(())
+    }
+
+    /// Records the working tree on the snapshot reference, without touching
+    /// the index nor the branch. Meant to be called after each autosave.
+    /// Returns None if snapshots are disabled or nothing changed since the last one.
+    pub fn take_snapshot(&self, project_id: Uuid) -> Result<Option<CommitInfo>> {
+        let repo = self.open_repository(&project_id)?;
+        if !repo.config()?.get_bool(SNAPSHOT_CONFIG_KEY).unwrap_or(false) {
+            return Ok(None);
+        }
+        self.snapshot(&repo, "Autosave")
+    }
+
+    /// Records the working tree on the snapshot reference, whether snapshots are
+    /// enabled or not, before an operation rewriting many files. Returns the id
+    /// of the snapshot holding the working tree, to recover to undo the operation.
+    pub fn take_backup(&self, project_id: Uuid, message: &str) -> Result<String> {
+        let repo = self.open_repository(&project_id)?;
+        if let Some(snapshot) = self.snapshot(&repo, message)? {
+            return Ok(snapshot.id);
+        }
+        let oid = match repo.refname_to_id(SNAPSHOT_REF) {
+            Ok(oid) => oid,
+            Err(_) => repo.head()?.peel_to_commit()?.id(),
+        };
+        Ok(oid.to_string())
+    }
+
+    /// Takes a snapshot after the project was saved. Saving must not fail
+    /// because of snapshots, e.g. for a project which is not a repository,
+    /// so errors are only logged.
+    pub fn snapshot_after_save(&self, project_id: &str) {
+        let snapshot = Uuid::parse_str(project_id)
+            .map_err(anyhow::Error::from)
+            .and_then(|uuid| self.take_snapshot(uuid));
+        if let Err(e) = snapshot {
+            log::warn!("could not take a snapshot of {project_id}: {e}");
+        }
+    }
+
+    /// Snapshots recorded since the last commit, the most recent first.
+    pub fn get_snapshots(&self, project_id: Uuid) -> Result<Vec<CommitInfo>> {
+        let repo = self.open_repository(&project_id)?;
+        let tip = match repo.refname_to_id(SNAPSHOT_REF) {
+            Ok(oid) => oid,
+            Err(_) => return Ok(vec![]),
+        };
+        let mut rev_walk = repo.revwalk()?;
+        rev_walk.push(tip)?;
+        if let Some(head) = repo.head().ok().and_then(|h| h.target()) {
+            rev_walk.hide(head)?;
+        }
+        rev_walk.simplify_first_parent()?;
+        let mut snapshots = vec![];
+        for oid in rev_walk {
+            snapshots.push(to_commit_info(&repo.find_commit(oid?)?));
+        }
+        Ok(snapshots)
+    }
+
+    /// Restores the working tree to a snapshot. The current working tree is
+    /// snapshotted beforehand, so that the recovery can itself be undone.
+    pub fn recover_snapshot(&self, project_id: Uuid, snapshot_id: &str) -> Result<()> {
+        let repo = self.open_repository(&project_id)?;
+        let oid = Oid::from_str(snapshot_id)?;
+        if !self.get_snapshots(project_id)?.iter().any(|s| s.id == snapshot_id) {
+            return Err(anyhow!("snapshot {snapshot_id} does not exist"));
+        }
+        let snapshot = repo.find_commit(oid)?;
+        self.snapshot(&repo, "Before recovering a snapshot")?;
+        // The index follows the recovered files, so that the status of the
+        // project shows the recovered changes against the last commit.
+        let mut checkout = CheckoutBuilder::new();
+        checkout.force().remove_untracked(true);
+        repo.checkout_tree(snapshot.as_object(), Some(&mut checkout))
+            .context("could not recover snapshot")?;
+        Ok(())
+    }
+
+    fn snapshot(&self, repo: &Repository, message: &str) -> Result<Option<CommitInfo>> {
+        // The index is only used to build the tree : it is never written,
+        // and reloaded from disk right after.
+        let mut index = repo.index()?;
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+        index.update_all(["*"].iter(), None)?;
+        let tree_oid = index.write_tree()?;
+        index.read(true)?;
+        let parent = match repo.refname_to_id(SNAPSHOT_REF) {
+            Ok(oid) => Some(repo.find_commit(oid)?),
+            Err(_) => repo.head().ok().and_then(|h| h.peel_to_commit().ok()),
+        };
+        if parent.as_ref().is_some_and(|p| p.tree_id() == tree_oid) {
+            return Ok(None);
+        }
+        let tree = repo.find_tree(tree_oid)?;
+        let sig = self.get_signature(repo)?;
+        let parents: Vec<&Commit> = parent.iter().collect();
+        let oid = repo.commit(None, &sig, &sig, message, &tree, &parents)?;
+        repo.reference(SNAPSHOT_REF, oid, true, message)?;
+        Ok(Some(to_commit_info(&repo.find_commit(oid)?)))
+    }
+
+    /// Once committed, the snapshots are squashed into the commit and dropped.
+    fn clear_snapshots(&self, repo: &Repository) -> Result<()> {
+        if let Ok(mut reference) = repo.find_reference(SNAPSHOT_REF) {
+            reference.delete()?;
+        }
         Ok(())
     }
 
@@ -46,15 +173,7 @@ impl<C: ODConfig> GitService<C> {
 
         rev_walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
         for oid in rev_walk.flatten() {
-            let commit = repo.find_commit(oid)?;
-            commits.push(CommitInfo {
-                id: commit.id().to_string(),
-                message: commit.message().map(String::from),
-                author: commit.author().name().map(String::from),
-                email: commit.author().email().map(String::from),
-                timestamp: commit.time().seconds(),
-                parents: commit.parents().map(|p| p.id().to_string()).collect(),
-            });
+            commits.push(to_commit_info(&repo.find_commit(oid)?));
         }
 
         let head = repo
@@ -78,6 +197,17 @@ impl<C: ODConfig> GitService<C> {
         Ok(branches)
     }
 
+    /// Signature of the git configuration, or the one of the
+    /// application if git has no user configured.
+    fn get_signature(&self, repo: &Repository) -> Result<Signature<'static>> {
+        if let Ok(sig) = repo.signature() {
+            return Ok(sig);
+        }
+        let config = self.config.lock()?;
+        let email = config.get_email().cloned().unwrap_or_default();
+        Signature::now(config.get_user(), &email).context("could not create git signature")
+    }
+
     fn open_repository(&self, project_id: &Uuid) -> Result<Repository> {
         let project_path = self.get_project_path(project_id)?;
         Repository::open(project_path).context("could not open Repository")
@@ -89,3 +219,14 @@ impl<C: ODConfig> GitService<C> {
     }
 
 }
+
+fn to_commit_info(commit: &Commit) -> CommitInfo {
+    CommitInfo {
+        id: commit.id().to_string(),
+        message: commit.message().map(String::from),
+        author: commit.author().name().map(String::from),
+        email: commit.author().email().map(String::from),
+        timestamp: commit.time().seconds(),
+        parents: commit.parents().map(|p| p.id().to_string()).collect(),
+    }
+}
@@ -0,0 +1,134 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use uuid::Uuid;
+
+use crate::pkg::serializer::ndjson::{read_ndjson, write_ndjson};
+use crate::shared::{
+    config::{ODConfig, CHAR_DIRNAME, DIALOG_DIRNAME, LOCALES, LOCALE_DIRNAME},
+    types::{
+        interfaces::Shared,
+        localization::{LocaleSettings, LocaleTable},
+    },
+};
+
+pub struct FileLocalizationDao<C: ODConfig> {
+    config: Shared<C>,
+}
+
+pub trait LocalizationDao<C: ODConfig> {
+    fn get_locale_settings(&self, project_id: &str) -> Result<LocaleSettings>;
+    fn persist_locale_settings(&self, project_id: &str, settings: &LocaleSettings) -> Result<()>;
+    fn get_dialog_table(
+        &self,
+        project_id: &str,
+        dialog_id: &Uuid,
+        locale: &str,
+    ) -> Result<LocaleTable>;
+    fn persist_dialog_table(
+        &self,
+        project_id: &str,
+        dialog_id: &Uuid,
+        locale: &str,
+        table: &LocaleTable,
+    ) -> Result<()>;
+    fn get_character_table(&self, project_id: &str, locale: &str) -> Result<LocaleTable>;
+    fn persist_character_table(
+        &self,
+        project_id: &str,
+        locale: &str,
+        table: &LocaleTable,
+    ) -> Result<()>;
+}
+
+impl<C: ODConfig> LocalizationDao<C> for FileLocalizationDao<C> {
+    fn get_locale_settings(&self, project_id: &str) -> Result<LocaleSettings> {
+        let path = self.get_project_dir(project_id)?.join(LOCALES);
+        if !path.is_file() {
+            return Ok(LocaleSettings::new());
+        }
+        read_ndjson(&path).context("could not deserialize locales file")
+    }
+
+    fn persist_locale_settings(&self, project_id: &str, settings: &LocaleSettings) -> Result<()> {
+        write_ndjson(&self.get_project_dir(project_id)?.join(LOCALES), settings)
+            .context("could not serialize locale settings")
+    }
+
+    fn get_dialog_table(
+        &self,
+        project_id: &str,
+        dialog_id: &Uuid,
+        locale: &str,
+    ) -> Result<LocaleTable> {
+        self.read_table(self.get_dialog_locale_dir(project_id, dialog_id)?, locale)
+    }
+
+    fn persist_dialog_table(
+        &self,
+        project_id: &str,
+        dialog_id: &Uuid,
+        locale: &str,
+        table: &LocaleTable,
+    ) -> Result<()> {
+        self.write_table(
+            self.get_dialog_locale_dir(project_id, dialog_id)?,
+            locale,
+            table,
+        )
+    }
+
+    fn get_character_table(&self, project_id: &str, locale: &str) -> Result<LocaleTable> {
+        self.read_table(self.get_character_locale_dir(project_id)?, locale)
+    }
+
+    fn persist_character_table(
+        &self,
+        project_id: &str,
+        locale: &str,
+        table: &LocaleTable,
+    ) -> Result<()> {
+        self.write_table(self.get_character_locale_dir(project_id)?, locale, table)
+    }
+}
+
+impl<C: ODConfig> FileLocalizationDao<C> {
+    pub fn new(config: Shared<C>) -> Self {
+        Self { config }
+    }
+
+    fn read_table(&self, dir: PathBuf, locale: &str) -> Result<LocaleTable> {
+        let path = dir.join(format!("{locale}.json"));
+        if !path.is_file() {
+            return Ok(LocaleTable::new());
+        }
+        read_ndjson(&path).context("could not deserialize locale table")
+    }
+
+    fn write_table(&self, dir: PathBuf, locale: &str, table: &LocaleTable) -> Result<()> {
+        if !dir.is_dir() {
+            fs::create_dir_all(&dir).context("could not create locale directory")?;
+        }
+        write_ndjson(&dir.join(format!("{locale}.json")), table)
+            .context("could not serialize locale table")
+    }
+
+    fn get_project_dir(&self, project_id: &str) -> Result<PathBuf> {
+        self.config.lock()?.get_project_dir(project_id)
+    }
+
+    fn get_dialog_locale_dir(&self, project_id: &str, dialog_id: &Uuid) -> Result<PathBuf> {
+        Ok(self
+            .get_project_dir(project_id)?
+            .join(DIALOG_DIRNAME)
+            .join(dialog_id.to_string())
+            .join(LOCALE_DIRNAME))
+    }
+
+    fn get_character_locale_dir(&self, project_id: &str) -> Result<PathBuf> {
+        Ok(self
+            .get_project_dir(project_id)?
+            .join(CHAR_DIRNAME)
+            .join(LOCALE_DIRNAME))
+    }
+}
@@ -0,0 +1,44 @@
+use od_macros::tauri_command;
+use uuid::Uuid;
+
+use crate::shared::types::localization::{
+    LocaleProgress, LocaleSettings, LocalizedCharacterName, LocalizedDialog,
+};
+
+pub mod dao;
+pub mod service;
+
+#[tauri_command(localization_service)]
+pub fn get_locale_settings(project_id: &str) -> LocaleSettings {}
+
+#[tauri_command(localization_service)]
+pub fn save_locale_settings(project_id: &str, settings: LocaleSettings) {}
+
+#[tauri_command(localization_service)]
+pub fn get_localized_dialog(project_id: &str, dialog_id: Uuid, locale: &str) -> LocalizedDialog {}
+
+#[tauri_command(localization_service)]
+pub fn save_localized_line(
+    project_id: &str,
+    dialog_id: Uuid,
+    locale: &str,
+    node_id: Uuid,
+    choice_id: Option<Uuid>,
+    text: &str,
+) {
+}
+
+#[tauri_command(localization_service)]
+pub fn get_localized_characters(project_id: &str, locale: &str) -> Vec<LocalizedCharacterName> {}
+
+#[tauri_command(localization_service)]
+pub fn save_localized_character_name(
+    project_id: &str,
+    locale: &str,
+    character_id: Uuid,
+    name: &str,
+) {
+}
+
+#[tauri_command(localization_service)]
+pub fn get_translation_progress(project_id: &str) -> Vec<LocaleProgress> {}
@@ -0,0 +1,283 @@
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use uuid::Uuid;
+
+use crate::{
+    pkg::{
+        character::dao::CharacterDao,
+        dialog::{dao::DialogDao, service::DialogServiceLocalImpl},
+        gitter::service::GitService,
+        localization::dao::LocalizationDao,
+        meta::dao::MetaDao,
+    },
+    shared::{
+        config::ODConfig,
+        hash::content_hash,
+        types::{
+            interfaces::Shared,
+            localization::{
+                DialogTranslationProgress, FallbackTables, LocaleProgress, LocaleSettings,
+                LocalizedCharacterName, LocalizedDialog, LocalizedLine, TranslationCounts,
+            },
+        },
+    },
+};
+
+pub struct LocalizationServiceLocalImpl<
+    C: ODConfig,
+    LD: LocalizationDao<C>,
+    DD: DialogDao<C>,
+    CD: CharacterDao<C>,
+    MD: MetaDao<C>,
+> {
+    config: Shared<C>,
+    loc_dao: Arc<LD>,
+    dialog_srv: Arc<DialogServiceLocalImpl<C, DD, CD, MD>>,
+    char_dao: Arc<CD>,
+    git_srv: GitService<C>,
+}
+
+impl<
+        C: ODConfig,
+        LD: LocalizationDao<C>,
+        DD: DialogDao<C>,
+        CD: CharacterDao<C>,
+        MD: MetaDao<C>,
+    > LocalizationServiceLocalImpl<C, LD, DD, CD, MD>
+{
+    pub fn new(
+        config: Shared<C>,
+        loc_dao: Arc<LD>,
+        dialog_srv: Arc<DialogServiceLocalImpl<C, DD, CD, MD>>,
+        char_dao: Arc<CD>,
+        git_srv: GitService<C>,
+    ) -> Self {
+        Self {
+            config,
+            loc_dao,
+            dialog_srv,
+            char_dao,
+            git_srv,
+        }
+    }
+
+    pub fn get_locale_settings(&self, project_id: &str) -> Result<LocaleSettings> {
+        self.loc_dao.get_locale_settings(project_id)
+    }
+
+    pub fn save_locale_settings(&self, project_id: &str, settings: LocaleSettings) -> Result<()> {
+        settings.enforce_coherence()?;
+        self.loc_dao.persist_locale_settings(project_id, &settings)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
+    }
+
+    /// Returns every line of the dialog in the given locale,
+    /// with the fallback rules of the project applied.
+    pub fn get_localized_dialog(
+        &self,
+        project_id: &str,
+        dialog_id: Uuid,
+        locale: &str,
+    ) -> Result<LocalizedDialog> {
+        let settings = self.loc_dao.get_locale_settings(project_id)?;
+        let tables = self.get_dialog_fallback_tables(project_id, &dialog_id, &settings, locale)?;
+        let dialog = self.dialog_srv.get_dialog_by_id(project_id, dialog_id)?;
+        let lines = dialog
+            .get_lines()
+            .into_iter()
+            .map(|line| {
+                let id = line.choice_id.unwrap_or(line.node_id);
+                let resolved = tables.resolve(id, line.content);
+                LocalizedLine {
+                    node_id: *line.node_id,
+                    choice_id: line.choice_id.copied(),
+                    source_text: line.content.map(String::from),
+                    text: tables.get_own_text(id, line.content),
+                    resolved_text: resolved.as_ref().map(|(t, _)| t.clone()),
+                    resolved_locale: resolved.map(|(_, l)| l),
+                }
+            })
+            .collect();
+        Ok(LocalizedDialog {
+            dialog_id,
+            locale: String::from(locale),
+            lines,
+        })
+    }
+
+    /// Writes the text of a line in the given locale.
+    /// Text of the source locale goes through the regular save
+    /// of the dialog, other locales are stored in their string table
+    /// along with the hash of the source text they translate.
+    pub fn save_localized_line(
+        &self,
+        project_id: &str,
+        dialog_id: Uuid,
+        locale: &str,
+        node_id: Uuid,
+        choice_id: Option<Uuid>,
+        text: &str,
+    ) -> Result<()> {
+        let settings = self.loc_dao.get_locale_settings(project_id)?;
+        settings.get_locale(locale)?;
+        let mut dialog = self.dialog_srv.get_dialog_by_id(project_id, dialog_id)?;
+        if settings.is_source(locale) {
+            dialog.set_line_content(&node_id, choice_id.as_ref(), text)?;
+            return self.dialog_srv.save_dialog(project_id, dialog);
+        }
+        let source = dialog
+            .get_line_content(&node_id, choice_id.as_ref())?
+            .unwrap_or_default();
+        let mut table = self
+            .loc_dao
+            .get_dialog_table(project_id, &dialog_id, locale)?;
+        table.set_text(choice_id.unwrap_or(node_id), text, content_hash(source));
+        self.loc_dao
+            .persist_dialog_table(project_id, &dialog_id, locale, &table)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
+    }
+
+    pub fn get_localized_characters(
+        &self,
+        project_id: &str,
+        locale: &str,
+    ) -> Result<Vec<LocalizedCharacterName>> {
+        let settings = self.loc_dao.get_locale_settings(project_id)?;
+        let mut tables = vec![];
+        for code in settings.get_fallback_chain(locale)? {
+            let table = match settings.is_source(code) {
+                true => None,
+                false => Some(self.loc_dao.get_character_table(project_id, code)?),
+            };
+            tables.push((String::from(code), table));
+        }
+        let tables = FallbackTables::new(tables);
+        let metadata = self.char_dao.get_meta_file(project_id)?;
+        Ok(metadata
+            .get_ordered_characters()
+            .into_iter()
+            .map(|c| {
+                let source = c.get_name();
+                let (resolved_text, resolved_locale) = tables
+                    .resolve(c.get_id(), Some(source))
+                    .unwrap_or((source.clone(), settings.get_source_locale().clone()));
+                LocalizedCharacterName {
+                    character_id: *c.get_id(),
+                    source_text: source.clone(),
+                    text: tables.get_own_text(c.get_id(), Some(source)),
+                    resolved_text,
+                    resolved_locale,
+                }
+            })
+            .collect())
+    }
+
+    pub fn save_localized_character_name(
+        &self,
+        project_id: &str,
+        locale: &str,
+        character_id: Uuid,
+        name: &str,
+    ) -> Result<()> {
+        let settings = self.loc_dao.get_locale_settings(project_id)?;
+        settings.get_locale(locale)?;
+        if settings.is_source(locale) {
+            bail!("the name of a character in the source locale is changed through the character itself")
+        }
+        let character = self.char_dao.get_character(project_id, &character_id)?;
+        let mut table = self.loc_dao.get_character_table(project_id, locale)?;
+        table.set_text(character_id, name, content_hash(character.get_name()));
+        self.loc_dao
+            .persist_character_table(project_id, locale, &table)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
+    }
+
+    /// Counts, for every locale but the source one, the translated,
+    /// missing and stale strings of each dialog and of the character names.
+    pub fn get_translation_progress(&self, project_id: &str) -> Result<Vec<LocaleProgress>> {
+        let settings = self.loc_dao.get_locale_settings(project_id)?;
+        let targets: Vec<&String> = settings
+            .get_locales()
+            .iter()
+            .map(|l| l.get_code())
+            .filter(|code| !settings.is_source(code))
+            .collect();
+        let mut progress: Vec<LocaleProgress> = targets
+            .iter()
+            .map(|code| LocaleProgress {
+                locale: (*code).clone(),
+                counts: TranslationCounts::default(),
+                characters: TranslationCounts::default(),
+                dialogs: vec![],
+            })
+            .collect();
+
+        let char_metadata = self.char_dao.get_meta_file(project_id)?;
+        let characters = char_metadata.get_ordered_characters();
+        for locale_progress in &mut progress {
+            let table = self
+                .loc_dao
+                .get_character_table(project_id, &locale_progress.locale)?;
+            let counts = table.count(
+                characters
+                    .iter()
+                    .map(|c| (c.get_id(), c.get_name().as_str())),
+            );
+            locale_progress.counts.add(&counts);
+            locale_progress.characters = counts;
+        }
+
+        let metadata = self.dialog_srv.get_dialog_metadata(project_id)?;
+        let mut simple_dialogs: Vec<_> = metadata.data.values().collect();
+        simple_dialogs.sort_by_key(|d| d.get_order());
+        for simple_dialog in simple_dialogs {
+            let dialog_id = *simple_dialog.get_id();
+            let dialog = self.dialog_srv.get_dialog_by_id(project_id, dialog_id)?;
+            let lines: Vec<(&Uuid, &str)> = dialog
+                .get_lines()
+                .into_iter()
+                .filter_map(|l| match l.content {
+                    Some(c) if !c.is_empty() => Some((l.choice_id.unwrap_or(l.node_id), c)),
+                    _ => None,
+                })
+                .collect();
+            for locale_progress in &mut progress {
+                let table = self.loc_dao.get_dialog_table(
+                    project_id,
+                    &dialog_id,
+                    &locale_progress.locale,
+                )?;
+                let counts = table.count(lines.iter().copied());
+                locale_progress.counts.add(&counts);
+                locale_progress.dialogs.push(DialogTranslationProgress {
+                    dialog_id,
+                    dialog_name: dialog.get_name().clone(),
+                    counts,
+                });
+            }
+        }
+        Ok(progress)
+    }
+
+    fn get_dialog_fallback_tables(
+        &self,
+        project_id: &str,
+        dialog_id: &Uuid,
+        settings: &LocaleSettings,
+        locale: &str,
+    ) -> Result<FallbackTables> {
+        let mut tables = vec![];
+        for code in settings.get_fallback_chain(locale)? {
+            let table = match settings.is_source(code) {
+                true => None,
+                false => Some(self.loc_dao.get_dialog_table(project_id, dialog_id, code)?),
+            };
+            tables.push((String::from(code), table));
+        }
+        Ok(FallbackTables::new(tables))
+    }
+}
@@ -1,11 +1,15 @@
-use std::{fs::{self, File}, io::BufWriter, path::PathBuf, str::FromStr};
+use std::{path::PathBuf, str::FromStr};
 
 use anyhow::{Context, Result};
 use uuid::Uuid;
 
+use crate::pkg::serializer::ndjson::{read_ndjson, write_ndjson};
 use crate::shared::{
     config::{ODConfig, META_DIRNAME},
-    types::{interfaces::Shared, meta::VarToPhylum},
+    types::{
+        interfaces::Shared,
+        meta::{LineIdRegistry, VarToPhylum},
+    },
 };
 
 pub struct FileMetaDao<C: ODConfig> {
@@ -15,26 +19,32 @@ pub struct FileMetaDao<C: ODConfig> {
 pub trait MetaDao<C: ODConfig> {
     fn get_var_to_phylum_map(&self, project_id: &str) -> Result<VarToPhylum>;
     fn save_var_to_phylum(&self,project_id: &str, vars: VarToPhylum) -> Result<()>;
+    fn get_line_id_registry(&self, project_id: &str) -> Result<LineIdRegistry>;
+    fn save_line_id_registry(&self, project_id: &str, registry: LineIdRegistry) -> Result<()>;
 }
 
 impl<C: ODConfig> MetaDao<C> for FileMetaDao<C> {
     fn get_var_to_phylum_map(&self, project_id: &str) -> Result<VarToPhylum> {
-        Ok(self
-            .get_meta_fk_var_dialogs_path(project_id)
-            .map(|path| fs::read(&path))
-            .context("could not read character file")?
-            .map(|b| serde_json::from_slice(&b))
-            .context("could not deserialize file into meta struct.")??)
+        read_ndjson(&self.get_meta_fk_var_dialogs_path(project_id)?)
+            .context("could not deserialize file into meta struct.")
     }
 
     fn save_var_to_phylum(&self,project_id: &str, vars: VarToPhylum) -> Result<()> {
-        Ok(self
-            .get_meta_fk_var_dialogs_path(project_id)
-            .map(|cp| File::create(cp))
-            .context("error creating the character file")?
-            .map(|file| BufWriter::new(file))
-            .map(|writer| serde_json::to_writer(writer, &vars))
-            .context("could not serialize character to write into file")??)
+        write_ndjson(&self.get_meta_fk_var_dialogs_path(project_id)?, &vars)
+            .context("could not serialize meta struct to write into file")
+    }
+
+    fn get_line_id_registry(&self, project_id: &str) -> Result<LineIdRegistry> {
+        let path = self.get_meta_line_ids_path(project_id)?;
+        if !path.is_file() {
+            return Ok(LineIdRegistry::new());
+        }
+        read_ndjson(&path).context("could not deserialize line ids file")
+    }
+
+    fn save_line_id_registry(&self, project_id: &str, registry: LineIdRegistry) -> Result<()> {
+        write_ndjson(&self.get_meta_line_ids_path(project_id)?, &registry)
+            .context("could not serialize line ids")
     }
 }
 
@@ -56,4 +66,8 @@ impl<C: ODConfig> FileMetaDao<C> {
     fn get_meta_fk_var_dialogs_path(&self, project_id: &str) -> Result<PathBuf> {
         Ok(self.get_meta_dir(project_id)?.join("vars_dialogs.json"))
     }
+
+    fn get_meta_line_ids_path(&self, project_id: &str) -> Result<PathBuf> {
+        Ok(self.get_meta_dir(project_id)?.join("line_ids.json"))
+    }
 }
@@ -7,7 +7,10 @@ use crate::{
     pkg::meta::dao::MetaDao,
     shared::{
         config::ODConfig,
-        types::{interfaces::Shared, meta::VarToPhylum},
+        types::{
+            interfaces::Shared,
+            meta::{LineIdRegistry, VarToPhylum},
+        },
     },
 };
 
@@ -33,4 +36,12 @@ impl<C: ODConfig, MD: MetaDao<C>> MetaServiceLocalImpl<C, MD> {
         self.meta_dao.save_var_to_phylum(project_id, vars)
     }
 
+    pub fn get_line_id_registry(&self, project_id: &str) -> Result<LineIdRegistry> {
+        self.meta_dao.get_line_id_registry(project_id)
+    }
+
+    pub fn save_line_id_registry(&self, project_id: &str, registry: LineIdRegistry) -> Result<()> {
+        self.meta_dao.save_line_id_registry(project_id, registry)
+    }
+
 }
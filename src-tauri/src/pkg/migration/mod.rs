@@ -0,0 +1,9 @@
+use od_macros::tauri_command;
+
+use crate::shared::types::project::MigrationReport;
+
+pub mod service;
+pub mod steps;
+
+#[tauri_command(migration_service)]
+pub fn open_project(project_id: &str) -> MigrationReport {}
@@ -0,0 +1,142 @@
+use std::{fs, path::Path, sync::Arc};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::{
+    pkg::{
+        gitter::service::GitService,
+        storage::dao::{read_project_format, write_project_format},
+    },
+    shared::{
+        config::{ODConfig, BACKUP_DIRNAME},
+        types::{
+            interfaces::Shared,
+            project::{MigrationReport, CURRENT_FORMAT_VERSION},
+        },
+    },
+};
+
+/// A step upgrading the files of a project from the
+/// previous format version to the target one.
+pub trait Migration: Send + Sync {
+    fn get_target_version(&self) -> u32;
+    fn get_description(&self) -> &'static str;
+    fn migrate(&self, project_id: &str) -> Result<()>;
+}
+
+pub struct MigrationServiceLocalImpl<C: ODConfig> {
+    config: Shared<C>,
+    git_srv: GitService<C>,
+    migrations: Vec<Arc<dyn Migration>>,
+}
+
+impl<C: ODConfig> MigrationServiceLocalImpl<C> {
+    pub fn new(
+        config: Shared<C>,
+        git_srv: GitService<C>,
+        mut migrations: Vec<Arc<dyn Migration>>,
+    ) -> Self {
+        migrations.sort_by_key(|m| m.get_target_version());
+        Self {
+            config,
+            git_srv,
+            migrations,
+        }
+    }
+
+    /// Brings the project to the current format, one version at a time.
+    /// The state of the project is snapshotted, or copied if it cannot be,
+    /// before the first migration runs, and the format version is saved
+    /// after each step so that an interrupted upgrade resumes where it stopped.
+    /// Projects of a newer format are refused when their format is read.
+    pub fn open_project(&self, project_id: &str) -> Result<MigrationReport> {
+        let from_version = self.get_format_version(project_id)?;
+        let mut report = MigrationReport {
+            from_version,
+            to_version: from_version,
+            applied: vec![],
+            backup: None,
+        };
+        if from_version == CURRENT_FORMAT_VERSION {
+            return Ok(report);
+        }
+
+        report.backup = Some(self.backup(project_id, from_version)?);
+        let mut version = from_version;
+        while version < CURRENT_FORMAT_VERSION {
+            let migration = self
+                .migrations
+                .iter()
+                .find(|m| m.get_target_version() == version + 1)
+                .with_context(|| format!("no migration from format {version}"))?;
+            migration
+                .migrate(project_id)
+                .with_context(|| format!("migration to format {} failed", version + 1))?;
+            version += 1;
+            self.save_format_version(project_id, version)?;
+            report
+                .applied
+                .push(String::from(migration.get_description()));
+            report.to_version = version;
+        }
+        Ok(report)
+    }
+
+    fn get_format_version(&self, project_id: &str) -> Result<u32> {
+        Ok(read_project_format(&self.config, project_id)?.version)
+    }
+
+    fn save_format_version(&self, project_id: &str, version: u32) -> Result<()> {
+        let mut format = read_project_format(&self.config, project_id)?;
+        format.version = version;
+        write_project_format(&self.config, project_id, &format)
+    }
+
+    /// Records the current state of the project as a snapshot, which leaves
+    /// the commits and the index of the user untouched. If git cannot be used,
+    /// the project is copied to the backup directory of the application.
+    /// Returns a description of the backup.
+    fn backup(&self, project_id: &str, version: u32) -> Result<String> {
+        let message = format!("Backup before migrating from format {version}");
+        let uuid = Uuid::parse_str(project_id)?;
+        match self.git_srv.take_backup(uuid, &message) {
+            Ok(snapshot) => return Ok(format!("snapshot {snapshot}")),
+            Err(e) => log::warn!("could not snapshot before migrating {project_id}: {e}"),
+        }
+        let (project_dir, backup_dir) = {
+            let config = self.config.lock()?;
+            let project_dir = config.get_project_dir(project_id)?;
+            let name = project_dir
+                .file_name()
+                .and_then(|n| n.to_str())
+                .map(String::from)
+                .unwrap_or_default();
+            let backup_dir = config.get_root_dir().join(BACKUP_DIRNAME).join(format!(
+                "{name}-format{version}-{}",
+                Utc::now().format("%Y%m%d%H%M%S")
+            ));
+            (project_dir, backup_dir)
+        };
+        copy_dir(&project_dir, &backup_dir).context("could not back the project up")?;
+        Ok(format!("copy in {}", backup_dir.display()))
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::{
+    pkg::{
+        character::dao::CharacterDao, dialog::dao::DialogDao, localization::dao::LocalizationDao,
+        meta::dao::MetaDao, migration::service::Migration,
+        serializer::service::SerializerServiceLocalImpl, variables::dao::VariableDao,
+    },
+    shared::config::ODConfig,
+};
+
+/// Format 2 : files are written as line delimited json,
+/// and the locales and line ids files exist.
+pub struct NdjsonStorageMigration<
+    C: ODConfig,
+    DD: DialogDao<C>,
+    CD: CharacterDao<C>,
+    VD: VariableDao<C>,
+    MD: MetaDao<C>,
+    LD: LocalizationDao<C>,
+> {
+    serializer_srv: Arc<SerializerServiceLocalImpl<C, DD, CD, VD, MD, LD>>,
+}
+
+impl<
+        C: ODConfig,
+        DD: DialogDao<C>,
+        CD: CharacterDao<C>,
+        VD: VariableDao<C>,
+        MD: MetaDao<C>,
+        LD: LocalizationDao<C>,
+    > NdjsonStorageMigration<C, DD, CD, VD, MD, LD>
+{
+    pub fn new(serializer_srv: Arc<SerializerServiceLocalImpl<C, DD, CD, VD, MD, LD>>) -> Self {
+        Self { serializer_srv }
+    }
+}
+
+impl<
+        C: ODConfig,
+        DD: DialogDao<C> + Send + Sync + 'static,
+        CD: CharacterDao<C> + Send + Sync + 'static,
+        VD: VariableDao<C> + Send + Sync + 'static,
+        MD: MetaDao<C> + Send + Sync + 'static,
+        LD: LocalizationDao<C> + Send + Sync + 'static,
+    > Migration for NdjsonStorageMigration<C, DD, CD, VD, MD, LD>
+{
+    fn get_target_version(&self) -> u32 {
+        2
+    }
+
+    fn get_description(&self) -> &'static str {
+        "Store dialogs, variables and metadata as line delimited json"
+    }
+
+    fn migrate(&self, project_id: &str) -> Result<()> {
+        self.serializer_srv.migrate_storage_format(project_id)?;
+        Ok(())
+    }
+}
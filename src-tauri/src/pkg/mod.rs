@@ -5,3 +5,14 @@ pub mod serializer;
 pub mod dialog;
 pub mod variables;
 pub mod meta;
+pub mod spreadsheet;
+pub mod localization;
+pub mod translation;
+pub mod stats;
+pub mod voice;
+pub mod migration;
+pub mod storage;
+pub mod comment;
+pub mod user;
+pub mod search;
+pub mod watcher;
@@ -1,13 +1,14 @@
-use std::{fs, io::BufWriter};
+use std::fs;
 
 use anyhow::{Context, Ok, Result};
 use git2::Repository;
 use serde::{Deserialize, Serialize};
 
+use crate::pkg::serializer::ndjson::write_ndjson;
 use crate::shared::{
-    config::{ASSETS_DIRNAME, CHAR_DIRNAME, CHARACTER_META, DIALOG_DIRNAME, DIALOG_META, META_DIRNAME, META_FK_VARS_DIALOGS, ODConfig, STATS_DIRNAME, VARS},
+    config::{ASSETS_DIRNAME, CHAR_DIRNAME, CHARACTER_META, DIALOG_DIRNAME, DIALOG_META, FORMAT, LOCALES, META_DIRNAME, META_FK_VARS_DIALOGS, META_LINE_IDS, ODConfig, STATS_DIRNAME, VARS},
     types::{
-        character::CharacterMetadata, dialog::DialogMetadata, interfaces::Shared, meta::VarToPhylum, project::{AtomicProject, AtomicProjects, Project}, variables::VariableStore
+        character::CharacterMetadata, dialog::DialogMetadata, interfaces::{LineDelimited, Shared}, localization::LocaleSettings, meta::{LineIdRegistry, VarToPhylum}, project::{AtomicProject, AtomicProjects, Project, ProjectFormat}, variables::VariableStore
     },
 };
 
@@ -21,11 +22,8 @@ impl<C: ODConfig> ProjectServiceLocaleImpl<C> {
         ProjectServiceLocaleImpl { config }
     }
 
-    fn save_file<'a, T:Serialize>(&self, project:&'a Project, path:&'a str, content: &'a T) -> Result<()> {
-        let file = fs::File::create(project.get_path().join(path))?;
-        let writer = BufWriter::new(file);
-        serde_json::to_writer(writer, content).context("could not write to file")
-
+    fn save_file<'a, T: Serialize + LineDelimited>(&self, project:&'a Project, path:&'a str, content: &'a T) -> Result<()> {
+        write_ndjson(&project.get_path().join(path), content).context("could not write to file")
     }
 
     pub fn create_project(&self, name: &str) -> Result<AtomicProject> {
@@ -35,9 +33,12 @@ impl<C: ODConfig> ProjectServiceLocaleImpl<C> {
         }
 
         self.save_file(&project, META_FK_VARS_DIALOGS, &VarToPhylum::new()).context("error creating meta var dialogs filek")?;
+        self.save_file(&project, META_LINE_IDS, &LineIdRegistry::new()).context("error creating meta line ids file")?;
         self.save_file(&project, VARS, &VariableStore::new()).context("error creating vars file")?;
         self.save_file(&project, DIALOG_META, &DialogMetadata::new()).context("error creating dialog meta file")?;
         self.save_file(&project, CHARACTER_META, &CharacterMetadata::new()).context("error creating dialog meta file")?;
+        self.save_file(&project, LOCALES, &LocaleSettings::new()).context("error creating locales file")?;
+        self.save_file(&project, FORMAT, &ProjectFormat::current()).context("error creating format file")?;
 
         
         Repository::init(project.get_path())?;
@@ -0,0 +1,191 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::shared::types::{
+    character::Character,
+    dialog::Dialog,
+    interfaces::{Identified, Shared},
+    search::SearchHitKind,
+    variables::VariableStore,
+};
+
+/// A piece of text that can be searched.
+pub struct SearchDocument {
+    pub kind: SearchHitKind,
+    pub dialog_id: Option<Uuid>,
+    pub node_id: Option<Uuid>,
+    pub choice_id: Option<Uuid>,
+    pub character_id: Option<Uuid>,
+    pub variable_id: Option<Uuid>,
+    pub speaker_id: Option<Uuid>,
+    pub text: String,
+}
+
+#[derive(Default)]
+pub struct ProjectIndex {
+    pub dialogs: HashMap<Uuid, Vec<SearchDocument>>,
+    pub characters: HashMap<Uuid, Vec<SearchDocument>>,
+    pub variables: Vec<SearchDocument>,
+    stale_dialogs: HashSet<Uuid>,
+    stale_characters: HashSet<Uuid>,
+    stale_variables: bool,
+}
+
+/// What must be reloaded before searching a project.
+pub enum Staleness {
+    Unindexed,
+    Stale {
+        dialogs: HashSet<Uuid>,
+        characters: HashSet<Uuid>,
+        variables: bool,
+    },
+}
+
+/// In memory documents of the projects that were searched. Services mark
+/// the entities they save as stale, and the search service reloads them
+/// before the next search, so that only what changed is read again.
+pub struct SearchIndex {
+    projects: Shared<HashMap<String, ProjectIndex>>,
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self {
+            projects: Shared::new(HashMap::new()),
+        }
+    }
+
+    pub fn mark_dialog_stale(&self, project_id: &str, dialog_id: &Uuid) -> Result<()> {
+        if let Some(index) = self.projects.lock()?.get_mut(project_id) {
+            index.stale_dialogs.insert(*dialog_id);
+        }
+        Ok(())
+    }
+
+    pub fn mark_character_stale(&self, project_id: &str, char_id: &Uuid) -> Result<()> {
+        if let Some(index) = self.projects.lock()?.get_mut(project_id) {
+            index.stale_characters.insert(*char_id);
+        }
+        Ok(())
+    }
+
+    pub fn mark_variables_stale(&self, project_id: &str) -> Result<()> {
+        if let Some(index) = self.projects.lock()?.get_mut(project_id) {
+            index.stale_variables = true;
+        }
+        Ok(())
+    }
+
+    /// Forgets a project, which will be fully reloaded on the next search.
+    pub fn clear_project(&self, project_id: &str) -> Result<()> {
+        self.projects.lock()?.remove(project_id);
+        Ok(())
+    }
+
+    /// Returns what must be reloaded, and clears the stale marks.
+    pub fn take_stale(&self, project_id: &str) -> Result<Staleness> {
+        Ok(match self.projects.lock()?.get_mut(project_id) {
+            None => Staleness::Unindexed,
+            Some(index) => Staleness::Stale {
+                dialogs: std::mem::take(&mut index.stale_dialogs),
+                characters: std::mem::take(&mut index.stale_characters),
+                variables: std::mem::take(&mut index.stale_variables),
+            },
+        })
+    }
+
+    pub fn update_project(
+        &self,
+        project_id: &str,
+        f: impl FnOnce(&mut ProjectIndex),
+    ) -> Result<()> {
+        let mut projects = self.projects.lock()?;
+        f(projects.entry(String::from(project_id)).or_default());
+        Ok(())
+    }
+
+    pub fn read_project<T>(
+        &self,
+        project_id: &str,
+        f: impl FnOnce(&ProjectIndex) -> T,
+    ) -> Result<T> {
+        let mut projects = self.projects.lock()?;
+        Ok(f(projects.entry(String::from(project_id)).or_default()))
+    }
+}
+
+/// The name of the dialog and its lines in reading order.
+/// The content of dialog nodes must have been loaded beforehand.
+pub fn dialog_documents(dialog: &Dialog) -> Vec<SearchDocument> {
+    let dialog_id = dialog.get_id();
+    let mut res = vec![SearchDocument {
+        kind: SearchHitKind::DialogName,
+        dialog_id: Some(dialog_id),
+        node_id: None,
+        choice_id: None,
+        character_id: None,
+        variable_id: None,
+        speaker_id: None,
+        text: dialog.get_name().clone(),
+    }];
+    for line in dialog.get_lines() {
+        let text = match line.content {
+            Some(c) if !c.is_empty() => String::from(c),
+            _ => continue,
+        };
+        let (kind, speaker_id) = match line.choice_id {
+            Some(_) => (
+                SearchHitKind::ChoiceText,
+                Some(*dialog.get_main_character()),
+            ),
+            None => (SearchHitKind::NodeText, line.character_id.copied()),
+        };
+        res.push(SearchDocument {
+            kind,
+            dialog_id: Some(dialog_id),
+            node_id: Some(*line.node_id),
+            choice_id: line.choice_id.copied(),
+            character_id: speaker_id,
+            variable_id: None,
+            speaker_id,
+            text,
+        });
+    }
+    res
+}
+
+pub fn character_documents(character: &Character) -> Vec<SearchDocument> {
+    let document = |kind, text: &String| SearchDocument {
+        kind,
+        dialog_id: None,
+        node_id: None,
+        choice_id: None,
+        character_id: Some(*character.get_id()),
+        variable_id: None,
+        speaker_id: None,
+        text: text.clone(),
+    };
+    let mut res = vec![document(SearchHitKind::CharacterName, character.get_name())];
+    if let Some(description) = character.get_description() {
+        res.push(document(SearchHitKind::CharacterDescription, description));
+    }
+    res
+}
+
+pub fn variable_documents(vars: &VariableStore) -> Vec<SearchDocument> {
+    vars.data
+        .iter()
+        .map(|var| SearchDocument {
+            kind: SearchHitKind::VariableName,
+            dialog_id: None,
+            node_id: None,
+            choice_id: None,
+            character_id: None,
+            variable_id: Some(*var.get_id()),
+            speaker_id: None,
+            text: var.get_name().clone(),
+        })
+        .collect()
+}
@@ -0,0 +1,20 @@
+use od_macros::tauri_command;
+
+use crate::shared::types::search::{ReplaceMatch, ReplaceReport, SearchHit, SearchQuery};
+
+pub mod index;
+pub mod service;
+
+#[tauri_command(search_service)]
+pub fn search(project_id: &str, query: SearchQuery) -> Vec<SearchHit> {}
+
+#[tauri_command(search_service)]
+pub fn preview_replace(
+    project_id: &str,
+    query: SearchQuery,
+    replacement: &str,
+) -> Vec<ReplaceMatch> {
+}
+
+#[tauri_command(search_service)]
+pub fn apply_replace(project_id: &str, matches: Vec<ReplaceMatch>) -> ReplaceReport {}
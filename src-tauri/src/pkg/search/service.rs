@@ -0,0 +1,400 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::{bail, Context, Result};
+use regex::{Captures, Regex, RegexBuilder};
+use uuid::Uuid;
+
+use crate::{
+    pkg::{
+        character::dao::CharacterDao,
+        dialog::{dao::DialogDao, service::DialogServiceLocalImpl},
+        gitter::service::GitService,
+        meta::dao::MetaDao,
+        search::index::{
+            character_documents, dialog_documents, variable_documents, ProjectIndex,
+            SearchDocument, SearchIndex, Staleness,
+        },
+        variables::dao::VariableDao,
+    },
+    shared::{
+        config::ODConfig,
+        hash::content_hash,
+        types::{
+            dialog::DialogMetadata,
+            interfaces::Shared,
+            search::{ReplaceMatch, ReplaceReport, SearchHit, SearchHitKind, SearchQuery},
+        },
+    },
+};
+
+/// Hits returned by a single search.
+const MAX_HITS: usize = 1000;
+/// Number of characters kept on each side of a match in snippets.
+const SNIPPET_CONTEXT: usize = 40;
+
+pub struct SearchServiceLocalImpl<
+    C: ODConfig,
+    DD: DialogDao<C>,
+    CD: CharacterDao<C>,
+    MD: MetaDao<C>,
+    VD: VariableDao<C>,
+> {
+    config: Shared<C>,
+    index: Arc<SearchIndex>,
+    dialog_srv: Arc<DialogServiceLocalImpl<C, DD, CD, MD>>,
+    dialog_dao: Arc<DD>,
+    char_dao: Arc<CD>,
+    var_dao: Arc<VD>,
+    git_srv: GitService<C>,
+}
+
+impl<C: ODConfig, DD: DialogDao<C>, CD: CharacterDao<C>, MD: MetaDao<C>, VD: VariableDao<C>>
+    SearchServiceLocalImpl<C, DD, CD, MD, VD>
+{
+    pub fn new(
+        config: Shared<C>,
+        index: Arc<SearchIndex>,
+        dialog_srv: Arc<DialogServiceLocalImpl<C, DD, CD, MD>>,
+        dialog_dao: Arc<DD>,
+        char_dao: Arc<CD>,
+        var_dao: Arc<VD>,
+        git_srv: GitService<C>,
+    ) -> Self {
+        Self {
+            config,
+            index,
+            dialog_srv,
+            dialog_dao,
+            char_dao,
+            var_dao,
+            git_srv,
+        }
+    }
+
+    /// Searches the dialogs, characters and variables of the project.
+    /// Dialogs come first, in the order chosen by the user, with their
+    /// lines in reading order, then characters and variables.
+    pub fn search(&self, project_id: &str, query: SearchQuery) -> Result<Vec<SearchHit>> {
+        let matcher = build_matcher(&query)?;
+        self.find_matches(project_id, &query, &matcher, |document, caps| {
+            let m = caps.get(0)?;
+            Some(to_hit(document, m.start(), m.end()))
+        })
+    }
+
+    /// Computes the replacements of the matches found in the text of
+    /// dialog nodes and choices. When the query is a regular expression,
+    /// the replacement can refer to its groups with `$1` or `$name`.
+    pub fn preview_replace(
+        &self,
+        project_id: &str,
+        query: SearchQuery,
+        replacement: &str,
+    ) -> Result<Vec<ReplaceMatch>> {
+        let matcher = build_matcher(&query)?;
+        self.find_matches(project_id, &query, &matcher, |document, caps| {
+            let (dialog_id, node_id) = match document.kind {
+                SearchHitKind::NodeText | SearchHitKind::ChoiceText => {
+                    (document.dialog_id?, document.node_id?)
+                }
+                _ => return None,
+            };
+            let m = caps.get(0)?;
+            let hit = to_hit(document, m.start(), m.end());
+            let mut replaced = String::new();
+            match query.regex {
+                true => caps.expand(replacement, &mut replaced),
+                false => replaced.push_str(replacement),
+            }
+            Some(ReplaceMatch {
+                dialog_id,
+                node_id,
+                choice_id: document.choice_id,
+                start: m.start(),
+                end: m.end(),
+                line_checksum: content_hash(&document.text),
+                before: hit.before,
+                matched: hit.matched,
+                replacement: replaced,
+                after: hit.after,
+            })
+        })
+    }
+
+    /// Applies the selected replacements of a preview. Dialogs are saved
+    /// through the dialog service, so that their coherence is checked and
+    /// their content files are rewritten. The project is backed up on a
+    /// snapshot beforehand, which can be recovered to undo the replacements.
+    /// If a dialog cannot be saved, the dialogs already saved are restored.
+    pub fn apply_replace(
+        &self,
+        project_id: &str,
+        matches: Vec<ReplaceMatch>,
+    ) -> Result<ReplaceReport> {
+        let project_uuid = Uuid::parse_str(project_id)?;
+        let mut by_line: BTreeMap<(Uuid, Uuid, Option<Uuid>), Vec<ReplaceMatch>> = BTreeMap::new();
+        for m in matches {
+            by_line
+                .entry((m.dialog_id, m.node_id, m.choice_id))
+                .or_default()
+                .push(m);
+        }
+        let mut report = ReplaceReport {
+            replaced: 0,
+            dialogs: vec![],
+            conflicts: vec![],
+            backup: None,
+        };
+        let mut by_dialog: BTreeMap<Uuid, Vec<(Uuid, Option<Uuid>, Vec<ReplaceMatch>)>> =
+            BTreeMap::new();
+        for ((dialog_id, node_id, choice_id), line_matches) in by_line {
+            by_dialog
+                .entry(dialog_id)
+                .or_default()
+                .push((node_id, choice_id, line_matches));
+        }
+
+        let backup = self
+            .git_srv
+            .take_backup(project_uuid, "Before find and replace")?;
+        let mut originals = vec![];
+        for (dialog_id, lines) in by_dialog {
+            let mut dialog = self.dialog_srv.get_dialog_by_id(project_id, dialog_id)?;
+            let mut changed = false;
+            for (node_id, choice_id, mut line_matches) in lines {
+                let current = match dialog.get_line_content(&node_id, choice_id.as_ref()) {
+                    Ok(c) => String::from(c.unwrap_or_default()),
+                    Err(_) => {
+                        report.conflicts.extend(line_matches);
+                        continue;
+                    }
+                };
+                line_matches.sort_by_key(|m| std::cmp::Reverse(m.start));
+                match apply_line_replacements(&current, &line_matches) {
+                    Some(text) => {
+                        dialog.set_line_content(&node_id, choice_id.as_ref(), &text)?;
+                        report.replaced += line_matches.len();
+                        changed = true;
+                    }
+                    None => report.conflicts.extend(line_matches),
+                }
+            }
+            if changed {
+                let original = self.dialog_srv.get_dialog_by_id(project_id, dialog_id)?;
+                if let Err(e) = self.dialog_srv.save_dialog(project_id, dialog) {
+                    return Err(self.dialog_srv.restore_dialogs(project_id, originals, e));
+                }
+                originals.push(original);
+                report.dialogs.push(dialog_id);
+            }
+        }
+        if report.replaced > 0 {
+            report.backup = Some(backup);
+        }
+        Ok(report)
+    }
+
+    /// Calls `f` on every match of the documents of the project that pass
+    /// the filters of the query, keeping what it returns.
+    fn find_matches<T>(
+        &self,
+        project_id: &str,
+        query: &SearchQuery,
+        matcher: &Regex,
+        mut f: impl FnMut(&SearchDocument, &Captures) -> Option<T>,
+    ) -> Result<Vec<T>> {
+        self.refresh_index(project_id)?;
+        let metadata = self.dialog_srv.get_dialog_metadata(project_id)?;
+        self.index.read_project(project_id, |index| {
+            let mut res = vec![];
+            for document in ordered_documents(index, &metadata) {
+                if !matches_filters(document, query, &metadata) {
+                    continue;
+                }
+                for caps in matcher.captures_iter(&document.text) {
+                    if caps.get(0).is_none_or(|m| m.is_empty()) {
+                        continue;
+                    }
+                    if res.len() == MAX_HITS {
+                        return res;
+                    }
+                    if let Some(item) = f(document, &caps) {
+                        res.push(item);
+                    }
+                }
+            }
+            res
+        })
+    }
+
+    /// Loads the project on its first search, then only what was saved since.
+    fn refresh_index(&self, project_id: &str) -> Result<()> {
+        let (dialogs, characters, variables) = match self.index.take_stale(project_id)? {
+            Staleness::Unindexed => (
+                self.dialog_dao.get_dialog_identifiers(project_id)?,
+                self.char_dao.get_character_identifiers(project_id)?,
+                true,
+            ),
+            Staleness::Stale {
+                dialogs,
+                characters,
+                variables,
+            } => (dialogs, characters, variables),
+        };
+
+        let existing_dialogs = match dialogs.is_empty() {
+            true => HashSet::new(),
+            false => self.dialog_dao.get_dialog_identifiers(project_id)?,
+        };
+        let mut dialog_docs = vec![];
+        for id in dialogs {
+            let docs = match existing_dialogs.contains(&id) {
+                true => Some(dialog_documents(
+                    &self.dialog_srv.get_dialog_by_id(project_id, id)?,
+                )),
+                false => None,
+            };
+            dialog_docs.push((id, docs));
+        }
+
+        let existing_characters = match characters.is_empty() {
+            true => HashSet::new(),
+            false => self.char_dao.get_character_identifiers(project_id)?,
+        };
+        let mut character_docs = vec![];
+        for id in characters {
+            let docs = match existing_characters.contains(&id) {
+                true => Some(character_documents(
+                    &self.char_dao.get_character(project_id, &id)?,
+                )),
+                false => None,
+            };
+            character_docs.push((id, docs));
+        }
+
+        let variable_docs = match variables {
+            true => Some(variable_documents(
+                &self.var_dao.load_variables(project_id)?,
+            )),
+            false => None,
+        };
+
+        self.index.update_project(project_id, |index| {
+            for (id, docs) in dialog_docs {
+                match docs {
+                    Some(d) => index.dialogs.insert(id, d),
+                    None => index.dialogs.remove(&id),
+                };
+            }
+            for (id, docs) in character_docs {
+                match docs {
+                    Some(d) => index.characters.insert(id, d),
+                    None => index.characters.remove(&id),
+                };
+            }
+            if let Some(docs) = variable_docs {
+                index.variables = docs;
+            }
+        })
+    }
+}
+
+/// Applies replacements sorted by decreasing offset to a line. Returns None
+/// if the line changed since the preview or if replacements overlap.
+fn apply_line_replacements(current: &str, matches: &[ReplaceMatch]) -> Option<String> {
+    let checksum = content_hash(current);
+    let mut text = String::from(current);
+    let mut limit = text.len();
+    for m in matches {
+        if m.line_checksum != checksum || m.end > limit || text.get(m.start..m.end)? != m.matched {
+            return None;
+        }
+        text.replace_range(m.start..m.end, &m.replacement);
+        limit = m.start;
+    }
+    Some(text)
+}
+
+fn build_matcher(query: &SearchQuery) -> Result<Regex> {
+    if query.text.is_empty() {
+        bail!("the searched text cannot be empty")
+    }
+    let pattern = match query.regex {
+        true => query.text.clone(),
+        false => regex::escape(&query.text),
+    };
+    let pattern = match query.whole_word {
+        true => format!(r"\b(?:{pattern})\b"),
+        false => pattern,
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!query.case_sensitive)
+        .build()
+        .context("invalid regular expression")
+}
+
+fn ordered_documents<'a>(
+    index: &'a ProjectIndex,
+    metadata: &DialogMetadata,
+) -> impl Iterator<Item = &'a SearchDocument> {
+    let mut dialog_ids: Vec<&Uuid> = index.dialogs.keys().collect();
+    dialog_ids.sort_by_key(|id| (metadata.data.get(*id).map(|d| d.get_order()), **id));
+    let mut character_ids: Vec<&Uuid> = index.characters.keys().collect();
+    character_ids.sort();
+    dialog_ids
+        .into_iter()
+        .flat_map(|id| &index.dialogs[id])
+        .chain(
+            character_ids
+                .into_iter()
+                .flat_map(|id| &index.characters[id]),
+        )
+        .chain(&index.variables)
+}
+
+fn matches_filters(
+    document: &SearchDocument,
+    query: &SearchQuery,
+    metadata: &DialogMetadata,
+) -> bool {
+    if let Some(speaker_id) = &query.speaker_id {
+        let is_line = matches!(
+            document.kind,
+            SearchHitKind::NodeText | SearchHitKind::ChoiceText
+        );
+        if !is_line || document.speaker_id.as_ref() != Some(speaker_id) {
+            return false;
+        }
+    }
+    if let Some(group_id) = &query.dialog_group_id {
+        let in_group = document
+            .dialog_id
+            .is_some_and(|id| metadata.is_in_group(&id, group_id));
+        if !in_group {
+            return false;
+        }
+    }
+    true
+}
+
+fn to_hit(document: &SearchDocument, start: usize, end: usize) -> SearchHit {
+    let text = &document.text;
+    let before: String = {
+        let chars: Vec<char> = text[..start].chars().rev().take(SNIPPET_CONTEXT).collect();
+        chars.into_iter().rev().collect()
+    };
+    SearchHit {
+        kind: document.kind,
+        dialog_id: document.dialog_id,
+        node_id: document.node_id,
+        choice_id: document.choice_id,
+        character_id: document.character_id,
+        variable_id: document.variable_id,
+        before,
+        matched: String::from(&text[start..end]),
+        after: text[end..].chars().take(SNIPPET_CONTEXT).collect(),
+    }
+}
@@ -1 +1,8 @@
+use od_macros::tauri_command;
 
+pub mod ndjson;
+pub mod protobuf;
+pub mod service;
+
+#[tauri_command(serializer_service)]
+pub fn migrate_storage_format(project_id: &str) -> usize {}
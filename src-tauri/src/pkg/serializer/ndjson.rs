@@ -0,0 +1,161 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+use crate::shared::{own_writes, types::interfaces::LineDelimited};
+
+const FORMAT: &str = "od-ndjson";
+
+/// Serializes a value into a line delimited json document that stays
+/// stable between saves, so that git diffs only show what changed :
+///  * the first line holds the fields of the value, except the collections ;
+///  * every following line holds one element of a collection.
+///
+/// Elements of map collections are sorted by key, elements of list
+/// collections keep their order. Keys of every object are sorted.
+pub fn to_ndjson<T: Serialize>(value: &T, collections: &[&str]) -> Result<String> {
+    let mut object = match serde_json::to_value(value).context("could not serialize value")? {
+        Value::Object(o) => o,
+        _ => bail!("only structs can be serialized as ndjson"),
+    };
+    let mut kinds = Map::new();
+    let mut lines = vec![];
+    for collection in collections {
+        match object.remove(*collection) {
+            Some(Value::Object(map)) => {
+                kinds.insert(String::from(*collection), Value::from("map"));
+                let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+                for (key, element) in entries {
+                    lines.push(element_line(collection, Some(key), element));
+                }
+            }
+            Some(Value::Array(list)) => {
+                kinds.insert(String::from(*collection), Value::from("list"));
+                for element in list {
+                    lines.push(element_line(collection, None, element));
+                }
+            }
+            _ => bail!("{collection} is not a collection"),
+        }
+    }
+    let mut header = Map::new();
+    header.insert(String::from("format"), Value::from(FORMAT));
+    header.insert(String::from("collections"), Value::Object(kinds));
+    header.insert(String::from("header"), Value::Object(object));
+
+    let mut res = String::new();
+    write_canonical(&Value::Object(header), &mut res);
+    res.push('\n');
+    for line in lines {
+        write_canonical(&line, &mut res);
+        res.push('\n');
+    }
+    Ok(res)
+}
+
+/// Reads a document written by [`to_ndjson`], or a plain json
+/// document as written by the previous versions of the application.
+pub fn from_ndjson<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    let content = std::str::from_utf8(bytes).context("file is not valid utf-8")?;
+    let mut lines = content.lines().filter(|l| !l.trim().is_empty());
+    let header = lines
+        .next()
+        .and_then(|l| serde_json::from_str::<Value>(l).ok())
+        .filter(|h| h.get("format").and_then(Value::as_str) == Some(FORMAT));
+    let header = match header {
+        Some(h) => h,
+        None => return serde_json::from_slice(bytes).context("could not deserialize json file"),
+    };
+
+    let mut object = match header.get("header") {
+        Some(Value::Object(o)) => o.clone(),
+        _ => bail!("ndjson header is missing"),
+    };
+    if let Some(Value::Object(kinds)) = header.get("collections") {
+        for (collection, kind) in kinds {
+            let empty = match kind.as_str() {
+                Some("map") => Value::Object(Map::new()),
+                Some("list") => Value::Array(vec![]),
+                _ => bail!("unknown kind of collection for {collection}"),
+            };
+            object.insert(collection.clone(), empty);
+        }
+    }
+    for (i, line) in lines.enumerate() {
+        let mut element: Value = serde_json::from_str(line)
+            .with_context(|| format!("could not parse line {} of ndjson file", i + 2))?;
+        let collection = element
+            .get("collection")
+            .and_then(Value::as_str)
+            .map(String::from)
+            .ok_or(anyhow!("line {} has no collection", i + 2))?;
+        let value = element
+            .get_mut("value")
+            .map(Value::take)
+            .ok_or(anyhow!("line {} has no value", i + 2))?;
+        match (object.get_mut(&collection), element.get("key")) {
+            (Some(Value::Object(map)), Some(Value::String(key))) => {
+                map.insert(key.clone(), value);
+            }
+            (Some(Value::Array(list)), None) => list.push(value),
+            _ => bail!("line {} does not match collection {collection}", i + 2),
+        }
+    }
+    serde_json::from_value(Value::Object(object)).context("could not deserialize ndjson file")
+}
+
+/// Reads a file written by [`write_ndjson`], or a plain json file.
+pub fn read_ndjson<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let file = fs::read(path).with_context(|| format!("could not read {}", path.display()))?;
+    from_ndjson(&file)
+}
+
+pub fn write_ndjson<T: Serialize + LineDelimited>(path: &Path, value: &T) -> Result<()> {
+    let content = to_ndjson(value, T::COLLECTIONS)?;
+    own_writes::write(path, content).with_context(|| format!("could not write {}", path.display()))
+}
+
+fn element_line(collection: &str, key: Option<String>, value: Value) -> Value {
+    let mut line = Map::new();
+    line.insert(String::from("collection"), Value::from(collection));
+    if let Some(key) = key {
+        line.insert(String::from("key"), Value::from(key));
+    }
+    line.insert(String::from("value"), value);
+    Value::Object(line)
+}
+
+/// Compact json with the keys of every object sorted,
+/// whatever the ordering of the maps of serde_json.
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::from(key.as_str()).to_string());
+                out.push(':');
+                write_canonical(&map[key], out);
+            }
+            out.push('}');
+        }
+        Value::Array(list) => {
+            out.push('[');
+            for (i, element) in list.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(element, out);
+            }
+            out.push(']');
+        }
+        leaf => out.push_str(&leaf.to_string()),
+    }
+}
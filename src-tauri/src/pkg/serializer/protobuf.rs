@@ -0,0 +1,28 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+use prost::Message;
+
+use crate::shared::own_writes;
+
+/// Reads a file holding a single protobuf message `P`
+/// and converts it into the domain type `T`.
+pub fn read_protobuf<P, T>(path: &Path) -> Result<T>
+where
+    P: Message + Default,
+    T: TryFrom<P, Error = anyhow::Error>,
+{
+    let bytes = fs::read(path).with_context(|| format!("could not read {}", path.display()))?;
+    let message = P::decode(bytes.as_slice())
+        .with_context(|| format!("could not decode protobuf file {}", path.display()))?;
+    T::try_from(message)
+}
+
+/// Converts the domain type `T` into the protobuf message `P` and writes it to a file.
+pub fn write_protobuf<P, T>(path: &Path, value: &T) -> Result<()>
+where
+    P: Message + for<'a> From<&'a T>,
+{
+    own_writes::write(path, P::from(value).encode_to_vec())
+        .with_context(|| format!("could not write {}", path.display()))
+}
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+
+use crate::{
+    pkg::{
+        character::dao::CharacterDao, dialog::dao::DialogDao, localization::dao::LocalizationDao,
+        meta::dao::MetaDao, variables::dao::VariableDao,
+    },
+    shared::{config::ODConfig, types::interfaces::Shared},
+};
+
+pub struct SerializerServiceLocalImpl<
+    C: ODConfig,
+    DD: DialogDao<C>,
+    CD: CharacterDao<C>,
+    VD: VariableDao<C>,
+    MD: MetaDao<C>,
+    LD: LocalizationDao<C>,
+> {
+    config: Shared<C>,
+    dialog_dao: Arc<DD>,
+    char_dao: Arc<CD>,
+    var_dao: Arc<VD>,
+    meta_dao: Arc<MD>,
+    loc_dao: Arc<LD>,
+}
+
+impl<
+        C: ODConfig,
+        DD: DialogDao<C>,
+        CD: CharacterDao<C>,
+        VD: VariableDao<C>,
+        MD: MetaDao<C>,
+        LD: LocalizationDao<C>,
+    > SerializerServiceLocalImpl<C, DD, CD, VD, MD, LD>
+{
+    pub fn new(
+        config: Shared<C>,
+        dialog_dao: Arc<DD>,
+        char_dao: Arc<CD>,
+        var_dao: Arc<VD>,
+        meta_dao: Arc<MD>,
+        loc_dao: Arc<LD>,
+    ) -> Self {
+        Self {
+            config,
+            dialog_dao,
+            char_dao,
+            var_dao,
+            meta_dao,
+            loc_dao,
+        }
+    }
+
+    /// Rewrites every dialog, variable and metadata file of the project
+    /// in the line delimited format. Files are read with a reader accepting
+    /// both formats, so running it on an already migrated project is harmless.
+    /// Returns the number of rewritten files.
+    pub fn migrate_storage_format(&self, project_id: &str) -> Result<usize> {
+        let mut count = 0;
+        let settings = self.loc_dao.get_locale_settings(project_id)?;
+        let locales: Vec<&String> = settings
+            .get_locales()
+            .iter()
+            .map(|l| l.get_code())
+            .filter(|code| !settings.is_source(code))
+            .collect();
+
+        let metadata = self.dialog_dao.get_metadata(project_id)?;
+        self.dialog_dao.persist_metadata(project_id, &metadata)?;
+        count += 1;
+        for dialog_id in self.dialog_dao.get_dialog_identifiers(project_id)? {
+            let dialog = self
+                .dialog_dao
+                .get_dialog_by_id(project_id, &dialog_id)
+                .with_context(|| format!("could not migrate dialog {dialog_id}"))?;
+            self.dialog_dao.persist_dialog(project_id, dialog)?;
+            count += 1;
+            let audio = self.dialog_dao.get_voice_over(project_id, &dialog_id)?;
+            if !audio.entries.is_empty() {
+                self.dialog_dao
+                    .persist_voice_over(project_id, &dialog_id, &audio)?;
+                count += 1;
+            }
+            for locale in &locales {
+                let table = self
+                    .loc_dao
+                    .get_dialog_table(project_id, &dialog_id, locale)?;
+                if !table.entries.is_empty() {
+                    self.loc_dao
+                        .persist_dialog_table(project_id, &dialog_id, locale, &table)?;
+                    count += 1;
+                }
+            }
+        }
+
+        let char_metadata = self.char_dao.get_meta_file(project_id)?;
+        self.char_dao.save_metadata(project_id, char_metadata)?;
+        count += 1;
+        for locale in &locales {
+            let table = self.loc_dao.get_character_table(project_id, locale)?;
+            if !table.entries.is_empty() {
+                self.loc_dao
+                    .persist_character_table(project_id, locale, &table)?;
+                count += 1;
+            }
+        }
+
+        let vars = self.var_dao.load_variables(project_id)?;
+        self.var_dao.persist_variables(project_id, &vars)?;
+        let fks = self.meta_dao.get_var_to_phylum_map(project_id)?;
+        self.meta_dao.save_var_to_phylum(project_id, fks)?;
+        let line_ids = self.meta_dao.get_line_id_registry(project_id)?;
+        self.meta_dao.save_line_id_registry(project_id, line_ids)?;
+        self.loc_dao
+            .persist_locale_settings(project_id, &settings)?;
+        count += 4;
+        Ok(count)
+    }
+}
@@ -0,0 +1,14 @@
+use od_macros::tauri_command;
+
+use crate::shared::types::spreadsheet::LineImportReport;
+
+pub mod service;
+
+#[tauri_command(spreadsheet_service)]
+pub fn export_dialog_lines(project_id: &str, path: &str) -> usize {}
+
+#[tauri_command(spreadsheet_service)]
+pub fn export_character_sheet(project_id: &str, path: &str) -> usize {}
+
+#[tauri_command(spreadsheet_service)]
+pub fn import_dialog_lines(project_id: &str, path: &str) -> LineImportReport {}
@@ -0,0 +1,211 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use uuid::Uuid;
+
+use crate::{
+    pkg::{
+        character::dao::CharacterDao,
+        dialog::{dao::DialogDao, service::DialogServiceLocalImpl},
+        meta::dao::MetaDao,
+    },
+    shared::{
+        config::ODConfig,
+        hash::content_hash,
+        types::{
+            character::AttributeValue,
+            dialog::SimpleDialog,
+            interfaces::Shared,
+            spreadsheet::{DialogLineRow, LineConflict, LineImportReport},
+        },
+    },
+};
+
+/// Spreadsheet applications only detect UTF-8 CSV files
+/// if they start with a byte order mark.
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+pub struct SpreadsheetServiceLocalImpl<
+    C: ODConfig,
+    DD: DialogDao<C>,
+    CD: CharacterDao<C>,
+    MD: MetaDao<C>,
+> {
+    config: Shared<C>,
+    dialog_srv: Arc<DialogServiceLocalImpl<C, DD, CD, MD>>,
+    char_dao: Arc<CD>,
+}
+
+impl<C: ODConfig, DD: DialogDao<C>, CD: CharacterDao<C>, MD: MetaDao<C>>
+    SpreadsheetServiceLocalImpl<C, DD, CD, MD>
+{
+    pub fn new(
+        config: Shared<C>,
+        dialog_srv: Arc<DialogServiceLocalImpl<C, DD, CD, MD>>,
+        char_dao: Arc<CD>,
+    ) -> Self {
+        Self {
+            config,
+            dialog_srv,
+            char_dao,
+        }
+    }
+
+    /// Writes every dialog line and choice of the project to a CSV file,
+    /// dialogs being sorted by their order and lines in reading order.
+    /// Returns the number of exported lines.
+    pub fn export_dialog_lines(&self, project_id: &str, path: &str) -> Result<usize> {
+        let project_name = self
+            .config
+            .lock()?
+            .get_project(project_id)?
+            .read()
+            .expect("read lock could not be acquired")
+            .get_name()
+            .clone();
+        let metadata = self.dialog_srv.get_dialog_metadata(project_id)?;
+        let characters = self.char_dao.get_meta_file(project_id)?;
+        let mut simple_dialogs: Vec<&SimpleDialog> = metadata.data.values().collect();
+        simple_dialogs.sort_by_key(|d| d.get_order());
+
+        let mut writer =
+            BufWriter::new(File::create(path).context("could not create spreadsheet file")?);
+        writer.write_all(UTF8_BOM)?;
+        let mut csv_writer = csv::Writer::from_writer(writer);
+        let mut count = 0;
+        for simple_dialog in simple_dialogs {
+            let dialog = self
+                .dialog_srv
+                .get_dialog_by_id(project_id, *simple_dialog.get_id())?;
+            for line in dialog.get_lines() {
+                let text = line.content.unwrap_or_default();
+                let speaker = line
+                    .character_id
+                    .and_then(|id| characters.get_character_by_id(id).ok())
+                    .map(|c| c.get_name().clone())
+                    .unwrap_or_default();
+                csv_writer
+                    .serialize(DialogLineRow {
+                        project: project_name.clone(),
+                        dialog: dialog.get_name().clone(),
+                        dialog_id: dialog.get_id(),
+                        node_id: *line.node_id,
+                        choice_id: line.choice_id.copied(),
+                        line_id: line.line_id.map(String::from),
+                        speaker,
+                        text: String::from(text),
+                        checksum: content_hash(text),
+                    })
+                    .context("could not write line to spreadsheet")?;
+                count += 1;
+            }
+        }
+        csv_writer.flush()?;
+        Ok(count)
+    }
+
+    /// Writes the characters of the project to a CSV file, sorted by their order,
+    /// with a column for each attribute of the schema. Characters referenced
+    /// by an attribute are written by name. Returns the number of characters.
+    pub fn export_character_sheet(&self, project_id: &str, path: &str) -> Result<usize> {
+        let metadata = self.char_dao.get_meta_file(project_id)?;
+        let attributes = metadata.get_ordered_attributes();
+
+        let mut writer =
+            BufWriter::new(File::create(path).context("could not create spreadsheet file")?);
+        writer.write_all(UTF8_BOM)?;
+        let mut csv_writer = csv::Writer::from_writer(writer);
+        let mut header = vec![String::from("id"), String::from("name")];
+        header.extend(attributes.iter().map(|a| a.get_name().clone()));
+        csv_writer
+            .write_record(&header)
+            .context("could not write header to spreadsheet")?;
+        let characters = metadata.get_ordered_characters();
+        for simple_character in &characters {
+            let character = self
+                .char_dao
+                .get_character(project_id, simple_character.get_id())?;
+            let mut record = vec![character.get_id().to_string(), character.get_name().clone()];
+            for attribute in &attributes {
+                let cell = match character.get_attribute_value(attribute.get_id()) {
+                    Some(AttributeValue::Text(s))
+                    | Some(AttributeValue::Enum(s))
+                    | Some(AttributeValue::Image(s)) => s.clone(),
+                    Some(AttributeValue::Number(n)) => n.to_string(),
+                    Some(AttributeValue::Character(id)) => metadata
+                        .get_character_by_id(id)
+                        .map(|c| c.get_name().clone())
+                        .unwrap_or_else(|_| id.to_string()),
+                    None => String::new(),
+                };
+                record.push(cell);
+            }
+            csv_writer
+                .write_record(&record)
+                .context("could not write character to spreadsheet")?;
+        }
+        csv_writer.flush()?;
+        Ok(characters.len())
+    }
+
+    /// Applies the text of a previously exported spreadsheet.
+    /// Only lines whose node still exists are updated, and lines
+    /// that were changed in the application since the export are
+    /// reported as conflicts instead of being overwritten.
+    pub fn import_dialog_lines(&self, project_id: &str, path: &str) -> Result<LineImportReport> {
+        let file = fs::read(path).context("could not read spreadsheet file")?;
+        let content = file.strip_prefix(UTF8_BOM).unwrap_or(&file);
+        let mut reader = csv::Reader::from_reader(content);
+        let mut rows_by_dialog: BTreeMap<Uuid, Vec<DialogLineRow>> = BTreeMap::new();
+        for row in reader.deserialize() {
+            let row: DialogLineRow = row.context("malformed row in spreadsheet")?;
+            rows_by_dialog.entry(row.dialog_id).or_default().push(row);
+        }
+
+        let metadata = self.dialog_srv.get_dialog_metadata(project_id)?;
+        let mut report = LineImportReport::new();
+        for (dialog_id, rows) in rows_by_dialog {
+            if !metadata.data.contains_key(&dialog_id) {
+                report
+                    .missing
+                    .extend(rows.iter().map(|r| r.get_reference()));
+                continue;
+            }
+            let mut dialog = self.dialog_srv.get_dialog_by_id(project_id, dialog_id)?;
+            let mut changed = false;
+            for row in rows {
+                let current = match dialog.get_line_content(&row.node_id, row.choice_id.as_ref()) {
+                    Ok(c) => String::from(c.unwrap_or_default()),
+                    Err(_) => {
+                        report.missing.push(row.get_reference());
+                        continue;
+                    }
+                };
+                if current == row.text {
+                    report.unchanged += 1;
+                    continue;
+                }
+                if content_hash(&current) != row.checksum {
+                    report.conflicts.push(LineConflict {
+                        line: row.get_reference(),
+                        current_text: current,
+                        proposed_text: row.text,
+                    });
+                    continue;
+                }
+                dialog.set_line_content(&row.node_id, row.choice_id.as_ref(), &row.text)?;
+                report.updated.push(row.get_reference());
+                changed = true;
+            }
+            if changed {
+                self.dialog_srv.save_dialog(project_id, dialog)?;
+            }
+        }
+        Ok(report)
+    }
+}
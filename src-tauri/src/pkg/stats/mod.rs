@@ -0,0 +1,8 @@
+use od_macros::tauri_command;
+
+use crate::shared::types::stats::ProjectStats;
+
+pub mod service;
+
+#[tauri_command(stats_service)]
+pub fn get_project_stats(project_id: &str) -> ProjectStats {}
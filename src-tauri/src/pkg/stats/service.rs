@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::{
+    pkg::{
+        character::dao::CharacterDao,
+        dialog::{dao::DialogDao, service::DialogServiceLocalImpl},
+        localization::{dao::LocalizationDao, service::LocalizationServiceLocalImpl},
+        meta::dao::MetaDao,
+        variables::dao::VariableDao,
+    },
+    shared::{
+        config::ODConfig,
+        types::{interfaces::Shared, stats::ProjectStats},
+    },
+};
+
+pub struct StatsServiceLocalImpl<
+    C: ODConfig,
+    LD: LocalizationDao<C>,
+    DD: DialogDao<C>,
+    CD: CharacterDao<C>,
+    MD: MetaDao<C>,
+    VD: VariableDao<C>,
+> {
+    config: Shared<C>,
+    loc_srv: Arc<LocalizationServiceLocalImpl<C, LD, DD, CD, MD>>,
+    dialog_srv: Arc<DialogServiceLocalImpl<C, DD, CD, MD>>,
+    char_dao: Arc<CD>,
+    var_dao: Arc<VD>,
+}
+
+impl<
+        C: ODConfig,
+        LD: LocalizationDao<C>,
+        DD: DialogDao<C>,
+        CD: CharacterDao<C>,
+        MD: MetaDao<C>,
+        VD: VariableDao<C>,
+    > StatsServiceLocalImpl<C, LD, DD, CD, MD, VD>
+{
+    pub fn new(
+        config: Shared<C>,
+        loc_srv: Arc<LocalizationServiceLocalImpl<C, LD, DD, CD, MD>>,
+        dialog_srv: Arc<DialogServiceLocalImpl<C, DD, CD, MD>>,
+        char_dao: Arc<CD>,
+        var_dao: Arc<VD>,
+    ) -> Self {
+        Self {
+            config,
+            loc_srv,
+            dialog_srv,
+            char_dao,
+            var_dao,
+        }
+    }
+
+    pub fn get_project_stats(&self, project_id: &str) -> Result<ProjectStats> {
+        let metadata = self.dialog_srv.get_dialog_metadata(project_id)?;
+        let mut nodes = 0;
+        let mut lines = 0;
+        let mut words = 0;
+        for dialog_id in metadata.data.keys() {
+            let mut dialog = self.dialog_srv.get_dialog_by_id(project_id, *dialog_id)?;
+            for line in dialog.get_lines() {
+                if let Some(content) = line.content.filter(|c| !c.is_empty()) {
+                    lines += 1;
+                    words += content.split_whitespace().count();
+                }
+            }
+            nodes += dialog.get_nodes().len();
+        }
+        Ok(ProjectStats {
+            dialogs: metadata.data.len(),
+            characters: self.char_dao.get_character_identifiers(project_id)?.len(),
+            variables: self.var_dao.load_variables(project_id)?.data.len(),
+            nodes,
+            lines,
+            words,
+            translations: self.loc_srv.get_translation_progress(project_id)?,
+        })
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+    sync::{LazyLock, Mutex},
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use uuid::Uuid;
+
+use crate::{
+    pkg::{
+        character::dao::{CharacterDao, FileCharacterDao, ProtoCharacterDao},
+        dialog::dao::{DialogDao, FileDialogDao, ProtoDialogDao},
+        serializer::ndjson::{read_ndjson, write_ndjson},
+        variables::dao::{FileVariableDao, ProtoVariableDao, VariableDao},
+    },
+    shared::{
+        config::{ODConfig, FORMAT},
+        types::{
+            character::{Character, CharacterMetadata},
+            dialog::{Dialog, DialogMetadata},
+            interfaces::Shared,
+            project::{ProjectFormat, StorageBackend, CURRENT_FORMAT_VERSION},
+            variables::VariableStore,
+            voice::VoiceOverTable,
+        },
+    },
+};
+
+/// Formats already read, by format file, along with the modification time of
+/// the file when it was read. Every dao call needs the storage of the project :
+/// the file is only parsed again once it changed.
+static FORMATS: LazyLock<Mutex<HashMap<PathBuf, (SystemTime, ProjectFormat)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Reads the format file of a project. Projects without
+/// one predate versioning and are stored as json.
+/// Refuses projects saved by a newer version of the application,
+/// so that they are never read nor written with an older format.
+pub fn read_project_format<C: ODConfig>(
+    config: &Shared<C>,
+    project_id: &str,
+) -> Result<ProjectFormat> {
+    let path = get_format_file(config, project_id)?;
+    let modified = match fs::metadata(&path) {
+        Ok(metadata) if metadata.is_file() => metadata.modified()?,
+        _ => return Ok(ProjectFormat::unversioned()),
+    };
+    let mut formats = FORMATS.lock().map_err(|e| anyhow!("lock error: {e}"))?;
+    if let Some((at, format)) = formats.get(&path) {
+        if *at == modified {
+            return Ok(format.clone());
+        }
+    }
+    let format: ProjectFormat = read_ndjson(&path).context("could not read format file")?;
+    if format.version > CURRENT_FORMAT_VERSION {
+        bail!(
+            "this project was saved with a newer version of the application (format {}, \
+            this version supports up to format {CURRENT_FORMAT_VERSION}). Please update the application to open it.",
+            format.version
+        )
+    }
+    formats.insert(path, (modified, format.clone()));
+    Ok(format)
+}
+
+/// The cached format is dropped, as the modification time
+/// may not change when the file is written twice in a row.
+pub fn write_project_format<C: ODConfig>(
+    config: &Shared<C>,
+    project_id: &str,
+    format: &ProjectFormat,
+) -> Result<()> {
+    let path = get_format_file(config, project_id)?;
+    let mut formats = FORMATS.lock().map_err(|e| anyhow!("lock error: {e}"))?;
+    formats.remove(&path);
+    write_ndjson(&path, format).context("could not write format file")
+}
+
+fn get_format_file<C: ODConfig>(config: &Shared<C>, project_id: &str) -> Result<PathBuf> {
+    Ok(config.lock()?.get_project_dir(project_id)?.join(FORMAT))
+}
+
+/// Dialog dao reading and writing dialogs with the
+/// storage backend chosen in the format file of the project.
+pub struct StorageDialogDao<C: ODConfig> {
+    config: Shared<C>,
+    json: FileDialogDao<C>,
+    proto: ProtoDialogDao<C>,
+}
+
+pub struct StorageCharacterDao<C: ODConfig> {
+    config: Shared<C>,
+    json: FileCharacterDao<C>,
+    proto: ProtoCharacterDao<C>,
+}
+
+pub struct StorageVariableDao<C: ODConfig> {
+    config: Shared<C>,
+    json: FileVariableDao<C>,
+    proto: ProtoVariableDao<C>,
+}
+
+impl<C: ODConfig> StorageDialogDao<C> {
+    pub fn new(config: Shared<C>) -> Self {
+        Self {
+            json: FileDialogDao::new(config.clone()),
+            proto: ProtoDialogDao::new(config.clone()),
+            config,
+        }
+    }
+
+    pub fn get_dao(&self, storage: StorageBackend) -> &dyn DialogDao<C> {
+        match storage {
+            StorageBackend::Json => &self.json,
+            StorageBackend::Protobuf => &self.proto,
+        }
+    }
+
+    /// Path of the file holding the structure of a dialog with the given backend.
+    pub fn get_dialog_file(
+        &self,
+        storage: StorageBackend,
+        project_id: &str,
+        dialog_id: &Uuid,
+    ) -> Result<PathBuf> {
+        match storage {
+            StorageBackend::Json => self.json.get_dialog_meta_file(project_id, dialog_id),
+            StorageBackend::Protobuf => self.proto.get_dialog_proto_file(project_id, dialog_id),
+        }
+    }
+
+    fn dao(&self, project_id: &str) -> Result<&dyn DialogDao<C>> {
+        Ok(self.get_dao(read_project_format(&self.config, project_id)?.storage))
+    }
+}
+
+impl<C: ODConfig> DialogDao<C> for StorageDialogDao<C> {
+    fn persist_dialog(&self, project_id: &str, dialog: Dialog) -> Result<()> {
+        self.dao(project_id)?.persist_dialog(project_id, dialog)
+    }
+
+    fn persist_metadata(&self, project_id: &str, metadata: &DialogMetadata) -> Result<()> {
+        self.dao(project_id)?.persist_metadata(project_id, metadata)
+    }
+
+    fn get_metadata(&self, project_id: &str) -> Result<DialogMetadata> {
+        self.dao(project_id)?.get_metadata(project_id)
+    }
+
+    fn create_metadata(&self, project_id: &str) -> Result<DialogMetadata> {
+        self.dao(project_id)?.create_metadata(project_id)
+    }
+
+    fn get_dialog_by_id(&self, project_id: &str, dialog_id: &Uuid) -> Result<Dialog> {
+        self.dao(project_id)?
+            .get_dialog_by_id(project_id, dialog_id)
+    }
+
+    fn get_dialog_metadata(&self, project_id: &str) -> Result<DialogMetadata> {
+        self.dao(project_id)?.get_dialog_metadata(project_id)
+    }
+
+    fn get_content(&self, project_id: &str, dialog_id: &Uuid, node_id: &Uuid) -> Result<String> {
+        self.dao(project_id)?
+            .get_content(project_id, dialog_id, node_id)
+    }
+
+    fn persist_dialog_content(
+        &self,
+        project_id: &str,
+        dialog_id: &Uuid,
+        node_id: &Uuid,
+        content: &str,
+    ) -> Result<()> {
+        self.dao(project_id)?
+            .persist_dialog_content(project_id, dialog_id, node_id, content)
+    }
+
+    fn get_dialog_identifiers(&self, project_id: &str) -> Result<HashSet<Uuid>> {
+        self.dao(project_id)?.get_dialog_identifiers(project_id)
+    }
+
+    fn delete_dialog_by_id(&self, project_id: &str, dialog_id: &Uuid) -> Result<()> {
+        self.dao(project_id)?
+            .delete_dialog_by_id(project_id, dialog_id)
+    }
+
+    fn get_voice_over(&self, project_id: &str, dialog_id: &Uuid) -> Result<VoiceOverTable> {
+        self.dao(project_id)?.get_voice_over(project_id, dialog_id)
+    }
+
+    fn persist_voice_over(
+        &self,
+        project_id: &str,
+        dialog_id: &Uuid,
+        table: &VoiceOverTable,
+    ) -> Result<()> {
+        self.dao(project_id)?
+            .persist_voice_over(project_id, dialog_id, table)
+    }
+}
+
+impl<C: ODConfig> StorageCharacterDao<C> {
+    pub fn new(config: Shared<C>) -> Self {
+        Self {
+            json: FileCharacterDao::new(config.clone()),
+            proto: ProtoCharacterDao::new(config.clone()),
+            config,
+        }
+    }
+
+    pub fn get_dao(&self, storage: StorageBackend) -> &dyn CharacterDao<C> {
+        match storage {
+            StorageBackend::Json => &self.json,
+            StorageBackend::Protobuf => &self.proto,
+        }
+    }
+
+    pub fn get_character_file(
+        &self,
+        storage: StorageBackend,
+        project_id: &str,
+        char_id: &Uuid,
+    ) -> Result<PathBuf> {
+        match storage {
+            StorageBackend::Json => self.json.get_char_path(project_id, char_id),
+            StorageBackend::Protobuf => self.proto.get_char_path(project_id, char_id),
+        }
+    }
+
+    fn dao(&self, project_id: &str) -> Result<&dyn CharacterDao<C>> {
+        Ok(self.get_dao(read_project_format(&self.config, project_id)?.storage))
+    }
+}
+
+impl<C: ODConfig> CharacterDao<C> for StorageCharacterDao<C> {
+    fn persist_character(&self, project_id: &str, character: &Character) -> Result<()> {
+        self.dao(project_id)?
+            .persist_character(project_id, character)
+    }
+
+    fn get_character(&self, project_id: &str, char_id: &Uuid) -> Result<Character> {
+        self.dao(project_id)?.get_character(project_id, char_id)
+    }
+
+    fn persist_description(&self, project_id: &str, desc_id: &Uuid, desc: &str) -> Result<()> {
+        self.dao(project_id)?
+            .persist_description(project_id, desc_id, desc)
+    }
+
+    fn get_character_identifiers(&self, project_id: &str) -> Result<HashSet<Uuid>> {
+        self.dao(project_id)?.get_character_identifiers(project_id)
+    }
+
+    fn enforce_character_existence(&self, project_id: &str, char_id: &Uuid) -> Result<()> {
+        self.dao(project_id)?
+            .enforce_character_existence(project_id, char_id)
+    }
+
+    fn get_meta_file(&self, project_id: &str) -> Result<CharacterMetadata> {
+        self.dao(project_id)?.get_meta_file(project_id)
+    }
+
+    fn save_metadata(&self, project_id: &str, metadata: CharacterMetadata) -> Result<()> {
+        self.dao(project_id)?.save_metadata(project_id, metadata)
+    }
+
+    fn delete_character_by_id(&self, project_id: &str, char_id: &Uuid) -> Result<()> {
+        self.dao(project_id)?
+            .delete_character_by_id(project_id, char_id)
+    }
+}
+
+impl<C: ODConfig> StorageVariableDao<C> {
+    pub fn new(config: Shared<C>) -> Self {
+        Self {
+            json: FileVariableDao::new(config.clone()),
+            proto: ProtoVariableDao::new(config.clone()),
+            config,
+        }
+    }
+
+    pub fn get_dao(&self, storage: StorageBackend) -> &dyn VariableDao<C> {
+        match storage {
+            StorageBackend::Json => &self.json,
+            StorageBackend::Protobuf => &self.proto,
+        }
+    }
+
+    pub fn get_variables_file(&self, storage: StorageBackend, project_id: &str) -> Result<PathBuf> {
+        match storage {
+            StorageBackend::Json => self.json.get_var_file_name(project_id),
+            StorageBackend::Protobuf => self.proto.get_var_file_name(project_id),
+        }
+    }
+
+    fn dao(&self, project_id: &str) -> Result<&dyn VariableDao<C>> {
+        Ok(self.get_dao(read_project_format(&self.config, project_id)?.storage))
+    }
+}
+
+impl<C: ODConfig> VariableDao<C> for StorageVariableDao<C> {
+    fn persist_variables(&self, project_id: &str, vars: &VariableStore) -> Result<()> {
+        self.dao(project_id)?.persist_variables(project_id, vars)
+    }
+
+    fn load_variables(&self, project_id: &str) -> Result<VariableStore> {
+        self.dao(project_id)?.load_variables(project_id)
+    }
+}
@@ -0,0 +1,12 @@
+use od_macros::tauri_command;
+
+use crate::shared::types::project::StorageBackend;
+
+pub mod dao;
+pub mod service;
+
+#[tauri_command(storage_service)]
+pub fn get_storage_backend(project_id: &str) -> StorageBackend {}
+
+#[tauri_command(storage_service)]
+pub fn convert_project_storage(project_id: &str, storage: StorageBackend) -> usize {}
@@ -0,0 +1,107 @@
+use std::{io::ErrorKind, path::PathBuf, sync::Arc};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    pkg::storage::dao::{
+        read_project_format, write_project_format, StorageCharacterDao, StorageDialogDao,
+        StorageVariableDao,
+    },
+    shared::{
+        config::ODConfig,
+        own_writes,
+        types::{interfaces::Shared, project::StorageBackend},
+    },
+};
+
+pub struct StorageServiceLocalImpl<C: ODConfig> {
+    config: Shared<C>,
+    dialog_dao: Arc<StorageDialogDao<C>>,
+    character_dao: Arc<StorageCharacterDao<C>>,
+    var_dao: Arc<StorageVariableDao<C>>,
+}
+
+impl<C: ODConfig> StorageServiceLocalImpl<C> {
+    pub fn new(
+        config: Shared<C>,
+        dialog_dao: Arc<StorageDialogDao<C>>,
+        character_dao: Arc<StorageCharacterDao<C>>,
+        var_dao: Arc<StorageVariableDao<C>>,
+    ) -> Self {
+        Self {
+            config,
+            dialog_dao,
+            character_dao,
+            var_dao,
+        }
+    }
+
+    pub fn get_storage_backend(&self, project_id: &str) -> Result<StorageBackend> {
+        Ok(read_project_format(&self.config, project_id)?.storage)
+    }
+
+    /// Rewrites the dialogs, characters and variables of the project
+    /// with another storage backend. Every file is written before the
+    /// format file is switched and the previous files are removed, so that
+    /// a failed conversion leaves the project as it was.
+    /// Returns the number of converted files.
+    pub fn convert_project_storage(
+        &self,
+        project_id: &str,
+        storage: StorageBackend,
+    ) -> Result<usize> {
+        let mut format = read_project_format(&self.config, project_id)?;
+        let from = format.storage;
+        if from == storage {
+            return Ok(0);
+        }
+        let mut obsolete: Vec<PathBuf> = vec![];
+
+        let (from_dao, to_dao) = (
+            self.dialog_dao.get_dao(from),
+            self.dialog_dao.get_dao(storage),
+        );
+        for id in from_dao.get_dialog_identifiers(project_id)? {
+            let dialog = from_dao.get_dialog_by_id(project_id, &id)?;
+            to_dao
+                .persist_dialog(project_id, dialog)
+                .with_context(|| format!("could not convert dialog {id}"))?;
+            obsolete.push(self.dialog_dao.get_dialog_file(from, project_id, &id)?);
+        }
+
+        let (from_dao, to_dao) = (
+            self.character_dao.get_dao(from),
+            self.character_dao.get_dao(storage),
+        );
+        for id in from_dao.get_character_identifiers(project_id)? {
+            let character = from_dao.get_character(project_id, &id)?;
+            to_dao
+                .persist_character(project_id, &character)
+                .with_context(|| format!("could not convert character {id}"))?;
+            obsolete.push(
+                self.character_dao
+                    .get_character_file(from, project_id, &id)?,
+            );
+        }
+
+        let vars = self.var_dao.get_dao(from).load_variables(project_id)?;
+        self.var_dao
+            .get_dao(storage)
+            .persist_variables(project_id, &vars)
+            .context("could not convert variables")?;
+        obsolete.push(self.var_dao.get_variables_file(from, project_id)?);
+
+        format.storage = storage;
+        write_project_format(&self.config, project_id, &format)?;
+        // The project is converted once the format file is switched : files left
+        // behind are never read again, so failing to remove them is only logged.
+        for path in &obsolete {
+            match own_writes::remove_file(path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => log::warn!("could not remove {}: {e}", path.display()),
+            }
+        }
+        Ok(obsolete.len())
+    }
+}
@@ -0,0 +1,25 @@
+use od_macros::tauri_command;
+
+use crate::shared::types::translation::{TranslationFormat, TranslationImportReport};
+
+pub mod po;
+pub mod service;
+pub mod xliff;
+
+#[tauri_command(translation_service)]
+pub fn export_translations(
+    project_id: &str,
+    locale: &str,
+    format: TranslationFormat,
+    path: &str,
+) -> usize {
+}
+
+#[tauri_command(translation_service)]
+pub fn import_translations(
+    project_id: &str,
+    locale: &str,
+    format: TranslationFormat,
+    path: &str,
+) -> TranslationImportReport {
+}
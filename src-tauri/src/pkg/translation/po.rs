@@ -0,0 +1,187 @@
+use anyhow::{bail, Result};
+
+use crate::shared::types::translation::{ParsedTranslations, ParsedUnit, TranslationUnit};
+
+/// Writes the units as a gettext PO file.
+/// The translation key is used as `msgctxt`, so that two identical
+/// source strings can be translated differently.
+pub fn write_po(
+    units: &[TranslationUnit],
+    project_name: &str,
+    source_locale: &str,
+    target_locale: &str,
+) -> String {
+    let mut res = format!("# Translation of {project_name} into {target_locale}\n");
+    res.push_str("msgid \"\"\nmsgstr \"\"\n");
+    res.push_str("\"MIME-Version: 1.0\\n\"\n");
+    res.push_str("\"Content-Type: text/plain; charset=UTF-8\\n\"\n");
+    res.push_str("\"Content-Transfer-Encoding: 8bit\\n\"\n");
+    res.push_str(&format!("\"Language: {}\\n\"\n", escape(target_locale)));
+    res.push_str(&format!(
+        "\"X-Source-Language: {}\\n\"\n",
+        escape(source_locale)
+    ));
+    for unit in units {
+        res.push('\n');
+        if let Some(speaker) = &unit.speaker {
+            push_comment(&mut res, &format!("Speaker: {speaker}"));
+        }
+        if let Some(previous) = &unit.previous_line {
+            push_comment(&mut res, &format!("Previous line: {previous}"));
+        }
+        for note in &unit.notes {
+            push_comment(&mut res, note);
+        }
+        res.push_str(&format!("#: {}\n", unit.group_name.replace('\n', " ")));
+        res.push_str(&format!("msgctxt \"{}\"\n", escape(&unit.key.to_string())));
+        res.push_str(&format!("msgid \"{}\"\n", escape(&unit.source)));
+        res.push_str(&format!(
+            "msgstr \"{}\"\n",
+            escape(unit.target.as_deref().unwrap_or_default())
+        ));
+    }
+    res
+}
+
+/// Reads the entries of a PO file. Entries without `msgctxt`,
+/// such as the header, are ignored.
+pub fn parse_po(content: &str) -> Result<ParsedTranslations> {
+    let mut units = vec![];
+    let mut target_locale = None;
+    let mut entry = PoEntry::new();
+    let mut current: Option<PoField> = None;
+    for (i, raw_line) in content.lines().enumerate() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            if line.is_empty() {
+                entry.flush(&mut units, &mut target_locale);
+                current = None;
+            }
+            continue;
+        }
+        let (field, rest) = if let Some(rest) = line.strip_prefix("msgctxt ") {
+            (Some(PoField::Context), rest)
+        } else if let Some(rest) = line.strip_prefix("msgid ") {
+            (Some(PoField::Id), rest)
+        } else if let Some(rest) = line.strip_prefix("msgstr ") {
+            (Some(PoField::Str), rest)
+        } else if line.starts_with('"') {
+            (None, line)
+        } else {
+            bail!("unexpected line {} in po file", i + 1)
+        };
+        if let Some(field) = field {
+            if field == PoField::Context || (field == PoField::Id && current == Some(PoField::Str))
+            {
+                entry.flush(&mut units, &mut target_locale);
+            }
+            current = Some(field);
+        }
+        let value = unescape(rest).map_err(|e| e.context(format!("line {} of po file", i + 1)))?;
+        match current {
+            Some(PoField::Context) => entry
+                .context
+                .get_or_insert_with(String::new)
+                .push_str(&value),
+            Some(PoField::Id) => entry.id.push_str(&value),
+            Some(PoField::Str) => entry.text.push_str(&value),
+            None => bail!("string without keyword at line {} of po file", i + 1),
+        }
+    }
+    entry.flush(&mut units, &mut target_locale);
+    Ok(ParsedTranslations {
+        target_locale,
+        units,
+    })
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum PoField {
+    Context,
+    Id,
+    Str,
+}
+
+struct PoEntry {
+    context: Option<String>,
+    id: String,
+    text: String,
+}
+
+impl PoEntry {
+    fn new() -> Self {
+        Self {
+            context: None,
+            id: String::new(),
+            text: String::new(),
+        }
+    }
+
+    fn flush(&mut self, units: &mut Vec<ParsedUnit>, target_locale: &mut Option<String>) {
+        let entry = std::mem::replace(self, PoEntry::new());
+        match entry.context {
+            Some(key) => units.push(ParsedUnit {
+                key,
+                source: entry.id,
+                target: Some(entry.text).filter(|t| !t.is_empty()),
+            }),
+            None if entry.id.is_empty() => {
+                let language = entry
+                    .text
+                    .lines()
+                    .find_map(|l| l.strip_prefix("Language:"))
+                    .map(|l| String::from(l.trim()));
+                if language.is_some() {
+                    *target_locale = language;
+                }
+            }
+            None => (),
+        }
+    }
+}
+
+fn push_comment(res: &mut String, comment: &str) {
+    for line in comment.lines() {
+        res.push_str(&format!("#. {line}\n"));
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => res.push_str("\\\\"),
+            '"' => res.push_str("\\\""),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
+fn unescape(quoted: &str) -> Result<String> {
+    let inner = quoted
+        .strip_prefix('"')
+        .and_then(|q| q.strip_suffix('"'))
+        .ok_or(anyhow::anyhow!("string is not quoted"))?;
+    let mut res = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => res.push('\n'),
+            Some('r') => res.push('\r'),
+            Some('t') => res.push('\t'),
+            Some('"') => res.push('"'),
+            Some('\\') => res.push('\\'),
+            Some(other) => bail!("unknown escape sequence \\{other}"),
+            None => bail!("unterminated escape sequence"),
+        }
+    }
+    Ok(res)
+}
@@ -0,0 +1,317 @@
+use std::{collections::BTreeMap, fs, str::FromStr, sync::Arc};
+
+use anyhow::{bail, Context, Result};
+use uuid::Uuid;
+
+use crate::{
+    pkg::{
+        character::dao::CharacterDao,
+        dialog::{dao::DialogDao, service::DialogServiceLocalImpl},
+        gitter::service::GitService,
+        localization::dao::LocalizationDao,
+        meta::dao::MetaDao,
+        translation::{
+            po::{parse_po, write_po},
+            xliff::{parse_xliff, write_xliff},
+        },
+    },
+    shared::{
+        config::ODConfig,
+        hash::content_hash,
+        types::{
+            interfaces::Shared,
+            localization::{LocaleSettings, LocaleTable},
+            translation::{
+                ParsedUnit, StaleTranslation, TranslationFormat, TranslationImportReport,
+                TranslationKey, TranslationUnit,
+            },
+        },
+    },
+};
+
+const CHARACTERS_GROUP_ID: &str = "characters";
+const CHARACTERS_GROUP_NAME: &str = "Characters";
+
+pub struct TranslationServiceLocalImpl<
+    C: ODConfig,
+    LD: LocalizationDao<C>,
+    DD: DialogDao<C>,
+    CD: CharacterDao<C>,
+    MD: MetaDao<C>,
+> {
+    config: Shared<C>,
+    loc_dao: Arc<LD>,
+    dialog_srv: Arc<DialogServiceLocalImpl<C, DD, CD, MD>>,
+    char_dao: Arc<CD>,
+    git_srv: GitService<C>,
+}
+
+impl<
+        C: ODConfig,
+        LD: LocalizationDao<C>,
+        DD: DialogDao<C>,
+        CD: CharacterDao<C>,
+        MD: MetaDao<C>,
+    > TranslationServiceLocalImpl<C, LD, DD, CD, MD>
+{
+    pub fn new(
+        config: Shared<C>,
+        loc_dao: Arc<LD>,
+        dialog_srv: Arc<DialogServiceLocalImpl<C, DD, CD, MD>>,
+        char_dao: Arc<CD>,
+        git_srv: GitService<C>,
+    ) -> Self {
+        Self {
+            config,
+            loc_dao,
+            dialog_srv,
+            char_dao,
+            git_srv,
+        }
+    }
+
+    /// Writes every character name and dialog line of the project
+    /// to a file for the given target locale.
+    /// Returns the number of exported strings.
+    pub fn export_translations(
+        &self,
+        project_id: &str,
+        locale: &str,
+        format: TranslationFormat,
+        path: &str,
+    ) -> Result<usize> {
+        let settings = self.get_target_locale_settings(project_id, locale)?;
+        let units = self.collect_units(project_id, locale)?;
+        let source_locale = settings.get_source_locale();
+        let content = match format {
+            TranslationFormat::Xliff => write_xliff(&units, source_locale, locale),
+            TranslationFormat::Po => {
+                let project_name = self
+                    .config
+                    .lock()?
+                    .get_project(project_id)?
+                    .read()
+                    .expect("read lock could not be acquired")
+                    .get_name()
+                    .clone();
+                write_po(&units, &project_name, source_locale, locale)
+            }
+        };
+        fs::write(path, content).context("could not write translation file")?;
+        Ok(units.len())
+    }
+
+    /// Writes the translations of a file back into the string tables of the locale.
+    /// Translations made from a source text that changed since the export
+    /// are written but reported as stale.
+    pub fn import_translations(
+        &self,
+        project_id: &str,
+        locale: &str,
+        format: TranslationFormat,
+        path: &str,
+    ) -> Result<TranslationImportReport> {
+        self.get_target_locale_settings(project_id, locale)?;
+        let content = fs::read_to_string(path).context("could not read translation file")?;
+        let parsed = match format {
+            TranslationFormat::Xliff => parse_xliff(&content)?,
+            TranslationFormat::Po => parse_po(&content)?,
+        };
+        if let Some(target) = &parsed.target_locale {
+            if target != locale {
+                bail!("file contains translations into {target}, not {locale}")
+            }
+        }
+
+        let mut report = TranslationImportReport::new();
+        let mut by_dialog: BTreeMap<Uuid, Vec<(TranslationKey, ParsedUnit)>> = BTreeMap::new();
+        let mut characters = vec![];
+        for unit in parsed.units {
+            match TranslationKey::from_str(&unit.key) {
+                Ok(key @ TranslationKey::Line { dialog_id, .. }) => {
+                    by_dialog.entry(dialog_id).or_default().push((key, unit))
+                }
+                Ok(key @ TranslationKey::CharacterName { .. }) => characters.push((key, unit)),
+                Err(_) => report.missing.push(unit.key),
+            }
+        }
+
+        let metadata = self.dialog_srv.get_dialog_metadata(project_id)?;
+        for (dialog_id, units) in by_dialog {
+            if !metadata.data.contains_key(&dialog_id) {
+                report
+                    .missing
+                    .extend(units.iter().map(|(k, _)| k.to_string()));
+                continue;
+            }
+            let dialog = self.dialog_srv.get_dialog_by_id(project_id, dialog_id)?;
+            let mut table = self
+                .loc_dao
+                .get_dialog_table(project_id, &dialog_id, locale)?;
+            let mut changed = false;
+            for (key, unit) in units {
+                let current = match key {
+                    TranslationKey::Line {
+                        node_id, choice_id, ..
+                    } => dialog.get_line_content(&node_id, choice_id.as_ref()),
+                    TranslationKey::CharacterName { .. } => continue,
+                };
+                match current {
+                    Ok(current) => {
+                        changed |= apply_unit(
+                            &mut report,
+                            &mut table,
+                            &key,
+                            unit,
+                            current.unwrap_or_default(),
+                        )
+                    }
+                    Err(_) => report.missing.push(key.to_string()),
+                }
+            }
+            if changed {
+                self.loc_dao
+                    .persist_dialog_table(project_id, &dialog_id, locale, &table)?;
+            }
+        }
+
+        let char_metadata = self.char_dao.get_meta_file(project_id)?;
+        let mut table = self.loc_dao.get_character_table(project_id, locale)?;
+        let mut changed = false;
+        for (key, unit) in characters {
+            match char_metadata.get_character_by_id(key.get_table_id()) {
+                Ok(c) => changed |= apply_unit(&mut report, &mut table, &key, unit, c.get_name()),
+                Err(_) => report.missing.push(key.to_string()),
+            }
+        }
+        if changed {
+            self.loc_dao
+                .persist_character_table(project_id, locale, &table)?;
+        }
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(report)
+    }
+
+    fn get_target_locale_settings(&self, project_id: &str, locale: &str) -> Result<LocaleSettings> {
+        let settings = self.loc_dao.get_locale_settings(project_id)?;
+        settings.get_locale(locale)?;
+        if settings.is_source(locale) {
+            bail!("{locale} is the source locale of the project and cannot be translated")
+        }
+        Ok(settings)
+    }
+
+    fn collect_units(&self, project_id: &str, locale: &str) -> Result<Vec<TranslationUnit>> {
+        let mut units = vec![];
+        let char_metadata = self.char_dao.get_meta_file(project_id)?;
+        let char_table = self.loc_dao.get_character_table(project_id, locale)?;
+        for character in char_metadata.get_ordered_characters() {
+            let key = TranslationKey::CharacterName {
+                character_id: *character.get_id(),
+            };
+            let mut notes = vec![String::from("Character name")];
+            notes.extend(stale_note(&char_table, &key, character.get_name()));
+            units.push(TranslationUnit {
+                key,
+                group_id: String::from(CHARACTERS_GROUP_ID),
+                group_name: String::from(CHARACTERS_GROUP_NAME),
+                source: character.get_name().clone(),
+                target: char_table.get_text(key.get_table_id()).cloned(),
+                speaker: None,
+                previous_line: None,
+                notes,
+            });
+        }
+
+        let metadata = self.dialog_srv.get_dialog_metadata(project_id)?;
+        let mut simple_dialogs: Vec<_> = metadata.data.values().collect();
+        simple_dialogs.sort_by_key(|d| d.get_order());
+        for simple_dialog in simple_dialogs {
+            let dialog_id = *simple_dialog.get_id();
+            let dialog = self.dialog_srv.get_dialog_by_id(project_id, dialog_id)?;
+            let table = self
+                .loc_dao
+                .get_dialog_table(project_id, &dialog_id, locale)?;
+            let previous_lines = dialog.get_previous_lines();
+            for line in dialog.get_lines() {
+                let source = match line.content {
+                    Some(c) if !c.is_empty() => c,
+                    _ => continue,
+                };
+                let key = TranslationKey::Line {
+                    dialog_id,
+                    node_id: *line.node_id,
+                    choice_id: line.choice_id.copied(),
+                };
+                let mut notes = vec![];
+                if let Some(line_id) = line.line_id {
+                    notes.push(format!("Line ID: {line_id}"));
+                }
+                if line.choice_id.is_some() {
+                    notes.push(String::from("Player choice"));
+                }
+                notes.extend(stale_note(&table, &key, source));
+                units.push(TranslationUnit {
+                    key,
+                    group_id: format!("dialog-{dialog_id}"),
+                    group_name: dialog.get_name().clone(),
+                    source: String::from(source),
+                    target: table.get_text(key.get_table_id()).cloned(),
+                    speaker: line
+                        .character_id
+                        .and_then(|id| char_metadata.get_character_by_id(id).ok())
+                        .map(|c| c.get_name().clone()),
+                    previous_line: previous_lines.get(line.node_id).map(|l| String::from(*l)),
+                    notes,
+                });
+            }
+        }
+        Ok(units)
+    }
+}
+
+fn stale_note(table: &LocaleTable, key: &TranslationKey, source: &str) -> Option<String> {
+    table
+        .entries
+        .get(key.get_table_id())
+        .filter(|e| e.source_hash != content_hash(source))
+        .map(|_| String::from("The source text changed since the last translation"))
+}
+
+/// Writes the translation of the unit in the table.
+/// Returns whether the table changed.
+fn apply_unit(
+    report: &mut TranslationImportReport,
+    table: &mut LocaleTable,
+    key: &TranslationKey,
+    unit: ParsedUnit,
+    current_source: &str,
+) -> bool {
+    let text = match unit.target {
+        Some(t) => t,
+        None => {
+            report.untranslated += 1;
+            return false;
+        }
+    };
+    let source_hash = content_hash(&unit.source);
+    let id = *key.get_table_id();
+    if let Some(existing) = table.entries.get(&id) {
+        if existing.text == text && existing.source_hash == source_hash {
+            report.unchanged += 1;
+            return false;
+        }
+    }
+    if unit.source != current_source {
+        report.stale.push(StaleTranslation {
+            key: key.to_string(),
+            translated_source: unit.source,
+            current_source: String::from(current_source),
+            text: text.clone(),
+        });
+    } else {
+        report.updated.push(key.to_string());
+    }
+    table.set_text(id, &text, source_hash);
+    true
+}
@@ -0,0 +1,141 @@
+use anyhow::{bail, Context, Result};
+
+use crate::shared::types::translation::{ParsedTranslations, ParsedUnit, TranslationUnit};
+
+const XLIFF_NAMESPACE: &str = "urn:oasis:names:tc:xliff:document:2.0";
+
+/// Writes the units as an XLIFF 2.0 document, one `<file>` per dialog.
+/// Units must be grouped by dialog.
+pub fn write_xliff(units: &[TranslationUnit], source_locale: &str, target_locale: &str) -> String {
+    let mut res = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    res.push_str(&format!(
+        "<xliff xmlns=\"{XLIFF_NAMESPACE}\" version=\"2.0\" srcLang=\"{}\" trgLang=\"{}\">\n",
+        escape(source_locale),
+        escape(target_locale)
+    ));
+    let mut current_group: Option<&str> = None;
+    for unit in units {
+        if current_group != Some(&unit.group_id) {
+            if current_group.is_some() {
+                res.push_str("  </file>\n");
+            }
+            res.push_str(&format!(
+                "  <file id=\"{}\" original=\"{}\">\n",
+                escape(&unit.group_id),
+                escape(&unit.group_name)
+            ));
+            current_group = Some(&unit.group_id);
+        }
+        res.push_str(&format!(
+            "    <unit id=\"{}\">\n",
+            escape(&unit.key.to_string())
+        ));
+        res.push_str("      <notes>\n");
+        res.push_str(&format!(
+            "        <note category=\"dialog\">{}</note>\n",
+            escape(&unit.group_name)
+        ));
+        if let Some(speaker) = &unit.speaker {
+            res.push_str(&format!(
+                "        <note category=\"speaker\">{}</note>\n",
+                escape(speaker)
+            ));
+        }
+        if let Some(previous) = &unit.previous_line {
+            res.push_str(&format!(
+                "        <note category=\"previous\">{}</note>\n",
+                escape(previous)
+            ));
+        }
+        for note in &unit.notes {
+            res.push_str(&format!("        <note>{}</note>\n", escape(note)));
+        }
+        res.push_str("      </notes>\n");
+        let state = match unit.target {
+            Some(_) => "translated",
+            None => "initial",
+        };
+        res.push_str(&format!("      <segment state=\"{state}\">\n"));
+        res.push_str(&format!(
+            "        <source>{}</source>\n",
+            escape(&unit.source)
+        ));
+        if let Some(target) = &unit.target {
+            res.push_str(&format!("        <target>{}</target>\n", escape(target)));
+        }
+        res.push_str("      </segment>\n");
+        res.push_str("    </unit>\n");
+    }
+    if current_group.is_some() {
+        res.push_str("  </file>\n");
+    }
+    res.push_str("</xliff>\n");
+    res
+}
+
+/// Reads the units of an XLIFF 2.0 document.
+/// Units split in several segments are joined back together.
+pub fn parse_xliff(content: &str) -> Result<ParsedTranslations> {
+    let doc = roxmltree::Document::parse(content).context("could not parse xliff file")?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "xliff" {
+        bail!("file is not an xliff document")
+    }
+    if root.attribute("version") != Some("2.0") {
+        bail!("only xliff 2.0 files are supported")
+    }
+    let mut units = vec![];
+    for unit in root
+        .descendants()
+        .filter(|n| n.is_element() && n.tag_name().name() == "unit")
+    {
+        let key = unit.attribute("id").context("xliff unit without id")?;
+        let mut source = String::new();
+        let mut target: Option<String> = None;
+        for segment in unit
+            .children()
+            .filter(|n| n.is_element() && n.tag_name().name() == "segment")
+        {
+            for child in segment.children().filter(|n| n.is_element()) {
+                match child.tag_name().name() {
+                    "source" => source.push_str(&text_of(child)),
+                    "target" => target
+                        .get_or_insert_with(String::new)
+                        .push_str(&text_of(child)),
+                    _ => (),
+                }
+            }
+        }
+        units.push(ParsedUnit {
+            key: String::from(key),
+            source,
+            target: target.filter(|t| !t.is_empty()),
+        });
+    }
+    Ok(ParsedTranslations {
+        target_locale: root.attribute("trgLang").map(String::from),
+        units,
+    })
+}
+
+fn text_of(node: roxmltree::Node) -> String {
+    node.descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&apos;"),
+            _ => res.push(c),
+        }
+    }
+    res
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension};
+
+use crate::shared::{
+    database::{get_uuid, Database},
+    types::user::{RecentProject, UserProfile},
+};
+
+/// Number of projects kept in the recent projects of a user.
+const RECENT_PROJECTS_LIMIT: u32 = 10;
+
+pub struct SqliteUserDao {
+    db: Arc<Database>,
+}
+
+pub trait UserDao {
+    fn persist_user_profile(&self, profile: &UserProfile) -> Result<()>;
+    fn get_user_profiles(&self) -> Result<Vec<UserProfile>>;
+    fn persist_recent_project(&self, user: &str, project: &RecentProject) -> Result<()>;
+    fn get_recent_projects(&self, user: &str) -> Result<Vec<RecentProject>>;
+    fn get_ui_state(&self, user: &str, project_id: &str, key: &str) -> Result<Option<String>>;
+    fn persist_ui_state(&self, user: &str, project_id: &str, key: &str, value: &str) -> Result<()>;
+}
+
+impl SqliteUserDao {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+}
+
+impl UserDao for SqliteUserDao {
+    fn persist_user_profile(&self, profile: &UserProfile) -> Result<()> {
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "INSERT INTO user_profiles (name, email, last_seen_at) VALUES (?1, ?2, ?3) \
+                ON CONFLICT(name) DO UPDATE SET email = ?2, last_seen_at = ?3",
+                params![profile.name, profile.email, profile.last_seen_at],
+            )
+            .context("could not save user profile")?;
+            Ok(())
+        })
+    }
+
+    fn get_user_profiles(&self) -> Result<Vec<UserProfile>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT name, email, last_seen_at FROM user_profiles ORDER BY last_seen_at DESC",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(UserProfile {
+                    name: row.get(0)?,
+                    email: row.get(1)?,
+                    last_seen_at: row.get(2)?,
+                })
+            })?;
+            rows.collect::<rusqlite::Result<Vec<UserProfile>>>()
+                .context("could not read user profiles")
+        })
+    }
+
+    fn persist_recent_project(&self, user: &str, project: &RecentProject) -> Result<()> {
+        self.db.with_connection(|conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "INSERT INTO recent_projects (user, project_id, name, opened_at) \
+                VALUES (?1, ?2, ?3, ?4) \
+                ON CONFLICT(user, project_id) DO UPDATE SET name = ?3, opened_at = ?4",
+                params![
+                    user,
+                    project.project_id.to_string(),
+                    project.name,
+                    project.opened_at
+                ],
+            )?;
+            tx.execute(
+                "DELETE FROM recent_projects WHERE user = ?1 AND project_id NOT IN \
+                (SELECT project_id FROM recent_projects WHERE user = ?1 \
+                ORDER BY opened_at DESC LIMIT ?2)",
+                params![user, RECENT_PROJECTS_LIMIT],
+            )?;
+            tx.commit().context("could not save recent project")
+        })
+    }
+
+    fn get_recent_projects(&self, user: &str) -> Result<Vec<RecentProject>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT project_id, name, opened_at FROM recent_projects \
+                WHERE user = ?1 ORDER BY opened_at DESC",
+            )?;
+            let rows = stmt.query_map(params![user], |row| {
+                Ok(RecentProject {
+                    project_id: get_uuid(row, 0)?,
+                    name: row.get(1)?,
+                    opened_at: row.get(2)?,
+                })
+            })?;
+            rows.collect::<rusqlite::Result<Vec<RecentProject>>>()
+                .context("could not read recent projects")
+        })
+    }
+
+    fn get_ui_state(&self, user: &str, project_id: &str, key: &str) -> Result<Option<String>> {
+        self.db.with_connection(|conn| {
+            conn.query_row(
+                "SELECT value FROM ui_state WHERE user = ?1 AND project_id = ?2 AND key = ?3",
+                params![user, project_id, key],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("could not read ui state")
+        })
+    }
+
+    fn persist_ui_state(&self, user: &str, project_id: &str, key: &str, value: &str) -> Result<()> {
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "INSERT INTO ui_state (user, project_id, key, value, updated_at) \
+                VALUES (?1, ?2, ?3, ?4, ?5) \
+                ON CONFLICT(user, project_id, key) DO UPDATE SET value = ?4, updated_at = ?5",
+                params![user, project_id, key, value, Utc::now()],
+            )
+            .context("could not save ui state")?;
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,24 @@
+use od_macros::tauri_command;
+
+use crate::shared::types::user::{RecentProject, UserProfile};
+
+pub mod dao;
+pub mod service;
+
+#[tauri_command(user_service)]
+pub fn get_user_profile() -> UserProfile {}
+
+#[tauri_command(user_service)]
+pub fn get_user_profiles() -> Vec<UserProfile> {}
+
+#[tauri_command(user_service)]
+pub fn get_recent_projects() -> Vec<RecentProject> {}
+
+#[tauri_command(user_service)]
+pub fn record_recent_project(project_id: &str) -> Vec<RecentProject> {}
+
+#[tauri_command(user_service)]
+pub fn get_ui_state(project_id: Option<String>, key: &str) -> Option<String> {}
+
+#[tauri_command(user_service)]
+pub fn save_ui_state(project_id: Option<String>, key: &str, value: &str) -> () {}
@@ -0,0 +1,96 @@
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::{
+    pkg::user::dao::UserDao,
+    shared::{
+        config::ODConfig,
+        types::{
+            interfaces::Shared,
+            user::{RecentProject, UserProfile},
+        },
+    },
+};
+
+/// Project id under which the UI state that
+/// does not belong to a project is stored.
+const GLOBAL_SCOPE: &str = "";
+
+pub struct UserServiceLocalImpl<C: ODConfig, UD: UserDao> {
+    config: Shared<C>,
+    dao: Arc<UD>,
+}
+
+impl<C: ODConfig, UD: UserDao> UserServiceLocalImpl<C, UD> {
+    pub fn new(config: Shared<C>, dao: Arc<UD>) -> Self {
+        Self { config, dao }
+    }
+
+    /// Returns the profile of the current user, saving
+    /// the name and email of the configuration.
+    pub fn get_user_profile(&self) -> Result<UserProfile> {
+        let profile = {
+            let config = self.config.lock()?;
+            UserProfile {
+                name: config.get_user().clone(),
+                email: config.get_email().cloned(),
+                last_seen_at: Utc::now(),
+            }
+        };
+        self.dao.persist_user_profile(&profile)?;
+        Ok(profile)
+    }
+
+    pub fn get_user_profiles(&self) -> Result<Vec<UserProfile>> {
+        self.dao.get_user_profiles()
+    }
+
+    /// Returns the projects recently opened by the current
+    /// user that still exist, most recent first.
+    pub fn get_recent_projects(&self) -> Result<Vec<RecentProject>> {
+        let config = self.config.lock()?;
+        let projects = self.dao.get_recent_projects(config.get_user())?;
+        Ok(projects
+            .into_iter()
+            .filter(|p| config.get_project(&p.project_id.to_string()).is_ok())
+            .collect())
+    }
+
+    pub fn record_recent_project(&self, project_id: &str) -> Result<Vec<RecentProject>> {
+        {
+            let config = self.config.lock()?;
+            let name = config
+                .get_project(project_id)?
+                .read()
+                .map_err(|e| anyhow!("lock error: {e}"))?
+                .get_name()
+                .clone();
+            let project = RecentProject {
+                project_id: Uuid::from_str(project_id)?,
+                name,
+                opened_at: Utc::now(),
+            };
+            self.dao
+                .persist_recent_project(config.get_user(), &project)?;
+        }
+        self.get_recent_projects()
+    }
+
+    /// UI state is stored as an opaque string chosen by the frontend,
+    /// per user and per project, or for the whole application when
+    /// no project is given.
+    pub fn get_ui_state(&self, project_id: Option<String>, key: &str) -> Result<Option<String>> {
+        let user = self.config.lock()?.get_user().clone();
+        let scope = project_id.as_deref().unwrap_or(GLOBAL_SCOPE);
+        self.dao.get_ui_state(&user, scope, key)
+    }
+
+    pub fn save_ui_state(&self, project_id: Option<String>, key: &str, value: &str) -> Result<()> {
+        let user = self.config.lock()?.get_user().clone();
+        let scope = project_id.as_deref().unwrap_or(GLOBAL_SCOPE);
+        self.dao.persist_ui_state(&user, scope, key, value)
+    }
+}
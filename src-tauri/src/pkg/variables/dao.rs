@@ -1,17 +1,25 @@
-use std::{fs::{self, File}, io::BufWriter, path::PathBuf, str::FromStr};
+use std::{path::PathBuf, str::FromStr};
 
 use anyhow::{Context, Result};
 use uuid::Uuid;
 
+use crate::pkg::serializer::{
+    ndjson::{read_ndjson, write_ndjson},
+    protobuf::{read_protobuf, write_protobuf},
+};
 use crate::shared::{
     config::ODConfig,
-    types::{interfaces::Shared, variables::VariableStore},
+    types::{interfaces::Shared, proto, variables::VariableStore},
 };
 
 pub struct FileVariableDao<C: ODConfig> {
     config: Shared<C>,
 }
 
+pub struct ProtoVariableDao<C: ODConfig> {
+    config: Shared<C>,
+}
+
 pub trait VariableDao<C: ODConfig> {
     fn persist_variables(&self, project_id: &str, vars: &VariableStore) -> Result<()>;
 
@@ -20,24 +28,13 @@ pub trait VariableDao<C: ODConfig> {
 
 impl<C: ODConfig> VariableDao<C> for FileVariableDao<C> {
     fn persist_variables(&self, project_id: &str, vars: &VariableStore) -> Result<()> {
-        Ok(self
-            .get_var_file_name(project_id)
-            .map(|cp| File::create(cp))
-            .context("error creating the variable file")?
-            .map(|file| BufWriter::new(file))
-            .map(|writer| serde_json::to_writer(writer, &vars))
-            .context("could not serialize character to write into file")??)
+        write_ndjson(&self.get_var_file_name(project_id)?, vars)
+            .context("could not serialize variables to write into file")
     }
 
     fn load_variables(&self, project_id: &str) -> Result<VariableStore> {
-        Ok(self
-            .get_var_file_name(project_id)
-            .map(|path| fs::read(&path))
-            .context("could not read var file")?
-            .map(|b| serde_json::from_slice(&b))
-            .context("could not deserialize file into character.")??)
-
-        
+        read_ndjson(&self.get_var_file_name(project_id)?)
+            .context("could not deserialize file into variables.")
     }
 }
 
@@ -46,7 +43,7 @@ impl<C: ODConfig> FileVariableDao<C> {
         Self { config }
     }
 
-    fn get_var_file_name(&self, project_id: &str) -> Result<PathBuf> {
+    pub fn get_var_file_name(&self, project_id: &str) -> Result<PathBuf> {
         let project_path = &Uuid::from_str(project_id)?.simple().to_string()[..12];
         Ok(self
             .config
@@ -57,3 +54,31 @@ impl<C: ODConfig> FileVariableDao<C> {
     }
 
 }
+
+impl<C: ODConfig> VariableDao<C> for ProtoVariableDao<C> {
+    fn persist_variables(&self, project_id: &str, vars: &VariableStore) -> Result<()> {
+        write_protobuf::<proto::VariableStore, VariableStore>(&self.get_var_file_name(project_id)?, vars)
+            .context("could not encode variables to write into file")
+    }
+
+    fn load_variables(&self, project_id: &str) -> Result<VariableStore> {
+        read_protobuf::<proto::VariableStore, VariableStore>(&self.get_var_file_name(project_id)?)
+            .context("could not decode file into variables.")
+    }
+}
+
+impl<C: ODConfig> ProtoVariableDao<C> {
+    pub fn new(config: Shared<C>) -> Self {
+        Self { config }
+    }
+
+    pub fn get_var_file_name(&self, project_id: &str) -> Result<PathBuf> {
+        let project_path = &Uuid::from_str(project_id)?.simple().to_string()[..12];
+        Ok(self
+            .config
+            .lock()?
+            .get_root_dir()
+            .join(project_path)
+            .join("vars.pb"))
+    }
+}
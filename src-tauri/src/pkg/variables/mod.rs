@@ -1,6 +1,8 @@
 use od_macros::tauri_command;
 
-use crate::shared::types::variables::VariableStore;
+use uuid::Uuid;
+
+use crate::shared::types::variables::{StateRename, VariableStore, VariableUsage, VariableUsages};
 
 pub mod dao;
 pub mod service;
@@ -8,6 +10,20 @@ pub mod service;
 #[tauri_command(var_service)]
 pub fn load_variables(project_id: &str) -> VariableStore {}
 #[tauri_command(var_service)]
-pub fn persist_variables(project_id: &str, vars: VariableStore)  {}
+pub fn persist_variables(
+    project_id: &str,
+    vars: VariableStore,
+    renames: Option<Vec<StateRename>>,
+) {
+}
+#[tauri_command(var_service)]
+pub fn check_variable_states(
+    project_id: &str,
+    vars: VariableStore,
+    renames: Option<Vec<StateRename>>,
+) -> Vec<VariableUsage> {
+}
 #[tauri_command(var_service)]
 pub fn delete_variable(project_id: &str, var_id: &str) {}
+#[tauri_command(var_service)]
+pub fn get_variable_usages(project_id: &str, variable_id: Option<Uuid>) -> Vec<VariableUsages> {}
@@ -1,16 +1,25 @@
 use std::{str::FromStr, sync::Arc};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use uuid::Uuid;
 
 use crate::{
     pkg::{
-        character::dao::CharacterDao, dialog::dao::DialogDao, meta::dao::MetaDao,
+        character::dao::CharacterDao,
+        dialog::{dao::DialogDao, service::DialogServiceLocalImpl},
+        gitter::service::GitService,
+        meta::dao::MetaDao,
+        search::index::SearchIndex,
         variables::dao::VariableDao,
     },
     shared::{
         config::ODConfig,
-        types::{interfaces::Shared, variables::VariableStore},
+        types::{
+            character::CharacterMetadata,
+            interfaces::{Identified, Shared},
+            dialog::Dialog,
+            variables::{StateRename, VariableStore, VariableUsage, VariableUsages},
+        },
     },
 };
 
@@ -26,6 +35,9 @@ pub struct VariableServiceLocalImpl<
     char_dao: Arc<CD>,
     dialog_dao: Arc<DD>,
     meta_dao: Arc<MD>,
+    dialog_srv: Arc<DialogServiceLocalImpl<C, DD, CD, MD>>,
+    search_index: Arc<SearchIndex>,
+    git_srv: GitService<C>,
 }
 
 impl<C: ODConfig, VD: VariableDao<C>, CD: CharacterDao<C>, DD: DialogDao<C>, MD: MetaDao<C>>
@@ -37,6 +49,9 @@ impl<C: ODConfig, VD: VariableDao<C>, CD: CharacterDao<C>, DD: DialogDao<C>, MD:
         char_dao: Arc<CD>,
         dialog_dao: Arc<DD>,
         meta_dao: Arc<MD>,
+        dialog_srv: Arc<DialogServiceLocalImpl<C, DD, CD, MD>>,
+        search_index: Arc<SearchIndex>,
+        git_srv: GitService<C>,
     ) -> Self {
         VariableServiceLocalImpl {
             char_dao,
@@ -44,29 +59,191 @@ impl<C: ODConfig, VD: VariableDao<C>, CD: CharacterDao<C>, DD: DialogDao<C>, MD:
             config,
             var_dao,
             meta_dao,
+            dialog_srv,
+            search_index,
+            git_srv,
         }
     }
 
-    pub fn persist_variables(&self, project_id: &str, vars: VariableStore) -> Result<()> {
+    /// Saves the variables. States removed from a variable must not be needed
+    /// by any condition, unless they are renamed : the renames are then
+    /// applied to every dialog, which are restored if the save fails.
+    pub fn persist_variables(
+        &self,
+        project_id: &str,
+        mut vars: VariableStore,
+        renames: Option<Vec<StateRename>>,
+    ) -> Result<()> {
+        let char_metadata = self.char_dao.get_meta_file(project_id)?;
+        vars.sync_group_targets(&char_metadata.get_group_members())?;
         let char_ids = self.char_dao.get_character_identifiers(project_id)?;
         let dialog_ids = self.dialog_dao.get_dialog_identifiers(project_id)?;
         vars.enforce_coherence(dialog_ids, char_ids)?;
-        self.var_dao.persist_variables(project_id, &vars)?;
+        let previous = self.var_dao.load_variables(project_id)?;
+        for var in previous
+            .data
+            .iter()
+            .filter(|v| !vars.is_global_character_variable(v.get_id()))
+        {
+            ensure_not_strength_variable(&char_metadata, var.get_id())?;
+        }
+        let renames = renames.unwrap_or_default();
+        let (dialogs, broken) = self.plan_state_changes(project_id, &mut vars, &renames)?;
+        if !broken.is_empty() {
+            bail!("removed states are still needed by {}", describe_usages(&broken))
+        }
+
+        let mut originals: Vec<Dialog> = vec![];
+        let mut saved = Ok(());
+        for mut dialog in dialogs {
+            if dialog.rename_variable_states(&renames) == 0 {
+                continue;
+            }
+            saved = self
+                .dialog_srv
+                .get_dialog_by_id(project_id, dialog.get_id())
+                .and_then(|original| {
+                    originals.push(original);
+                    self.dialog_srv.save_dialog(project_id, dialog)
+                });
+            if saved.is_err() {
+                break;
+            }
+        }
+        let saved = saved.and_then(|_| self.var_dao.persist_variables(project_id, &vars));
+        if let Err(e) = saved {
+            return Err(self.dialog_srv.restore_dialogs(project_id, originals, e));
+        }
+        self.search_index.mark_variables_stale(project_id)?;
         let mut var_to_phylum = self.meta_dao.get_var_to_phylum_map(project_id)?;
         var_to_phylum.fill_non_existing_keys(vars);
-        self.meta_dao.save_var_to_phylum(project_id, var_to_phylum)
+        self.meta_dao.save_var_to_phylum(project_id, var_to_phylum)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
+    }
+
+    /// Lists the conditions which would need a state removed from the variables,
+    /// once the renames are applied. Saving the variables is refused while any remains.
+    pub fn check_variable_states(
+        &self,
+        project_id: &str,
+        mut vars: VariableStore,
+        renames: Option<Vec<StateRename>>,
+    ) -> Result<Vec<VariableUsage>> {
+        let renames = renames.unwrap_or_default();
+        let (_, broken) = self.plan_state_changes(project_id, &mut vars, &renames)?;
+        Ok(broken)
+    }
+
+    /// Applies the renames to the current states of the variables, and finds the
+    /// conditions needing a removed state which is not renamed.
+    /// Returns the dialogs of the project along with these conditions.
+    fn plan_state_changes(
+        &self,
+        project_id: &str,
+        vars: &mut VariableStore,
+        renames: &[StateRename],
+    ) -> Result<(Vec<Dialog>, Vec<VariableUsage>)> {
+        let removed = self
+            .var_dao
+            .load_variables(project_id)?
+            .get_removed_states(vars);
+        vars.apply_state_renames(renames, &removed)?;
+        let mut dialogs = vec![];
+        for dialog_id in self.dialog_dao.get_dialog_identifiers(project_id)? {
+            dialogs.push(self.dialog_dao.get_dialog_by_id(project_id, &dialog_id)?);
+        }
+        let broken = dialogs
+            .iter()
+            .flat_map(|d| d.get_variable_usages())
+            .filter(|u| {
+                removed
+                    .get(&u.variable_id)
+                    .is_some_and(|r| r.contains(&u.necessary_state))
+                    && !renames.iter().any(|r| {
+                        r.variable_id == u.variable_id && r.from == u.necessary_state
+                    })
+            })
+            .collect();
+        Ok((dialogs, broken))
     }
 
     pub fn delete_variable(&self, project_id: &str, var_id: &str) -> Result<()> {
         let id = Uuid::from_str(var_id)?;
         let var_to_phylum = self.meta_dao.get_var_to_phylum_map(project_id)?;
         var_to_phylum.enforce_free_existing_var(&id)?;
+        ensure_not_strength_variable(&self.char_dao.get_meta_file(project_id)?, &id)?;
         let mut vars = self.var_dao.load_variables(project_id)?;
         vars.delete_var_with_id(&id);
-        self.var_dao.persist_variables(project_id, &vars)
+        self.var_dao.persist_variables(project_id, &vars)?;
+        self.search_index.mark_variables_stale(project_id)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
+    }
+
+    /// Lists, for each variable or only the given one, the conditions
+    /// of dialogs reading it. Dialogs are sorted by name.
+    pub fn get_variable_usages(
+        &self,
+        project_id: &str,
+        variable_id: Option<Uuid>,
+    ) -> Result<Vec<VariableUsages>> {
+        let vars = self.var_dao.load_variables(project_id)?;
+        let mut dialogs = vec![];
+        for dialog_id in self.dialog_dao.get_dialog_identifiers(project_id)? {
+            dialogs.push(self.dialog_dao.get_dialog_by_id(project_id, &dialog_id)?);
+        }
+        dialogs.sort_by(|a, b| a.get_name().cmp(b.get_name()).then(a.get_id().cmp(&b.get_id())));
+        let mut usages: Vec<VariableUsage> = dialogs
+            .iter()
+            .flat_map(|d| d.get_variable_usages())
+            .collect();
+
+        let mut res = vec![];
+        for var in &vars.data {
+            if variable_id.is_some_and(|id| id != *var.get_id()) {
+                continue;
+            }
+            let (own, others): (Vec<VariableUsage>, Vec<VariableUsage>) = usages
+                .into_iter()
+                .partition(|u| u.variable_id == *var.get_id());
+            usages = others;
+            res.push(VariableUsages {
+                variable_id: *var.get_id(),
+                variable_name: var.get_name().clone(),
+                usages: own,
+            });
+        }
+        Ok(res)
     }
 
     pub fn load_variables(&self, project_id: &str) -> Result<VariableStore> {
         self.var_dao.load_variables(project_id)
     }
 }
+
+/// Relationship strengths read the variable, which must stay a global character variable.
+fn ensure_not_strength_variable(char_metadata: &CharacterMetadata, var_id: &Uuid) -> Result<()> {
+    if char_metadata.get_strength_variables().contains(var_id) {
+        bail!("variable {var_id} backs the strength of character relationships")
+    }
+    Ok(())
+}
+
+/// Lists the first conditions for an error message.
+fn describe_usages(usages: &[VariableUsage]) -> String {
+    let mut res: Vec<String> = usages
+        .iter()
+        .take(5)
+        .map(|u| {
+            format!(
+                "condition {} of dialog {} ({})",
+                u.condition_name, u.dialog_name, u.necessary_state
+            )
+        })
+        .collect();
+    if usages.len() > 5 {
+        res.push(format!("{} others", usages.len() - 5));
+    }
+    res.join(", ")
+}
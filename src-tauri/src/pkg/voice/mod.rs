@@ -0,0 +1,25 @@
+use od_macros::tauri_command;
+use uuid::Uuid;
+
+use crate::shared::types::voice::{MissingAudio, VoiceOverTable};
+
+pub mod service;
+
+#[tauri_command(voice_service)]
+pub fn get_dialog_audio(project_id: &str, dialog_id: Uuid) -> VoiceOverTable {}
+
+#[tauri_command(voice_service)]
+pub fn attach_line_audio(
+    project_id: &str,
+    dialog_id: Uuid,
+    node_id: Uuid,
+    locale: &str,
+    path: &str,
+) -> String {
+}
+
+#[tauri_command(voice_service)]
+pub fn detach_line_audio(project_id: &str, dialog_id: Uuid, node_id: Uuid, locale: &str) {}
+
+#[tauri_command(voice_service)]
+pub fn get_missing_audio(project_id: &str, locale: Option<String>) -> Vec<MissingAudio> {}
@@ -0,0 +1,173 @@
+use std::{fs, sync::Arc};
+
+use anyhow::{anyhow, Context, Result};
+use uuid::Uuid;
+
+use crate::{
+    pkg::{
+        character::dao::CharacterDao,
+        dialog::{dao::DialogDao, service::DialogServiceLocalImpl},
+        gitter::service::GitService,
+        localization::dao::LocalizationDao,
+        meta::dao::MetaDao,
+    },
+    shared::{
+        config::{ODConfig, ASSETS_DIRNAME},
+        types::{
+            interfaces::{Shared, Uploader},
+            voice::{MissingAudio, VoiceOverTable},
+        },
+    },
+};
+
+pub struct VoiceServiceLocalImpl<
+    C: ODConfig,
+    LD: LocalizationDao<C>,
+    DD: DialogDao<C>,
+    CD: CharacterDao<C>,
+    MD: MetaDao<C>,
+> {
+    config: Shared<C>,
+    loc_dao: Arc<LD>,
+    dialog_dao: Arc<DD>,
+    dialog_srv: Arc<DialogServiceLocalImpl<C, DD, CD, MD>>,
+    uploader: Arc<dyn Uploader>,
+    git_srv: GitService<C>,
+}
+
+impl<
+        C: ODConfig,
+        LD: LocalizationDao<C>,
+        DD: DialogDao<C>,
+        CD: CharacterDao<C>,
+        MD: MetaDao<C>,
+    > VoiceServiceLocalImpl<C, LD, DD, CD, MD>
+{
+    pub fn new(
+        config: Shared<C>,
+        loc_dao: Arc<LD>,
+        dialog_dao: Arc<DD>,
+        dialog_srv: Arc<DialogServiceLocalImpl<C, DD, CD, MD>>,
+        uploader: Arc<dyn Uploader>,
+        git_srv: GitService<C>,
+    ) -> Self {
+        Self {
+            config,
+            loc_dao,
+            dialog_dao,
+            dialog_srv,
+            uploader,
+            git_srv,
+        }
+    }
+
+    pub fn get_dialog_audio(&self, project_id: &str, dialog_id: Uuid) -> Result<VoiceOverTable> {
+        self.dialog_dao.get_voice_over(project_id, &dialog_id)
+    }
+
+    /// Copies the audio file into the assets of the project and links it
+    /// to the line for the given locale, replacing the previous file if any.
+    /// Returns the name of the file in the assets.
+    pub fn attach_line_audio(
+        &self,
+        project_id: &str,
+        dialog_id: Uuid,
+        node_id: Uuid,
+        locale: &str,
+        path: &str,
+    ) -> Result<String> {
+        self.loc_dao
+            .get_locale_settings(project_id)?
+            .get_locale(locale)?;
+        let dialog = self.dialog_dao.get_dialog_by_id(project_id, &dialog_id)?;
+        dialog.get_line_content(&node_id, None)?;
+        let project_path = self.config.lock()?.get_project_dir(project_id)?;
+        let name = self.uploader.updload(path, &project_path)?;
+        let mut table = self.dialog_dao.get_voice_over(project_id, &dialog_id)?;
+        let replaced = table.set_audio(node_id, locale, name.clone());
+        self.dialog_dao
+            .persist_voice_over(project_id, &dialog_id, &table)?;
+        if let Some(replaced) = replaced {
+            self.delete_asset(project_id, &replaced)?;
+        }
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(name)
+    }
+
+    pub fn detach_line_audio(
+        &self,
+        project_id: &str,
+        dialog_id: Uuid,
+        node_id: Uuid,
+        locale: &str,
+    ) -> Result<()> {
+        let mut table = self.dialog_dao.get_voice_over(project_id, &dialog_id)?;
+        let removed = table
+            .remove_audio(&node_id, locale)
+            .ok_or(anyhow!("line {node_id} has no audio in {locale}"))?;
+        self.dialog_dao
+            .persist_voice_over(project_id, &dialog_id, &table)?;
+        self.delete_asset(project_id, &removed)?;
+        self.git_srv.snapshot_after_save(project_id);
+        Ok(())
+    }
+
+    /// Lists the dialog lines that have no audio file, for the given
+    /// locale or for every locale of the project.
+    pub fn get_missing_audio(
+        &self,
+        project_id: &str,
+        locale: Option<String>,
+    ) -> Result<Vec<MissingAudio>> {
+        let settings = self.loc_dao.get_locale_settings(project_id)?;
+        let locales: Vec<String> = match locale {
+            Some(l) => vec![settings.get_locale(&l)?.get_code().clone()],
+            None => settings
+                .get_locales()
+                .iter()
+                .map(|l| l.get_code().clone())
+                .collect(),
+        };
+        let metadata = self.dialog_srv.get_dialog_metadata(project_id)?;
+        let mut simple_dialogs: Vec<_> = metadata.data.values().collect();
+        simple_dialogs.sort_by_key(|d| d.get_order());
+        let mut res = vec![];
+        for simple_dialog in simple_dialogs {
+            let dialog_id = *simple_dialog.get_id();
+            let dialog = self.dialog_srv.get_dialog_by_id(project_id, dialog_id)?;
+            let table = self.dialog_dao.get_voice_over(project_id, &dialog_id)?;
+            for line in dialog.get_lines() {
+                let text = match (line.choice_id, line.content) {
+                    (None, Some(c)) if !c.is_empty() => c,
+                    _ => continue,
+                };
+                for locale in &locales {
+                    if table.get_audio(line.node_id, locale).is_none() {
+                        res.push(MissingAudio {
+                            dialog_id,
+                            dialog_name: dialog.get_name().clone(),
+                            node_id: *line.node_id,
+                            line_id: line.line_id.map(String::from),
+                            locale: locale.clone(),
+                            text: String::from(text),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    fn delete_asset(&self, project_id: &str, name: &str) -> Result<()> {
+        let path = self
+            .config
+            .lock()?
+            .get_project_dir(project_id)?
+            .join(ASSETS_DIRNAME)
+            .join(name);
+        if path.is_file() {
+            fs::remove_file(path).context("could not delete audio file")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,10 @@
+use od_macros::tauri_command;
+use tauri::AppHandle;
+
+pub mod service;
+
+#[tauri_command(watcher_service)]
+pub fn watch_project(app: AppHandle, project_id: &str) {}
+
+#[tauri_command(watcher_service)]
+pub fn unwatch_project(project_id: &str) {}
@@ -0,0 +1,280 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+    sync::{mpsc, Arc, Mutex, Weak},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+use crate::{
+    pkg::{
+        character::dao::CharacterDao,
+        dialog::{dao::DialogDao, service::DialogServiceLocalImpl},
+        meta::dao::MetaDao,
+        search::index::SearchIndex,
+        variables::dao::VariableDao,
+    },
+    shared::{
+        config::{ODConfig, CHAR_DIRNAME, DIALOG_DIRNAME, META_DIRNAME},
+        own_writes,
+        types::{
+            interfaces::Shared,
+            watcher::{ChangeTarget, ProjectFileChange},
+        },
+    },
+};
+
+/// Name of the event emitted for each change made outside of the application.
+pub const PROJECT_FILE_CHANGED: &str = "project-file-changed";
+/// Time without new file events before a batch of changes is handled,
+/// so that a `git pull` touching many files results in one reload per entity.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// File of the project, as identified from its path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum WatchedFile {
+    Dialog(Uuid),
+    DialogMetadata,
+    Character(Uuid),
+    Description(Uuid),
+    CharacterMetadata,
+    Variables,
+    Meta,
+}
+
+pub struct WatcherServiceLocalImpl<
+    C: ODConfig,
+    DD: DialogDao<C>,
+    CD: CharacterDao<C>,
+    MD: MetaDao<C>,
+    VD: VariableDao<C>,
+> {
+    config: Shared<C>,
+    dialog_srv: Arc<DialogServiceLocalImpl<C, DD, CD, MD>>,
+    dialog_dao: Arc<DD>,
+    char_dao: Arc<CD>,
+    var_dao: Arc<VD>,
+    search_index: Arc<SearchIndex>,
+    watchers: Mutex<HashMap<String, RecommendedWatcher>>,
+}
+
+impl<C, DD, CD, MD, VD> WatcherServiceLocalImpl<C, DD, CD, MD, VD>
+where
+    C: ODConfig,
+    DD: DialogDao<C> + Send + Sync + 'static,
+    CD: CharacterDao<C> + Send + Sync + 'static,
+    MD: MetaDao<C> + Send + Sync + 'static,
+    VD: VariableDao<C> + Send + Sync + 'static,
+{
+    pub fn new(
+        config: Shared<C>,
+        dialog_srv: Arc<DialogServiceLocalImpl<C, DD, CD, MD>>,
+        dialog_dao: Arc<DD>,
+        char_dao: Arc<CD>,
+        var_dao: Arc<VD>,
+        search_index: Arc<SearchIndex>,
+    ) -> Self {
+        Self {
+            config,
+            dialog_srv,
+            dialog_dao,
+            char_dao,
+            var_dao,
+            search_index,
+            watchers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Watches the files of the project until `unwatch_project` is called.
+    /// Watching a project again replaces its previous watcher.
+    pub fn watch_project(self: &Arc<Self>, app: AppHandle, project_id: &str) -> Result<()> {
+        let project_dir = self.config.lock()?.get_project_dir(project_id)?;
+        let (sender, receiver) = mpsc::channel::<Event>();
+        let mut watcher =
+            notify::recommended_watcher(move |res: notify::Result<Event>| match res {
+                Ok(event) => {
+                    let _ = sender.send(event);
+                }
+                Err(e) => log::error!("error while watching project files: {e}"),
+            })?;
+        watcher.watch(&project_dir, RecursiveMode::Recursive)?;
+
+        let srv = Arc::downgrade(self);
+        let id = String::from(project_id);
+        thread::spawn(move || handle_events(srv, app, id, project_dir, receiver));
+
+        self.watchers
+            .lock()
+            .map_err(|e| anyhow!("lock error: {e}"))?
+            .insert(String::from(project_id), watcher);
+        Ok(())
+    }
+
+    /// Dropping the watcher closes its channel, which ends its thread.
+    pub fn unwatch_project(&self, project_id: &str) -> Result<()> {
+        self.watchers
+            .lock()
+            .map_err(|e| anyhow!("lock error: {e}"))?
+            .remove(project_id);
+        Ok(())
+    }
+
+    /// Reloads the data behind a changed file and checks it is still valid.
+    fn reload(&self, project_id: &str, file: WatchedFile) -> Result<ProjectFileChange> {
+        let (target, target_id) = match file {
+            WatchedFile::Dialog(id) => (ChangeTarget::Dialog, Some(id)),
+            WatchedFile::DialogMetadata => (ChangeTarget::DialogMetadata, None),
+            WatchedFile::Character(id) => (ChangeTarget::Character, Some(id)),
+            WatchedFile::Description(id) => (
+                ChangeTarget::Character,
+                self.find_described_character(project_id, &id)?,
+            ),
+            WatchedFile::CharacterMetadata => (ChangeTarget::CharacterMetadata, None),
+            WatchedFile::Variables => (ChangeTarget::Variables, None),
+            WatchedFile::Meta => (ChangeTarget::Meta, None),
+        };
+        let mut change = ProjectFileChange {
+            project_id: String::from(project_id),
+            target,
+            target_id,
+            removed: false,
+            error: None,
+        };
+        let loaded = match (target, target_id) {
+            (ChangeTarget::Dialog, Some(id)) => {
+                self.search_index.mark_dialog_stale(project_id, &id)?;
+                change.removed = !self
+                    .dialog_dao
+                    .get_dialog_identifiers(project_id)?
+                    .contains(&id);
+                if change.removed {
+                    Ok(())
+                } else {
+                    self.dialog_srv.validate_dialog(project_id, id).map(|_| ())
+                }
+            }
+            (ChangeTarget::Character, Some(id)) => {
+                self.search_index.mark_character_stale(project_id, &id)?;
+                change.removed = !self
+                    .char_dao
+                    .get_character_identifiers(project_id)?
+                    .contains(&id);
+                if change.removed {
+                    Ok(())
+                } else {
+                    self.char_dao.get_character(project_id, &id).map(|_| ())
+                }
+            }
+            (ChangeTarget::DialogMetadata, _) => {
+                self.dialog_dao.get_metadata(project_id).map(|_| ())
+            }
+            (ChangeTarget::CharacterMetadata, _) => {
+                self.char_dao.get_meta_file(project_id).map(|_| ())
+            }
+            (ChangeTarget::Variables, _) => {
+                self.search_index.mark_variables_stale(project_id)?;
+                self.var_dao.load_variables(project_id).map(|_| ())
+            }
+            _ => Ok(()),
+        };
+        change.error = loaded.err().map(|e| format!("{e:#}"));
+        Ok(change)
+    }
+
+    /// Descriptions are stored apart from the characters and only linked by them.
+    fn find_described_character(&self, project_id: &str, desc_id: &Uuid) -> Result<Option<Uuid>> {
+        for char_id in self.char_dao.get_character_identifiers(project_id)? {
+            if let Ok(character) = self.char_dao.get_character(project_id, &char_id) {
+                if character.get_description_link().as_ref() == Some(desc_id) {
+                    return Ok(Some(char_id));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Collects file events until they settle, then reloads each entity changed
+/// outside of the application once and emits the result.
+/// Ends when the watcher is dropped.
+fn handle_events<C, DD, CD, MD, VD>(
+    srv: Weak<WatcherServiceLocalImpl<C, DD, CD, MD, VD>>,
+    app: AppHandle,
+    project_id: String,
+    project_dir: PathBuf,
+    receiver: mpsc::Receiver<Event>,
+) where
+    C: ODConfig,
+    DD: DialogDao<C> + Send + Sync + 'static,
+    CD: CharacterDao<C> + Send + Sync + 'static,
+    MD: MetaDao<C> + Send + Sync + 'static,
+    VD: VariableDao<C> + Send + Sync + 'static,
+{
+    while let Ok(first) = receiver.recv() {
+        let mut paths: HashSet<PathBuf> = HashSet::new();
+        let mut event = Some(first);
+        while let Some(e) = event {
+            if !matches!(e.kind, EventKind::Access(_)) {
+                paths.extend(e.paths);
+            }
+            event = receiver.recv_timeout(DEBOUNCE).ok();
+        }
+        // Checked once the files settled, so that a write of the application
+        // is not mistaken for an outside change while it is still in progress.
+        let changed: HashSet<WatchedFile> = paths
+            .iter()
+            .filter(|p| !own_writes::is_own_change(p))
+            .filter_map(|p| classify(&project_dir, p))
+            .collect();
+        let srv = match srv.upgrade() {
+            Some(s) => s,
+            None => return,
+        };
+        for file in changed {
+            match srv.reload(&project_id, file) {
+                Ok(change) => {
+                    if let Err(e) = app.emit(PROJECT_FILE_CHANGED, change) {
+                        log::error!("could not emit project file change: {e}");
+                    }
+                }
+                Err(e) => log::error!("could not reload changed project file: {e}"),
+            }
+        }
+    }
+}
+
+/// Finds which data of the project a file holds, from its path.
+/// Files of the repository and unknown files are ignored.
+fn classify(project_dir: &Path, path: &Path) -> Option<WatchedFile> {
+    let relative = path.strip_prefix(project_dir).ok()?;
+    let parts: Vec<&str> = relative
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(s) => s.to_str(),
+            _ => None,
+        })
+        .collect();
+    match parts.as_slice() {
+        ["vars.json" | "vars.pb"] => Some(WatchedFile::Variables),
+        [dir, "meta.json"] if *dir == DIALOG_DIRNAME => Some(WatchedFile::DialogMetadata),
+        [dir, id, ..] if *dir == DIALOG_DIRNAME => Uuid::from_str(id).ok().map(WatchedFile::Dialog),
+        [dir, "meta.json"] if *dir == CHAR_DIRNAME => Some(WatchedFile::CharacterMetadata),
+        [dir, file] if *dir == CHAR_DIRNAME => {
+            let (stem, ext) = file.rsplit_once('.')?;
+            let id = Uuid::from_str(stem).ok()?;
+            match ext {
+                "char" | "pb" => Some(WatchedFile::Character(id)),
+                "desc" => Some(WatchedFile::Description(id)),
+                _ => None,
+            }
+        }
+        [dir, ..] if *dir == META_DIRNAME => Some(WatchedFile::Meta),
+        _ => None,
+    }
+}
@@ -7,7 +7,7 @@ use std::{
 };
 
 use crate::shared::types::project::{AtomicProject, AtomicProjects, Project};
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use directories::ProjectDirs;
 use email_address::EmailAddress;
 use serde::{Deserialize, Serialize};
@@ -19,11 +19,17 @@ pub const DIALOG_DIRNAME: &str = "dialog";
 pub const STATS_DIRNAME: &str = "stats";
 pub const META_DIRNAME: &str = "meta";
 pub const ASSETS_DIRNAME: &str = "assets";
+pub const LOCALE_DIRNAME: &str = "locale";
 
 pub const VARS: &str = "vars.json";
 pub const META_FK_VARS_DIALOGS: &str = "meta/vars_dialogs.json";
+pub const META_LINE_IDS: &str = "meta/line_ids.json";
 pub const DIALOG_META: &str = "dialog/meta.json";
 pub const CHARACTER_META: &str = "character/meta.json";
+pub const LOCALES: &str = "locales.json";
+pub const FORMAT: &str = "format.json";
+pub const BACKUP_DIRNAME: &str = "backups";
+pub const DATABASE_FILE: &str = "open_dialog.db";
 
 #[async_trait::async_trait]
 pub trait ODConfig: Sized + Clone + Send + Sync + 'static {
@@ -32,8 +38,11 @@ pub trait ODConfig: Sized + Clone + Send + Sync + 'static {
     fn append_project(&mut self, project: Project) -> Result<AtomicProject>;
     fn get_projects(&self) -> AtomicProjects;
     fn get_project_dir(&self, project_id: &str) -> Result<PathBuf>;
+    fn get_project(&self, project_id: &str) -> Result<AtomicProject>;
     fn set_user(&mut self, name: &str);
     fn set_email(&mut self, email: &str) -> Result<()>;
+    fn get_user(&self) -> &String;
+    fn get_email(&self) -> Option<&String>;
     async fn save_async(&self) -> Result<()>;
 }
 
@@ -91,6 +100,14 @@ impl ODConfig for ODConfigLocal {
         self.user = String::from(name);
     }
 
+    fn get_user(&self) -> &String {
+        &self.user
+    }
+
+    fn get_email(&self) -> Option<&String> {
+        self.email.as_ref()
+    }
+
     fn set_email(&mut self, email: &str) -> Result<()> {
         if !EmailAddress::is_valid(email) {
             bail!("invalid email adress")
@@ -117,6 +134,22 @@ impl ODConfig for ODConfigLocal {
         Ok(self.get_root_dir().join(res))
     }
 
+    fn get_project(&self, project_id: &str) -> Result<AtomicProject> {
+        let id = Uuid::from_str(project_id)?;
+        self.projects
+            .read()
+            .expect("read lock could not be acquired")
+            .iter()
+            .find(|p| {
+                p.read()
+                    .expect("read lock could not be acquired")
+                    .get_id()
+                    == &id
+            })
+            .cloned()
+            .ok_or(anyhow!("project {project_id} does not exist"))
+    }
+
     async fn save_async(&self) -> Result<()> {
         let file =
             File::create(self.root_dir.join(CONFIG_FILE_PATH)).context("failed to open config file")?;
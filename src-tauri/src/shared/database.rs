@@ -0,0 +1,129 @@
+use std::{path::PathBuf, sync::Mutex};
+
+use anyhow::{anyhow, Context, Result};
+use rusqlite::{types::Type, Connection, Row};
+use uuid::Uuid;
+
+/// Migrations of the schema of the local database, applied in order.
+/// The number of applied migrations is stored in the `user_version` pragma,
+/// so a migration must never be modified once released : add a new one instead.
+const MIGRATIONS: &[&str] = &[
+    // 1 : comments, user profiles, recent projects and UI state.
+    "CREATE TABLE comments (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        target TEXT NOT NULL,
+        target_id TEXT NOT NULL,
+        dialog_id TEXT,
+        parent_id TEXT REFERENCES comments(id) ON DELETE CASCADE,
+        author TEXT NOT NULL,
+        email TEXT,
+        content TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT,
+        resolved_at TEXT,
+        resolved_by TEXT
+    );
+    CREATE INDEX comments_project ON comments(project_id, dialog_id);
+    CREATE INDEX comments_target ON comments(target_id);
+    CREATE TABLE user_profiles (
+        name TEXT PRIMARY KEY,
+        email TEXT,
+        last_seen_at TEXT NOT NULL
+    );
+    CREATE TABLE recent_projects (
+        user TEXT NOT NULL,
+        project_id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        opened_at TEXT NOT NULL,
+        PRIMARY KEY (user, project_id)
+    );
+    CREATE TABLE ui_state (
+        user TEXT NOT NULL,
+        project_id TEXT NOT NULL,
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        PRIMARY KEY (user, project_id, key)
+    );",
+    // 2 : undo history of dialogs.
+    "CREATE TABLE dialog_versions (
+        project_id TEXT NOT NULL,
+        dialog_id TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        content TEXT NOT NULL,
+        saved_at TEXT NOT NULL,
+        PRIMARY KEY (project_id, dialog_id, position)
+    );
+    CREATE TABLE dialog_history (
+        project_id TEXT NOT NULL,
+        dialog_id TEXT NOT NULL,
+        cursor INTEGER NOT NULL,
+        PRIMARY KEY (project_id, dialog_id)
+    );",
+];
+
+/// Local SQLite database holding what is not versioned with the projects :
+/// comments, user profiles, recent projects, UI state and undo history of dialogs.
+/// The file is created and migrated the first time it is used.
+pub struct Database {
+    path: PathBuf,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl Database {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            connection: Mutex::new(None),
+        }
+    }
+
+    pub fn with_connection<T>(&self, f: impl FnOnce(&mut Connection) -> Result<T>) -> Result<T> {
+        let mut guard = self
+            .connection
+            .lock()
+            .map_err(|e| anyhow!("lock error: {e}"))?;
+        let connection = match guard.as_mut() {
+            Some(c) => c,
+            None => guard.insert(self.open()?),
+        };
+        f(connection)
+    }
+
+    fn open(&self) -> Result<Connection> {
+        let mut connection = Connection::open(&self.path)
+            .with_context(|| format!("could not open database {}", self.path.display()))?;
+        connection.pragma_update(None, "foreign_keys", true)?;
+        migrate(&mut connection).context("could not migrate database")?;
+        Ok(connection)
+    }
+}
+
+fn migrate(connection: &mut Connection) -> Result<()> {
+    let version: u32 = connection.pragma_query_value(None, "user_version", |r| r.get(0))?;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let tx = connection.transaction()?;
+        tx.execute_batch(migration)
+            .with_context(|| format!("migration {} failed", i + 1))?;
+        tx.pragma_update(None, "user_version", i as u32 + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Reads an uuid stored as text.
+pub fn get_uuid(row: &Row, idx: usize) -> rusqlite::Result<Uuid> {
+    let text: String = row.get(idx)?;
+    Uuid::parse_str(&text)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, Type::Text, Box::new(e)))
+}
+
+pub fn get_opt_uuid(row: &Row, idx: usize) -> rusqlite::Result<Option<Uuid>> {
+    let text: Option<String> = row.get(idx)?;
+    text.map(|t| {
+        Uuid::parse_str(&t)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, Type::Text, Box::new(e)))
+    })
+    .transpose()
+}
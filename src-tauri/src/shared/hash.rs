@@ -0,0 +1,7 @@
+use sha2::{Digest, Sha256};
+
+/// Hash of a piece of text written by the user, used to detect
+/// whether it changed since it was last exported or translated.
+pub fn content_hash(content: &str) -> String {
+    hex::encode(Sha256::digest(content.as_bytes()))
+}
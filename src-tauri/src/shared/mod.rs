@@ -1,4 +1,7 @@
 pub mod config;
+pub mod database;
+pub mod hash;
 pub mod models;
+pub mod own_writes;
 pub mod state;
 pub mod types;
@@ -0,0 +1,106 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use sha2::{Digest, Sha256};
+
+/// How long a change made by the application is remembered.
+/// Events reach the project watcher well within this time.
+const RETENTION: Duration = Duration::from_secs(5);
+
+/// State a path was left in by the application.
+enum Written {
+    Content(Vec<u8>),
+    Directory,
+    Removed,
+}
+
+static WRITES: LazyLock<Mutex<HashMap<PathBuf, (Instant, Written)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Writes a file of a project and remembers it was written by the application.
+pub fn write(path: &Path, content: impl AsRef<[u8]>) -> io::Result<()> {
+    let content = content.as_ref();
+    record(path, Written::Content(Sha256::digest(content).to_vec()));
+    fs::write(path, content)
+}
+
+pub fn create_dir(path: &Path) -> io::Result<()> {
+    record(path, Written::Directory);
+    fs::create_dir(path)
+}
+
+pub fn remove_file(path: &Path) -> io::Result<()> {
+    record(path, Written::Removed);
+    fs::remove_file(path)
+}
+
+pub fn remove_dir_all(path: &Path) -> io::Result<()> {
+    record(path, Written::Removed);
+    fs::remove_dir_all(path)
+}
+
+/// Whether the path is still in the state the application recently left it in,
+/// meaning an event about it was caused by the application itself.
+/// A file changed again by another program since then is not an own change.
+pub fn is_own_change(path: &Path) -> bool {
+    let writes = match WRITES.lock() {
+        Ok(w) => w,
+        Err(_) => return false,
+    };
+    let path = normalize(path);
+    let recent = |p: &Path| {
+        writes
+            .get(p)
+            .filter(|(at, _)| at.elapsed() < RETENTION)
+            .map(|(_, written)| written)
+    };
+    if let Some(written) = recent(&path) {
+        return match written {
+            Written::Content(hash) => {
+                fs::read(&path).is_ok_and(|c| Sha256::digest(&c).as_slice() == hash.as_slice())
+            }
+            Written::Directory => path.is_dir(),
+            Written::Removed => !path.exists(),
+        };
+    }
+    // Files inside a removed directory are reported one by one.
+    !path.exists()
+        && path
+            .ancestors()
+            .any(|a| matches!(recent(a), Some(Written::Removed)))
+}
+
+/// Changes are recorded before they are made, so that the watcher
+/// cannot receive an event before knowing where it comes from.
+fn record(path: &Path, written: Written) {
+    if let Ok(mut writes) = WRITES.lock() {
+        let now = Instant::now();
+        writes.retain(|_, (at, _)| now.duration_since(*at) < RETENTION);
+        writes.insert(normalize(path), (now, written));
+    }
+}
+
+/// Watchers report resolved paths, which may differ from the configured ones
+/// when the project lives behind a symlink. Paths that do not exist (anymore)
+/// are resolved through their closest existing ancestor.
+fn normalize(path: &Path) -> PathBuf {
+    let mut missing = Vec::new();
+    let mut existing = path;
+    loop {
+        if let Ok(resolved) = fs::canonicalize(existing) {
+            return missing.iter().rev().fold(resolved, |p, name| p.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
@@ -1,30 +1,120 @@
-use crate::pkg::character::dao::FileCharacterDao;
 use crate::pkg::character::service::CharacterServiceLocalImpl;
-use crate::pkg::dialog::dao::FileDialogDao;
+use crate::pkg::comment::dao::SqliteCommentDao;
+use crate::pkg::comment::service::CommentServiceLocalImpl;
 use crate::pkg::dialog::service::DialogServiceLocalImpl;
 use crate::pkg::gitter::service::GitService;
+use crate::pkg::localization::dao::FileLocalizationDao;
+use crate::pkg::localization::service::LocalizationServiceLocalImpl;
 use crate::pkg::meta::dao::FileMetaDao;
+use crate::pkg::migration::service::MigrationServiceLocalImpl;
 use crate::pkg::project::service::ProjectServiceLocaleImpl;
-use crate::pkg::variables::dao::FileVariableDao;
+use crate::pkg::search::service::SearchServiceLocalImpl;
+use crate::pkg::serializer::service::SerializerServiceLocalImpl;
+use crate::pkg::spreadsheet::service::SpreadsheetServiceLocalImpl;
+use crate::pkg::stats::service::StatsServiceLocalImpl;
+use crate::pkg::storage::dao::{StorageCharacterDao, StorageDialogDao, StorageVariableDao};
+use crate::pkg::storage::service::StorageServiceLocalImpl;
+use crate::pkg::translation::service::TranslationServiceLocalImpl;
+use crate::pkg::user::dao::SqliteUserDao;
+use crate::pkg::user::service::UserServiceLocalImpl;
 use crate::pkg::variables::service::VariableServiceLocalImpl;
+use crate::pkg::voice::service::VoiceServiceLocalImpl;
+use crate::pkg::watcher::service::WatcherServiceLocalImpl;
 use crate::shared::config::ODConfigLocal;
+use std::sync::Arc;
 
 pub struct AppState {
     pub project_service: ProjectServiceLocaleImpl<ODConfigLocal>,
-    pub character_service:
-        CharacterServiceLocalImpl<ODConfigLocal, FileCharacterDao<ODConfigLocal>>,
-    pub dialog_service: DialogServiceLocalImpl<
+    pub character_service: CharacterServiceLocalImpl<
         ODConfigLocal,
-        FileDialogDao<ODConfigLocal>,
-        FileCharacterDao<ODConfigLocal>,
+        StorageCharacterDao<ODConfigLocal>,
+        StorageDialogDao<ODConfigLocal>,
         FileMetaDao<ODConfigLocal>,
+        StorageVariableDao<ODConfigLocal>,
     >,
-    pub var_service: VariableServiceLocalImpl<
+    pub dialog_service: Arc<
+        DialogServiceLocalImpl<
+            ODConfigLocal,
+            StorageDialogDao<ODConfigLocal>,
+            StorageCharacterDao<ODConfigLocal>,
+            FileMetaDao<ODConfigLocal>,
+        >,
+    >,
+    pub var_service: Arc<
+        VariableServiceLocalImpl<
+            ODConfigLocal,
+            StorageVariableDao<ODConfigLocal>,
+            StorageCharacterDao<ODConfigLocal>,
+            StorageDialogDao<ODConfigLocal>,
+            FileMetaDao<ODConfigLocal>,
+        >,
+    >,
+    pub git_service: GitService<ODConfigLocal>,
+    pub spreadsheet_service: SpreadsheetServiceLocalImpl<
+        ODConfigLocal,
+        StorageDialogDao<ODConfigLocal>,
+        StorageCharacterDao<ODConfigLocal>,
+        FileMetaDao<ODConfigLocal>,
+    >,
+    pub localization_service: Arc<
+        LocalizationServiceLocalImpl<
+            ODConfigLocal,
+            FileLocalizationDao<ODConfigLocal>,
+            StorageDialogDao<ODConfigLocal>,
+            StorageCharacterDao<ODConfigLocal>,
+            FileMetaDao<ODConfigLocal>,
+        >,
+    >,
+    pub translation_service: TranslationServiceLocalImpl<
+        ODConfigLocal,
+        FileLocalizationDao<ODConfigLocal>,
+        StorageDialogDao<ODConfigLocal>,
+        StorageCharacterDao<ODConfigLocal>,
+        FileMetaDao<ODConfigLocal>,
+    >,
+    pub stats_service: StatsServiceLocalImpl<
         ODConfigLocal,
-        FileVariableDao<ODConfigLocal>,
-        FileCharacterDao<ODConfigLocal>,
-        FileDialogDao<ODConfigLocal>,
+        FileLocalizationDao<ODConfigLocal>,
+        StorageDialogDao<ODConfigLocal>,
+        StorageCharacterDao<ODConfigLocal>,
         FileMetaDao<ODConfigLocal>,
+        StorageVariableDao<ODConfigLocal>,
+    >,
+    pub voice_service: VoiceServiceLocalImpl<
+        ODConfigLocal,
+        FileLocalizationDao<ODConfigLocal>,
+        StorageDialogDao<ODConfigLocal>,
+        StorageCharacterDao<ODConfigLocal>,
+        FileMetaDao<ODConfigLocal>,
+    >,
+    pub serializer_service: Arc<
+        SerializerServiceLocalImpl<
+            ODConfigLocal,
+            StorageDialogDao<ODConfigLocal>,
+            StorageCharacterDao<ODConfigLocal>,
+            StorageVariableDao<ODConfigLocal>,
+            FileMetaDao<ODConfigLocal>,
+            FileLocalizationDao<ODConfigLocal>,
+        >,
+    >,
+    pub migration_service: MigrationServiceLocalImpl<ODConfigLocal>,
+    pub storage_service: StorageServiceLocalImpl<ODConfigLocal>,
+    pub user_service: UserServiceLocalImpl<ODConfigLocal, SqliteUserDao>,
+    pub comment_service: CommentServiceLocalImpl<ODConfigLocal, SqliteCommentDao>,
+    pub search_service: SearchServiceLocalImpl<
+        ODConfigLocal,
+        StorageDialogDao<ODConfigLocal>,
+        StorageCharacterDao<ODConfigLocal>,
+        FileMetaDao<ODConfigLocal>,
+        StorageVariableDao<ODConfigLocal>,
+    >,
+    pub watcher_service: Arc<
+        WatcherServiceLocalImpl<
+            ODConfigLocal,
+            StorageDialogDao<ODConfigLocal>,
+            StorageCharacterDao<ODConfigLocal>,
+            FileMetaDao<ODConfigLocal>,
+            StorageVariableDao<ODConfigLocal>,
+        >,
     >,
-    pub git_service: GitService<ODConfigLocal>
 }
@@ -1,13 +1,20 @@
 use anyhow::anyhow;
 use std::sync::Arc;
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
 
 use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 use uuid::Uuid;
 
-use crate::shared::types::interfaces::Uploader;
+use crate::shared::types::{
+    dialog::NodeReference,
+    interfaces::{LineDelimited, Uploader},
+    proto::{self, parse_id, parse_opt_id},
+};
 
 /// Character is created by the user
 /// to represent a actor that can
@@ -19,7 +26,7 @@ use crate::shared::types::interfaces::Uploader;
 /// stats
 #[derive(TS)]
 #[ts(export, export_to = "../../src/bindings/")]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Character {
     id: Uuid,
     display_name: String,
@@ -30,6 +37,12 @@ pub struct Character {
     portrait_link: Option<String>,
     artwork_link: Option<String>,
     background_link: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    group_ids: Vec<Uuid>,
+    #[serde(default)]
+    attributes: HashMap<Uuid, AttributeValue>,
 }
 
 #[derive(TS)]
@@ -37,6 +50,12 @@ pub struct Character {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CharacterMetadata {
     data: HashMap<Uuid, SimpleCharacter>,
+    #[serde(default)]
+    groups: HashMap<Uuid, CharacterGroup>,
+    #[serde(default)]
+    attributes: HashMap<Uuid, CharacterAttribute>,
+    #[serde(default)]
+    relationships: HashMap<Uuid, CharacterRelationship>,
 }
 
 #[derive(TS)]
@@ -47,6 +66,116 @@ pub struct SimpleCharacter {
     display_name: String,
     portrait_link: Option<String>,
     order: usize,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    group_ids: Vec<Uuid>,
+}
+
+/// Group of characters, such as a faction or the people of a location.
+/// A character can belong to several groups.
+#[derive(TS)]
+#[ts(export, export_to = "../../src/bindings/")]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CharacterGroup {
+    id: Uuid,
+    name: String,
+    order: usize,
+}
+
+/// Attribute the characters of a project can be given,
+/// such as an age, a faction or a voice actor.
+#[derive(TS)]
+#[ts(export, export_to = "../../src/bindings/")]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CharacterAttribute {
+    id: Uuid,
+    name: String,
+    kind: AttributeKind,
+    order: usize,
+}
+
+/// Type of the values of an attribute. Enums list the values they accept.
+#[derive(TS)]
+#[ts(export, export_to = "../../src/bindings/")]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum AttributeKind {
+    Text,
+    Number,
+    Enum(Vec<String>),
+    Image,
+    Character,
+}
+
+/// Value of an attribute of a character. Images are the names of
+/// uploaded files, and characters are referenced by id.
+#[derive(TS)]
+#[ts(export, export_to = "../../src/bindings/")]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum AttributeValue {
+    Text(String),
+    Number(f64),
+    Enum(String),
+    Image(String),
+    Character(Uuid),
+}
+
+/// Relationship between two characters, such as siblings, rivals or an employer.
+/// The label reads from the first character to the second, and the reverse label,
+/// if any, from the second to the first. Without one, the label reads both ways.
+#[derive(TS)]
+#[ts(export, export_to = "../../src/bindings/")]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CharacterRelationship {
+    id: Uuid,
+    from: Uuid,
+    to: Uuid,
+    label: String,
+    reverse_label: Option<String>,
+    strength: Option<RelationshipStrength>,
+}
+
+/// Strength of a relationship, either fixed or read from the state
+/// of the first character in a global character variable.
+#[derive(TS)]
+#[ts(export, export_to = "../../src/bindings/")]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum RelationshipStrength {
+    Value(i32),
+    Variable(Uuid),
+}
+
+/// RelationshipForm represents the form a user can fill
+/// to create or change a relationship.
+#[derive(TS)]
+#[ts(export, export_to = "../../src/bindings/")]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelationshipForm {
+    pub from: Uuid,
+    pub to: Uuid,
+    pub label: String,
+    pub reverse_label: Option<String>,
+    pub strength: Option<RelationshipStrength>,
+}
+
+/// Relationships between the characters, along with the current state
+/// of the variables backing their strength, by relationship id.
+#[derive(TS)]
+#[ts(export, export_to = "../../src/bindings/")]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelationshipGraph {
+    pub relationships: Vec<CharacterRelationship>,
+    pub variable_strengths: HashMap<Uuid, String>,
+}
+
+/// Keeps the characters of the group, if any, having all the tags.
+#[derive(TS)]
+#[ts(export, export_to = "../../src/bindings/")]
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct CharacterFilter {
+    pub group_id: Option<Uuid>,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 /// CharacterForm represents the form a user can fill
@@ -60,6 +189,10 @@ pub struct CharacterForm<'a> {
     pub first_name: Option<String>,
     pub last_name: Option<String>,
     pub description: Option<String>,
+    /// Replaces every attribute of the character when given.
+    #[serde(default)]
+    #[ts(optional)]
+    pub attributes: Option<HashMap<Uuid, AttributeValue>>,
 }
 
 #[derive(TS)]
@@ -69,6 +202,64 @@ pub enum ImageField {
     Portrait,
     Artwork,
     Background,
+    Attribute(Uuid),
+}
+
+/// What to do with the references to a character being deleted.
+/// Block refuses the deletion if the character is referenced,
+/// Reassign moves every reference to another character, and
+/// Cascade deletes the dialogs and character variables of the character,
+/// and removes it from the nodes and global character variables.
+#[derive(TS)]
+#[ts(export, export_to = "../../src/bindings/")]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub enum CharacterDeletionMode {
+    Block,
+    Reassign(Uuid),
+    Cascade,
+}
+
+/// Everything in a project pointing to a character.
+#[derive(TS)]
+#[ts(export, export_to = "../../src/bindings/")]
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct CharacterReferences {
+    pub main_character_of: Vec<Uuid>,
+    pub speaking_nodes: Vec<NodeReference>,
+    pub character_variables: Vec<Uuid>,
+    pub global_character_variables: Vec<Uuid>,
+    #[serde(default)]
+    pub attributes_of: Vec<Uuid>,
+    #[serde(default)]
+    pub relationships: Vec<Uuid>,
+}
+
+impl CharacterReferences {
+    pub fn is_empty(&self) -> bool {
+        self.main_character_of.is_empty()
+            && self.speaking_nodes.is_empty()
+            && self.character_variables.is_empty()
+            && self.global_character_variables.is_empty()
+            && self.attributes_of.is_empty()
+            && self.relationships.is_empty()
+    }
+
+    /// Dialogs where the character is the main one or speaks, sorted.
+    pub fn has_variables(&self) -> bool {
+        !self.character_variables.is_empty() || !self.global_character_variables.is_empty()
+    }
+
+    pub fn get_dialog_ids(&self) -> Vec<Uuid> {
+        let mut res: Vec<Uuid> = self
+            .main_character_of
+            .iter()
+            .copied()
+            .chain(self.speaking_nodes.iter().map(|n| n.dialog_id))
+            .collect();
+        res.sort();
+        res.dedup();
+        res
+    }
 }
 
 impl Character {
@@ -84,13 +275,69 @@ impl Character {
             portrait_link: None,
             artwork_link: None,
             background_link: None,
+            tags: vec![],
+            group_ids: vec![],
+            attributes: HashMap::new(),
         })
     }
 
+    /// Tags are trimmed, deduplicated and sorted. Empty tags are dropped.
+    pub fn set_tags(&mut self, tags: Vec<String>) {
+        let mut tags: Vec<String> = tags
+            .iter()
+            .map(|t| String::from(t.trim()))
+            .filter(|t| !t.is_empty())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        self.tags = tags;
+    }
+
+    pub fn add_group(&mut self, group_id: Uuid) {
+        if !self.group_ids.contains(&group_id) {
+            self.group_ids.push(group_id);
+        }
+    }
+
+    pub fn remove_group(&mut self, group_id: &Uuid) {
+        self.group_ids.retain(|id| id != group_id);
+    }
+
     pub fn get_id(&self) -> &Uuid {
         &self.id
     }
 
+    pub fn get_attribute_value(&self, attribute_id: &Uuid) -> Option<&AttributeValue> {
+        self.attributes.get(attribute_id)
+    }
+
+    pub fn remove_attribute(&mut self, attribute_id: &Uuid) {
+        self.attributes.remove(attribute_id);
+    }
+
+    /// Whether an attribute of the character points to the other character.
+    pub fn references_character(&self, character_id: &Uuid) -> bool {
+        self.attributes
+            .values()
+            .any(|v| *v == AttributeValue::Character(*character_id))
+    }
+
+    /// Points the attributes referencing the character to another one,
+    /// or removes them without a replacement.
+    pub fn replace_character_reference(&mut self, character_id: &Uuid, by: Option<Uuid>) {
+        let referenced = AttributeValue::Character(*character_id);
+        match by {
+            Some(by) => {
+                for value in self.attributes.values_mut() {
+                    if *value == referenced {
+                        *value = AttributeValue::Character(by);
+                    }
+                }
+            }
+            None => self.attributes.retain(|_, v| *v != referenced),
+        }
+    }
+
     pub fn upload_image(
         &mut self,
         from: &str,
@@ -103,6 +350,9 @@ impl Character {
             ImageField::Artwork => self.artwork_link = Some(name),
             ImageField::Portrait => self.portrait_link = Some(name),
             ImageField::Background => self.background_link = Some(name),
+            ImageField::Attribute(id) => {
+                self.attributes.insert(id, AttributeValue::Image(name));
+            }
         };
         Ok(())
     }
@@ -115,6 +365,10 @@ impl Character {
         return self.description_link;
     }
 
+    pub fn get_description(&self) -> Option<&String> {
+        self.description.as_ref()
+    }
+
     pub fn set_description(&mut self, description: &str) {
         self.description = Some(String::from(description))
     }
@@ -169,6 +423,9 @@ impl Character {
         self.display_name = char_form.display_name.clone();
         self.first_name = char_form.first_name.clone();
         self.description = None;
+        if let Some(attributes) = &char_form.attributes {
+            self.attributes = attributes.clone();
+        }
     }
 }
 
@@ -176,7 +433,215 @@ impl CharacterMetadata {
     pub fn new() -> Self {
         Self {
             data: HashMap::new(),
+            groups: HashMap::new(),
+            attributes: HashMap::new(),
+            relationships: HashMap::new(),
+        }
+    }
+
+    pub fn create_group(&mut self, name: &str) -> Result<CharacterGroup> {
+        CharacterGroup::validate_name(name)?;
+        let group = CharacterGroup {
+            id: Uuid::new_v4(),
+            name: String::from(name.trim()),
+            order: self.groups.len(),
+        };
+        self.groups.insert(group.id, group.clone());
+        Ok(group)
+    }
+
+    pub fn rename_group(&mut self, group_id: &Uuid, name: &str) -> Result<()> {
+        CharacterGroup::validate_name(name)?;
+        let group = self
+            .groups
+            .get_mut(group_id)
+            .ok_or(anyhow!("group {group_id} does not exist"))?;
+        group.name = String::from(name.trim());
+        Ok(())
+    }
+
+    /// Removes the group, and the characters from it.
+    pub fn delete_group(&mut self, group_id: &Uuid) -> Result<()> {
+        self.groups
+            .remove(group_id)
+            .ok_or(anyhow!("group {group_id} does not exist"))?;
+        for character in self.data.values_mut() {
+            character.group_ids.retain(|id| id != group_id);
+        }
+        Ok(())
+    }
+
+    pub fn enforce_group_existence(&self, group_id: &Uuid) -> Result<()> {
+        if !self.groups.contains_key(group_id) {
+            bail!("group {group_id} does not exist")
+        }
+        Ok(())
+    }
+
+    /// Characters of each group, empty groups included.
+    pub fn get_group_members(&self) -> HashMap<Uuid, Vec<Uuid>> {
+        let mut res: HashMap<Uuid, Vec<Uuid>> =
+            self.groups.keys().map(|id| (*id, vec![])).collect();
+        for character in self.get_ordered_characters() {
+            for group_id in &character.group_ids {
+                if let Some(members) = res.get_mut(group_id) {
+                    members.push(character.id);
+                }
+            }
+        }
+        res
+    }
+
+    pub fn create_attribute(&mut self, name: &str, kind: AttributeKind) -> Result<CharacterAttribute> {
+        CharacterAttribute::validate_name(name)?;
+        let attribute = CharacterAttribute {
+            id: Uuid::new_v4(),
+            name: String::from(name.trim()),
+            kind: kind.normalized()?,
+            order: self.attributes.len(),
+        };
+        self.attributes.insert(attribute.id, attribute.clone());
+        Ok(attribute)
+    }
+
+    pub fn rename_attribute(&mut self, attribute_id: &Uuid, name: &str) -> Result<()> {
+        CharacterAttribute::validate_name(name)?;
+        let attribute = self
+            .attributes
+            .get_mut(attribute_id)
+            .ok_or(anyhow!("attribute {attribute_id} does not exist"))?;
+        attribute.name = String::from(name.trim());
+        Ok(())
+    }
+
+    /// Removes the attribute from the schema. The values of the
+    /// characters have to be removed separately.
+    pub fn delete_attribute(&mut self, attribute_id: &Uuid) -> Result<()> {
+        let attribute = self
+            .attributes
+            .remove(attribute_id)
+            .ok_or(anyhow!("attribute {attribute_id} does not exist"))?;
+        for other in self.attributes.values_mut() {
+            if other.order > attribute.order {
+                other.order -= 1;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_attribute(&self, attribute_id: &Uuid) -> Result<&CharacterAttribute> {
+        self.attributes
+            .get(attribute_id)
+            .ok_or(anyhow!("attribute {attribute_id} does not exist"))
+    }
+
+    /// Checks that every value belongs to an attribute of the schema and
+    /// matches its kind. Referenced characters must exist.
+    pub fn validate_attribute_values(&self, values: &HashMap<Uuid, AttributeValue>) -> Result<()> {
+        for (attribute_id, value) in values {
+            let attribute = self.get_attribute(attribute_id)?;
+            match (&attribute.kind, value) {
+                (AttributeKind::Text, AttributeValue::Text(_)) => (),
+                (AttributeKind::Number, AttributeValue::Number(n)) if n.is_finite() => (),
+                (AttributeKind::Enum(values), AttributeValue::Enum(v)) if values.contains(v) => (),
+                (AttributeKind::Image, AttributeValue::Image(name)) if !name.is_empty() => (),
+                (AttributeKind::Character, AttributeValue::Character(id)) => {
+                    self.get_character_by_id(id)?;
+                }
+                _ => bail!("invalid value {value:?} for attribute {}", attribute.name),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn create_relationship(&mut self, form: RelationshipForm) -> Result<CharacterRelationship> {
+        let relationship = CharacterRelationship::from_form(Uuid::new_v4(), form)?;
+        self.enforce_relationship_coherence(&relationship)?;
+        self.relationships.insert(relationship.id, relationship.clone());
+        Ok(relationship)
+    }
+
+    pub fn change_relationship(
+        &mut self,
+        relationship_id: &Uuid,
+        form: RelationshipForm,
+    ) -> Result<CharacterRelationship> {
+        self.get_relationship(relationship_id)?;
+        let relationship = CharacterRelationship::from_form(*relationship_id, form)?;
+        self.enforce_relationship_coherence(&relationship)?;
+        self.relationships.insert(relationship.id, relationship.clone());
+        Ok(relationship)
+    }
+
+    pub fn delete_relationship(&mut self, relationship_id: &Uuid) -> Result<()> {
+        self.relationships
+            .remove(relationship_id)
+            .ok_or(anyhow!("relationship {relationship_id} does not exist"))?;
+        Ok(())
+    }
+
+    pub fn get_relationship(&self, relationship_id: &Uuid) -> Result<&CharacterRelationship> {
+        self.relationships
+            .get(relationship_id)
+            .ok_or(anyhow!("relationship {relationship_id} does not exist"))
+    }
+
+    /// Relationships of the character, or all of them, sorted by id.
+    pub fn get_relationships(&self, character_id: Option<&Uuid>) -> Vec<&CharacterRelationship> {
+        let mut res: Vec<&CharacterRelationship> = self
+            .relationships
+            .values()
+            .filter(|r| character_id.is_none_or(|id| r.involves(id)))
+            .collect();
+        res.sort_by_key(|r| r.id);
+        res
+    }
+
+    /// Ids of the variables backing the strength of a relationship.
+    pub fn get_strength_variables(&self) -> HashSet<Uuid> {
+        self.relationships
+            .values()
+            .filter_map(|r| match r.strength {
+                Some(RelationshipStrength::Variable(id)) => Some(id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Moves the relationships of a character to another one. Without a
+    /// replacement, or when both ends become the same character, they are removed.
+    pub fn replace_relationship_character(&mut self, character_id: &Uuid, by: Option<Uuid>) {
+        self.relationships.retain(|_, r| {
+            if !r.involves(character_id) {
+                return true;
+            }
+            let Some(by) = by else {
+                return false;
+            };
+            if r.from == *character_id {
+                r.from = by;
+            }
+            if r.to == *character_id {
+                r.to = by;
+            }
+            r.from != r.to
+        });
+    }
+
+    fn enforce_relationship_coherence(&self, relationship: &CharacterRelationship) -> Result<()> {
+        self.get_character_by_id(&relationship.from)?;
+        self.get_character_by_id(&relationship.to)?;
+        if relationship.from == relationship.to {
+            bail!("a character cannot have a relationship with itself")
         }
+        Ok(())
+    }
+
+    pub fn retain_matching(&mut self, filter: &CharacterFilter) {
+        self.data.retain(|_, c| {
+            filter.group_id.is_none_or(|g| c.group_ids.contains(&g))
+                && filter.tags.iter().all(|t| c.tags.contains(t))
+        });
     }
 
     pub fn persist_character(&mut self, simple_character: SimpleCharacter) {
@@ -194,6 +659,21 @@ impl CharacterMetadata {
                 bail!("metadata not equal to other metadata in dialogs")
             }
         }
+        if self.groups.len() != other.groups.len()
+            || self.groups.keys().any(|k| !other.groups.contains_key(k))
+        {
+            bail!("metadata does not contain the same character groups")
+        }
+        if self.attributes.len() != other.attributes.len()
+            || self.attributes.keys().any(|k| !other.attributes.contains_key(k))
+        {
+            bail!("metadata does not contain the same character attributes")
+        }
+        if self.relationships.len() != other.relationships.len()
+            || self.relationships.keys().any(|k| !other.relationships.contains_key(k))
+        {
+            bail!("metadata does not contain the same character relationships")
+        }
         Ok(())
     }
 
@@ -203,6 +683,20 @@ impl CharacterMetadata {
             .ok_or(anyhow!("could not find user in metadata"))
     }
 
+    /// Returns the characters sorted by their order.
+    pub fn get_ordered_characters(&self) -> Vec<&SimpleCharacter> {
+        let mut res: Vec<&SimpleCharacter> = self.data.values().collect();
+        res.sort_by_key(|c| c.order);
+        res
+    }
+
+    /// Returns the attributes of the schema sorted by their order.
+    pub fn get_ordered_attributes(&self) -> Vec<&CharacterAttribute> {
+        let mut res: Vec<&CharacterAttribute> = self.attributes.values().collect();
+        res.sort_by_key(|a| a.order);
+        res
+    }
+
     pub fn delete_character_by_id(&mut self, id: &Uuid) -> Result<()> {
         self.data
             .remove_entry(id)
@@ -218,10 +712,290 @@ impl SimpleCharacter {
             display_name: character.display_name.clone(),
             portrait_link: character.portrait_link.clone(),
             order: order,
+            tags: character.tags.clone(),
+            group_ids: character.group_ids.clone(),
         };
     }
 
     pub fn get_order(&self) -> usize {
         return self.order;
     }
+
+    pub fn get_name(&self) -> &String {
+        &self.display_name
+    }
+
+    pub fn get_id(&self) -> &Uuid {
+        &self.id
+    }
+}
+
+impl CharacterGroup {
+    pub fn validate_name(name: &str) -> Result<()> {
+        if name.trim().is_empty() {
+            bail!("group name cannot be empty")
+        }
+        Ok(())
+    }
+}
+
+impl CharacterAttribute {
+    pub fn validate_name(name: &str) -> Result<()> {
+        if name.trim().is_empty() {
+            bail!("attribute name cannot be empty")
+        }
+        Ok(())
+    }
+
+    pub fn get_id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn get_kind(&self) -> &AttributeKind {
+        &self.kind
+    }
+}
+
+impl CharacterRelationship {
+    fn from_form(id: Uuid, form: RelationshipForm) -> Result<Self> {
+        let label = form.label.trim();
+        if label.is_empty() {
+            bail!("relationship label cannot be empty")
+        }
+        let reverse_label = form
+            .reverse_label
+            .as_deref()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(String::from);
+        Ok(Self {
+            id,
+            from: form.from,
+            to: form.to,
+            label: String::from(label),
+            reverse_label,
+            strength: form.strength,
+        })
+    }
+
+    pub fn get_id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn get_from(&self) -> &Uuid {
+        &self.from
+    }
+
+    pub fn get_strength(&self) -> Option<RelationshipStrength> {
+        self.strength
+    }
+
+    pub fn involves(&self, character_id: &Uuid) -> bool {
+        self.from == *character_id || self.to == *character_id
+    }
+}
+
+impl AttributeKind {
+    /// Trims and deduplicates the values of an enum, which cannot be empty.
+    fn normalized(self) -> Result<Self> {
+        match self {
+            AttributeKind::Enum(values) => {
+                let mut res: Vec<String> = vec![];
+                for value in values.iter().map(|v| v.trim()).filter(|v| !v.is_empty()) {
+                    if !res.iter().any(|r| r == value) {
+                        res.push(String::from(value));
+                    }
+                }
+                if res.is_empty() {
+                    bail!("an enum attribute needs at least one value")
+                }
+                Ok(AttributeKind::Enum(res))
+            }
+            kind => Ok(kind),
+        }
+    }
+}
+
+impl LineDelimited for CharacterMetadata {
+    const COLLECTIONS: &'static [&'static str] = &["data", "groups", "attributes", "relationships"];
+}
+
+impl From<&Character> for proto::Character {
+    fn from(character: &Character) -> Self {
+        let mut attributes: Vec<(&Uuid, &AttributeValue)> = character.attributes.iter().collect();
+        attributes.sort_by_key(|(id, _)| **id);
+        Self {
+            id: character.id.to_string(),
+            display_name: character.display_name.clone(),
+            first_name: character.first_name.clone(),
+            last_name: character.last_name.clone(),
+            description: character.description.clone(),
+            description_link: character.description_link.map(|d| d.to_string()),
+            portrait_link: character.portrait_link.clone(),
+            artwork_link: character.artwork_link.clone(),
+            background_link: character.background_link.clone(),
+            tags: character.tags.clone(),
+            group_ids: character.group_ids.iter().map(|g| g.to_string()).collect(),
+            attributes: attributes
+                .into_iter()
+                .map(proto::AttributeValue::from)
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::Character> for Character {
+    type Error = anyhow::Error;
+
+    fn try_from(character: proto::Character) -> Result<Self> {
+        Ok(Self {
+            id: parse_id(&character.id)?,
+            display_name: character.display_name,
+            first_name: character.first_name,
+            last_name: character.last_name,
+            description: character.description,
+            description_link: parse_opt_id(character.description_link.as_ref())?,
+            portrait_link: character.portrait_link,
+            artwork_link: character.artwork_link,
+            background_link: character.background_link,
+            tags: character.tags,
+            group_ids: character
+                .group_ids
+                .iter()
+                .map(|g| parse_id(g))
+                .collect::<Result<Vec<Uuid>>>()?,
+            attributes: character
+                .attributes
+                .into_iter()
+                .map(|a| {
+                    let id = parse_id(&a.attribute_id)?;
+                    Ok((id, AttributeValue::try_from(a)?))
+                })
+                .collect::<Result<HashMap<Uuid, AttributeValue>>>()?,
+        })
+    }
+}
+
+impl From<(&Uuid, &AttributeValue)> for proto::AttributeValue {
+    fn from((id, value): (&Uuid, &AttributeValue)) -> Self {
+        let value = match value {
+            AttributeValue::Text(t) => proto::attribute_value::Value::Text(t.clone()),
+            AttributeValue::Number(n) => proto::attribute_value::Value::Number(*n),
+            AttributeValue::Enum(e) => proto::attribute_value::Value::EnumValue(e.clone()),
+            AttributeValue::Image(i) => proto::attribute_value::Value::Image(i.clone()),
+            AttributeValue::Character(c) => {
+                proto::attribute_value::Value::CharacterId(c.to_string())
+            }
+        };
+        Self {
+            attribute_id: id.to_string(),
+            value: Some(value),
+        }
+    }
+}
+
+impl TryFrom<proto::AttributeValue> for AttributeValue {
+    type Error = anyhow::Error;
+
+    fn try_from(attribute: proto::AttributeValue) -> Result<Self> {
+        let value = attribute
+            .value
+            .ok_or(anyhow!("attribute {} has no value", attribute.attribute_id))?;
+        Ok(match value {
+            proto::attribute_value::Value::Text(t) => AttributeValue::Text(t),
+            proto::attribute_value::Value::Number(n) => AttributeValue::Number(n),
+            proto::attribute_value::Value::EnumValue(e) => AttributeValue::Enum(e),
+            proto::attribute_value::Value::Image(i) => AttributeValue::Image(i),
+            proto::attribute_value::Value::CharacterId(c) => AttributeValue::Character(parse_id(&c)?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_character(metadata: &mut CharacterMetadata, name: &str) -> Uuid {
+        let character = Character::new(name).unwrap();
+        let order = metadata.data.len();
+        metadata.persist_character(SimpleCharacter::from_character(&character, order));
+        character.id
+    }
+
+    fn relate(metadata: &mut CharacterMetadata, from: Uuid, to: Uuid) -> Uuid {
+        relate_with(metadata, from, to, None)
+    }
+
+    fn relate_with(
+        metadata: &mut CharacterMetadata,
+        from: Uuid,
+        to: Uuid,
+        strength: Option<RelationshipStrength>,
+    ) -> Uuid {
+        let form = RelationshipForm {
+            from,
+            to,
+            label: String::from("knows"),
+            reverse_label: None,
+            strength,
+        };
+        metadata.create_relationship(form).unwrap().id
+    }
+
+    #[test]
+    fn relationships_move_to_the_replacement() {
+        let mut metadata = CharacterMetadata::new();
+        let (a, b, c) = (
+            add_character(&mut metadata, "a"),
+            add_character(&mut metadata, "b"),
+            add_character(&mut metadata, "c"),
+        );
+        let id = relate(&mut metadata, a, b);
+        metadata.replace_relationship_character(&a, Some(c));
+        let relationship = metadata.get_relationship(&id).unwrap();
+        assert_eq!((relationship.from, relationship.to), (c, b));
+    }
+
+    #[test]
+    fn relationships_are_removed_without_replacement() {
+        let mut metadata = CharacterMetadata::new();
+        let (a, b, c) = (
+            add_character(&mut metadata, "a"),
+            add_character(&mut metadata, "b"),
+            add_character(&mut metadata, "c"),
+        );
+        relate(&mut metadata, a, b);
+        let kept = relate(&mut metadata, b, c);
+        metadata.replace_relationship_character(&a, None);
+        assert_eq!(metadata.relationships.len(), 1);
+        assert!(metadata.get_relationship(&kept).is_ok());
+    }
+
+    #[test]
+    fn relationships_with_the_replacement_itself_are_removed() {
+        let mut metadata = CharacterMetadata::new();
+        let (a, b) = (add_character(&mut metadata, "a"), add_character(&mut metadata, "b"));
+        relate(&mut metadata, a, b);
+        metadata.replace_relationship_character(&a, Some(b));
+        assert!(metadata.relationships.is_empty());
+    }
+
+    #[test]
+    fn strength_variables_are_only_the_variable_backed_ones() {
+        let mut metadata = CharacterMetadata::new();
+        let (a, b, c) = (
+            add_character(&mut metadata, "a"),
+            add_character(&mut metadata, "b"),
+            add_character(&mut metadata, "c"),
+        );
+        let var_id = Uuid::new_v4();
+        relate_with(&mut metadata, a, b, Some(RelationshipStrength::Variable(var_id)));
+        relate_with(&mut metadata, b, c, Some(RelationshipStrength::Value(3)));
+        relate(&mut metadata, a, c);
+        assert_eq!(metadata.get_strength_variables(), HashSet::from([var_id]));
+    }
 }
@@ -0,0 +1,80 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// Kind of entity a comment is attached to.
+#[derive(TS, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub enum CommentTarget {
+    Dialog,
+    Node,
+    Choice,
+    Character,
+    Variable,
+}
+
+/// Note left by a user on an entity of a project. Comments are
+/// stored in the local database and are not versioned with the project.
+/// Answers to a comment point to it with `parent_id`.
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct Comment {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub target: CommentTarget,
+    pub target_id: Uuid,
+    pub dialog_id: Option<Uuid>,
+    pub parent_id: Option<Uuid>,
+    pub author: String,
+    pub email: Option<String>,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub resolved_by: Option<String>,
+}
+
+impl CommentTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommentTarget::Dialog => "dialog",
+            CommentTarget::Node => "node",
+            CommentTarget::Choice => "choice",
+            CommentTarget::Character => "character",
+            CommentTarget::Variable => "variable",
+        }
+    }
+}
+
+impl FromStr for CommentTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "dialog" => CommentTarget::Dialog,
+            "node" => CommentTarget::Node,
+            "choice" => CommentTarget::Choice,
+            "character" => CommentTarget::Character,
+            "variable" => CommentTarget::Variable,
+            _ => bail!("unknown comment target {s}"),
+        })
+    }
+}
+
+/// A comment that is not an answer, with its answers in chronological order.
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct CommentThread {
+    pub comment: Comment,
+    pub replies: Vec<Comment>,
+}
+
+impl Comment {
+    pub fn is_resolved(&self) -> bool {
+        self.resolved_at.is_some()
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     str::FromStr,
 };
 
@@ -9,6 +9,13 @@ use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 use uuid::Uuid;
 
+use crate::shared::types::{
+    interfaces::LineDelimited,
+    meta::LineIdRegistry,
+    proto::{self, parse_id, parse_opt_id},
+    variables::{StateRename, VariableUsage},
+};
+
 #[derive(TS, Serialize, Deserialize, Debug)]
 #[ts(export, export_to = "../../src/bindings/")]
 pub struct SimpleDialog {
@@ -17,6 +24,8 @@ pub struct SimpleDialog {
     main_character: Uuid,
     characters: Vec<Uuid>,
     order:usize,
+    #[serde(default)]
+    group_id: Option<Uuid>,
 }
 
 #[derive(TS, Serialize, Deserialize, Debug)]
@@ -26,12 +35,16 @@ pub struct DialogMetadata {
     pub groups: HashMap<Uuid, DialogGroup>
 }
 
-#[derive(TS, Serialize, Deserialize, Debug)]
+/// Group of dialogs, such as an act, a chapter or a scene.
+/// Groups can be nested, and are ordered among the groups of their parent.
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
 #[ts(export, export_to = "../../src/bindings/")]
 pub struct DialogGroup {
     id:Uuid,
     name:String,
     order:usize,
+    #[serde(default)]
+    parent_id: Option<Uuid>,
 }
 
 #[derive(TS, Serialize, Deserialize, Debug)]
@@ -62,6 +75,7 @@ pub enum NodeData {
     Dialog(DialogNode),
     Phylum(Phylum),
     Choices(Choices),
+    Link(DialogLink),
 }
 
 #[derive(TS, Serialize, Deserialize, Debug)]
@@ -81,12 +95,44 @@ pub struct Choice {
 #[derive(TS, Serialize, Deserialize, Debug)]
 #[ts(export, export_to = "../../src/bindings/")]
 pub struct DialogNode {
+    #[serde(default)]
+    line_id: Option<String>,
     next_node: Option<Uuid>,
     character_id: Option<Uuid>,
     pub content_link: Option<Uuid>,
     pub content: Option<String>,
 }
 
+/// Jumps to another dialog, at its root or at the given node, then
+/// comes back to the next node once the other dialog is over.
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct DialogLink {
+    dialog_id: Uuid,
+    node_id: Option<Uuid>,
+    next_node: Option<Uuid>,
+}
+
+/// Link from a node of a dialog to another dialog of the project.
+#[derive(TS, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct StoryLink {
+    pub dialog_id: Uuid,
+    pub node_id: Uuid,
+    pub target_dialog_id: Uuid,
+    pub target_node_id: Option<Uuid>,
+}
+
+/// Dialogs of the project and the links between them.
+/// Broken links point to a dialog or a node which does not exist anymore.
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct StoryGraph {
+    pub dialogs: Vec<Uuid>,
+    pub links: Vec<StoryLink>,
+    pub broken_links: Vec<StoryLink>,
+}
+
 #[derive(TS, Serialize, Deserialize, Debug)]
 #[ts(export, export_to = "../../src/bindings/")]
 pub struct Phylum {
@@ -138,6 +184,22 @@ pub struct DialogCreationForm<'a> {
     pub order: usize,
 }
 
+/// Node of a dialog, as pointed to from outside of it.
+#[derive(TS, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct NodeReference {
+    pub dialog_id: Uuid,
+    pub node_id: Uuid,
+}
+
+/// Whether the saved versions of a dialog allow to undo or redo.
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct DialogHistoryState {
+    pub can_undo: bool,
+    pub can_redo: bool,
+}
+
 pub struct PhylumDiff<'a> {
     pub added: Vec<&'a Phylum>,
     pub deleted: Vec<&'a Phylum>,
@@ -148,6 +210,17 @@ pub struct DialogContent<'a> {
     pub node_id: &'a Uuid,
 }
 
+/// A piece of text written by the user in a dialog,
+/// either the content of a dialog node or the content
+/// of one of the choices of a choice node.
+pub struct DialogLine<'a> {
+    pub node_id: &'a Uuid,
+    pub line_id: Option<&'a str>,
+    pub choice_id: Option<&'a Uuid>,
+    pub character_id: Option<&'a Uuid>,
+    pub content: Option<&'a str>,
+}
+
 #[enum_dispatch::enum_dispatch]
 trait Coherent {
     fn enforce_coherence(&self, dialog: &Dialog) -> Result<()>;
@@ -191,6 +264,181 @@ impl DialogMetadata {
             .ok_or(anyhow!("entry did not exist in metadata"))?;
         Ok(())
     }
+
+    /// Creates a group at the end of its parent, or of the top level groups.
+    pub fn create_group(&mut self, name: &str, parent_id: Option<Uuid>) -> Result<DialogGroup> {
+        DialogGroup::validate_name(name)?;
+        self.enforce_group_existence(parent_id.as_ref())?;
+        let group = DialogGroup {
+            id: Uuid::new_v4(),
+            name: String::from(name.trim()),
+            order: self.groups.values().filter(|g| g.parent_id == parent_id).count(),
+            parent_id,
+        };
+        self.groups.insert(group.id, group.clone());
+        Ok(group)
+    }
+
+    pub fn rename_group(&mut self, group_id: &Uuid, name: &str) -> Result<()> {
+        DialogGroup::validate_name(name)?;
+        let group = self
+            .groups
+            .get_mut(group_id)
+            .ok_or(anyhow!("group {group_id} does not exist"))?;
+        group.name = String::from(name.trim());
+        Ok(())
+    }
+
+    /// Moves a group into another one, or to the top level, at the given place.
+    pub fn move_group(&mut self, group_id: &Uuid, parent_id: Option<Uuid>, order: usize) -> Result<()> {
+        self.enforce_group_existence(Some(group_id))?;
+        self.enforce_group_existence(parent_id.as_ref())?;
+        if self.group_chain(parent_id).contains(group_id) {
+            bail!("group {group_id} cannot be moved into itself")
+        }
+        let previous_parent = self.groups[group_id].parent_id;
+        if let Some(group) = self.groups.get_mut(group_id) {
+            group.parent_id = parent_id;
+        }
+        self.reorder_groups(previous_parent, None);
+        self.reorder_groups(parent_id, Some((*group_id, order)));
+        Ok(())
+    }
+
+    /// Deletes a group. Its dialogs and sub groups are moved to its parent.
+    pub fn delete_group(&mut self, group_id: &Uuid) -> Result<()> {
+        let group = self
+            .groups
+            .remove(group_id)
+            .ok_or(anyhow!("group {group_id} does not exist"))?;
+        for child in self.groups.values_mut() {
+            if child.parent_id == Some(*group_id) {
+                child.parent_id = group.parent_id;
+            }
+        }
+        for dialog in self.data.values_mut() {
+            if dialog.group_id == Some(*group_id) {
+                dialog.group_id = group.parent_id;
+            }
+        }
+        self.reorder_groups(group.parent_id, None);
+        self.reorder_dialogs(group.parent_id, None);
+        Ok(())
+    }
+
+    /// Moves a dialog into a group, or out of any group, at the given place.
+    pub fn assign_dialog(&mut self, dialog_id: &Uuid, group_id: Option<Uuid>, order: usize) -> Result<()> {
+        self.enforce_group_existence(group_id.as_ref())?;
+        let dialog = self
+            .data
+            .get_mut(dialog_id)
+            .ok_or(anyhow!("dialog {dialog_id} does not exist"))?;
+        let previous_group = dialog.group_id;
+        dialog.group_id = group_id;
+        self.reorder_dialogs(previous_group, None);
+        self.reorder_dialogs(group_id, Some((*dialog_id, order)));
+        Ok(())
+    }
+
+    /// Whether the dialog is in the group or in one of its sub groups.
+    pub fn is_in_group(&self, dialog_id: &Uuid, group_id: &Uuid) -> bool {
+        self.group_chain(self.data.get(dialog_id).and_then(|d| d.group_id))
+            .contains(group_id)
+    }
+
+    /// Checks that groups and dialogs are only placed in existing groups,
+    /// and that no group is nested in itself.
+    pub fn enforce_groups_coherence(&self) -> Result<()> {
+        for (id, group) in &self.groups {
+            if group.id != *id {
+                bail!("group {} is stored under the id {id}", group.id)
+            }
+            self.enforce_group_existence(group.parent_id.as_ref())?;
+            if self.group_chain(group.parent_id).contains(id) {
+                bail!("group {id} is nested in itself")
+            }
+        }
+        for dialog in self.data.values() {
+            self.enforce_group_existence(dialog.group_id.as_ref())?;
+        }
+        Ok(())
+    }
+
+    /// Ids of the group and of its ancestors, from the closest one. Stops at
+    /// the first group met twice, so that nested groups forming a cycle end.
+    fn group_chain(&self, group_id: Option<Uuid>) -> Vec<Uuid> {
+        let mut chain = vec![];
+        let mut current = group_id;
+        while let Some(id) = current {
+            if chain.contains(&id) {
+                break;
+            }
+            chain.push(id);
+            current = self.groups.get(&id).and_then(|g| g.parent_id);
+        }
+        chain
+    }
+
+    fn enforce_group_existence(&self, group_id: Option<&Uuid>) -> Result<()> {
+        match group_id {
+            Some(id) if !self.groups.contains_key(id) => bail!("group {id} does not exist"),
+            _ => Ok(()),
+        }
+    }
+
+    /// Numbers the groups of a parent from zero, keeping their order,
+    /// after placing the moved group at its new place.
+    fn reorder_groups(&mut self, parent_id: Option<Uuid>, moved: Option<(Uuid, usize)>) {
+        let mut siblings: Vec<(usize, Uuid)> = self
+            .groups
+            .values()
+            .filter(|g| g.parent_id == parent_id)
+            .map(|g| (g.order, g.id))
+            .collect();
+        let ids = place_moved(&mut siblings, moved);
+        for (order, id) in ids.into_iter().enumerate() {
+            if let Some(group) = self.groups.get_mut(&id) {
+                group.order = order;
+            }
+        }
+    }
+
+    /// Numbers the dialogs of a group from zero, keeping their order,
+    /// after placing the moved dialog at its new place.
+    fn reorder_dialogs(&mut self, group_id: Option<Uuid>, moved: Option<(Uuid, usize)>) {
+        let mut siblings: Vec<(usize, Uuid)> = self
+            .data
+            .values()
+            .filter(|d| d.group_id == group_id)
+            .map(|d| (d.order, d.id))
+            .collect();
+        let ids = place_moved(&mut siblings, moved);
+        for (order, id) in ids.into_iter().enumerate() {
+            if let Some(dialog) = self.data.get_mut(&id) {
+                dialog.order = order;
+            }
+        }
+    }
+}
+
+/// Sorts the siblings, then puts the moved one at its place.
+fn place_moved(siblings: &mut Vec<(usize, Uuid)>, moved: Option<(Uuid, usize)>) -> Vec<Uuid> {
+    siblings.sort();
+    let mut ids: Vec<Uuid> = siblings.iter().map(|(_, id)| *id).collect();
+    if let Some((moved_id, order)) = moved {
+        ids.retain(|id| *id != moved_id);
+        ids.insert(order.min(ids.len()), moved_id);
+    }
+    ids
+}
+
+impl DialogGroup {
+    pub fn validate_name(name: &str) -> Result<()> {
+        if name.trim().is_empty() {
+            bail!("group name cannot be empty")
+        }
+        Ok(())
+    }
 }
 
 impl SimpleDialog {
@@ -201,12 +449,29 @@ impl SimpleDialog {
             main_character: dialog.main_character,
             characters: dialog.characters_ids.clone(),
             order:order,
+            group_id: None,
         }
     }
 
+    /// Builds the metadata of a saved dialog, keeping the
+    /// place of the dialog chosen by the user.
+    pub fn from_saved_dialog(dialog: &Dialog, prev: &SimpleDialog) -> Self {
+        let mut res = SimpleDialog::from_dialog(dialog, prev.order);
+        res.group_id = prev.group_id;
+        res
+    }
+
     pub fn get_order(&self) -> usize {
         return self.order
     }
+
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn get_id(&self) -> &Uuid {
+        &self.id
+    }
 }
 
 impl Node {
@@ -217,6 +482,21 @@ impl Node {
     pub fn get_id(&self) -> &Uuid {
         return &self.id;
     }
+
+    pub fn get_node_data(&self) -> &NodeData {
+        &self.data
+    }
+}
+
+impl NodeData {
+    pub fn get_next_nodes(&self) -> Vec<Uuid> {
+        match self {
+            NodeData::Dialog(d) => d.next_node.into_iter().collect(),
+            NodeData::Choices(c) => c.choices.iter().filter_map(|c| c.next_node).collect(),
+            NodeData::Phylum(p) => p.branches.iter().filter_map(|b| b.next_node).collect(),
+            NodeData::Link(l) => l.next_node.into_iter().collect(),
+        }
+    }
 }
 
 impl Dialog {
@@ -242,6 +522,328 @@ impl Dialog {
         &mut self.nodes
     }
 
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn get_main_character(&self) -> &Uuid {
+        &self.main_character
+    }
+
+    /// Conditions of the phylums of the dialog reading variables, in reading order.
+    pub fn get_variable_usages(&self) -> Vec<VariableUsage> {
+        let mut res = vec![];
+        for node_id in self.get_ordered_node_ids() {
+            let phylum = match self.nodes.get(&node_id).map(|n| &n.data) {
+                Some(NodeData::Phylum(p)) => p,
+                _ => continue,
+            };
+            for condition in &phylum.branches {
+                let necessities = match &condition.necessities {
+                    Some(n) => n.get_var_necessities(),
+                    None => continue,
+                };
+                for necessity in necessities {
+                    res.push(VariableUsage {
+                        variable_id: necessity.var_id,
+                        dialog_id: self.id,
+                        dialog_name: self.name.clone(),
+                        node_id,
+                        phylum_id: phylum.id,
+                        phylum_name: phylum.name.clone(),
+                        condition_id: condition.id,
+                        condition_name: condition.name.clone(),
+                        necessary_state: necessity.necessary_state.clone(),
+                    });
+                }
+            }
+        }
+        res
+    }
+
+    /// Applies the renames to the conditions of the dialog.
+    /// Returns the number of conditions changed.
+    pub fn rename_variable_states(&mut self, renames: &[StateRename]) -> usize {
+        let mut count = 0;
+        for node in self.nodes.values_mut() {
+            let phylum = match &mut node.data {
+                NodeData::Phylum(p) => p,
+                _ => continue,
+            };
+            for condition in phylum.branches.iter_mut() {
+                if let Some(n) = &mut condition.necessities {
+                    for necessity in n.get_var_necessities_mut() {
+                        let rename = renames.iter().find(|r| {
+                            r.variable_id == necessity.var_id && r.from == necessity.necessary_state
+                        });
+                        if let Some(r) = rename {
+                            necessity.necessary_state = r.to.clone();
+                            count += 1;
+                        }
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    /// Characters speaking in the dialog nodes, sorted.
+    pub fn get_speakers(&self) -> Vec<Uuid> {
+        let speakers: HashSet<Uuid> = self
+            .nodes
+            .values()
+            .filter_map(|n| match &n.data {
+                NodeData::Dialog(d) => d.character_id,
+                _ => None,
+            })
+            .collect();
+        let mut res: Vec<Uuid> = speakers.into_iter().collect();
+        res.sort();
+        res
+    }
+
+    /// Sets the characters of the dialog to the speakers of its nodes.
+    pub fn sync_characters(&mut self) {
+        self.characters_ids = self.get_speakers();
+    }
+
+    /// Whether the character is the main character of the dialog or speaks in it.
+    pub fn features_character(&self, character_id: &Uuid) -> bool {
+        self.main_character == *character_id || !self.get_speaking_nodes(character_id).is_empty()
+    }
+
+    pub fn has_node(&self, node_id: &Uuid) -> bool {
+        self.nodes.contains_key(node_id)
+    }
+
+    /// Links of the dialog to other dialogs, in reading order.
+    pub fn get_story_links(&self) -> Vec<StoryLink> {
+        self.get_ordered_node_ids()
+            .into_iter()
+            .filter_map(|node_id| match &self.nodes[&node_id].data {
+                NodeData::Link(l) => Some(StoryLink {
+                    dialog_id: self.id,
+                    node_id,
+                    target_dialog_id: l.dialog_id,
+                    target_node_id: l.node_id,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Ids of the dialog nodes spoken by the character.
+    pub fn get_speaking_nodes(&self, character_id: &Uuid) -> Vec<Uuid> {
+        let mut res: Vec<Uuid> = self
+            .nodes
+            .values()
+            .filter(|n| match &n.data {
+                NodeData::Dialog(d) => d.character_id.as_ref() == Some(character_id),
+                _ => false,
+            })
+            .map(|n| n.id)
+            .collect();
+        res.sort();
+        res
+    }
+
+    /// Replaces the character everywhere in the dialog. Without a replacement,
+    /// the nodes it spoke lose their speaker; the main character has to be replaced.
+    pub fn replace_character(&mut self, character_id: &Uuid, by: Option<Uuid>) -> Result<()> {
+        if self.main_character == *character_id {
+            self.main_character = by.ok_or(anyhow!(
+                "main character of dialog {} cannot be removed",
+                self.id
+            ))?;
+        }
+        for node in self.nodes.values_mut() {
+            if let NodeData::Dialog(d) = &mut node.data {
+                if d.character_id.as_ref() == Some(character_id) {
+                    d.character_id = by;
+                }
+            }
+        }
+        match by {
+            Some(by) if !self.characters_ids.contains(&by) => {
+                for id in self.characters_ids.iter_mut() {
+                    if id == character_id {
+                        *id = by;
+                    }
+                }
+            }
+            _ => (),
+        }
+        self.characters_ids.retain(|id| id != character_id);
+        Ok(())
+    }
+
+    /// Returns the ids of the nodes in reading order : a breadth first
+    /// traversal starting from the root node, followed by the nodes
+    /// that cannot be reached from it, sorted by id.
+    pub fn get_ordered_node_ids(&self) -> Vec<Uuid> {
+        let mut res = vec![];
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        if let Some(root) = self.root_node {
+            queue.push_back(root);
+        }
+        while let Some(id) = queue.pop_front() {
+            let node = match self.nodes.get(&id) {
+                Some(n) => n,
+                None => continue,
+            };
+            if !seen.insert(id) {
+                continue;
+            }
+            res.push(id);
+            queue.extend(node.data.get_next_nodes());
+        }
+        let mut orphans: Vec<Uuid> = self
+            .nodes
+            .keys()
+            .filter(|id| !seen.contains(*id))
+            .copied()
+            .collect();
+        orphans.sort();
+        res.extend(orphans);
+        res
+    }
+
+    /// Gives a line id to every dialog node. Nodes keep the id they had
+    /// in the previous version of the dialog, whatever the frontend sent,
+    /// so that ids stay stable and are never duplicated by copies of a node.
+    /// Nodes coming back, e.g. restored from the history, keep the id they
+    /// carry if the registry issued it and no other node of the dialog uses it.
+    pub fn assign_line_ids(&mut self, prev: &Dialog, registry: &mut LineIdRegistry) {
+        let prefix = LineIdRegistry::prefix_for(&self.name);
+        let ordered = self.get_ordered_node_ids();
+        let previous: HashMap<Uuid, String> = ordered
+            .iter()
+            .filter(|id| matches!(self.nodes[id].data, NodeData::Dialog(_)))
+            .filter_map(|id| match prev.nodes.get(id).map(|n| &n.data) {
+                Some(NodeData::Dialog(d)) => d.line_id.clone().map(|l| (*id, l)),
+                _ => None,
+            })
+            .collect();
+        let mut taken: HashSet<String> = previous.values().cloned().collect();
+        for id in ordered {
+            if let Some(NodeData::Dialog(d)) = self.nodes.get_mut(&id).map(|n| &mut n.data) {
+                if let Some(line_id) = previous.get(&id) {
+                    d.line_id = Some(line_id.clone());
+                    continue;
+                }
+                let line_id = match d.line_id.take() {
+                    Some(l) if registry.has_issued(&l) && !taken.contains(&l) => l,
+                    _ => registry.next_id(&prefix),
+                };
+                taken.insert(line_id.clone());
+                d.line_id = Some(line_id);
+            }
+        }
+    }
+
+    /// Collects every line of text of the dialog in reading order.
+    /// The content of dialog nodes must have been loaded beforehand.
+    pub fn get_lines(&self) -> Vec<DialogLine<'_>> {
+        let mut res = vec![];
+        for id in self.get_ordered_node_ids() {
+            let node = &self.nodes[&id];
+            match &node.data {
+                NodeData::Dialog(d) => res.push(DialogLine {
+                    node_id: &node.id,
+                    line_id: d.line_id.as_deref(),
+                    choice_id: None,
+                    character_id: d.character_id.as_ref(),
+                    content: d.content.as_deref(),
+                }),
+                NodeData::Choices(c) => {
+                    for choice in &c.choices {
+                        res.push(DialogLine {
+                            node_id: &node.id,
+                            line_id: None,
+                            choice_id: Some(&choice.id),
+                            character_id: None,
+                            content: Some(&choice.content),
+                        })
+                    }
+                }
+                NodeData::Phylum(_) | NodeData::Link(_) => (),
+            }
+        }
+        res
+    }
+
+    /// Maps each node to the text that leads to it : the content of the
+    /// dialog node or of the choice pointing to it, looking through phylums.
+    pub fn get_previous_lines(&self) -> HashMap<Uuid, &str> {
+        let mut predecessors: HashMap<Uuid, Uuid> = HashMap::new();
+        for id in self.get_ordered_node_ids() {
+            for next in self.nodes[&id].data.get_next_nodes() {
+                predecessors.entry(next).or_insert(id);
+            }
+        }
+        let mut res = HashMap::new();
+        for id in self.nodes.keys() {
+            let mut current = *id;
+            let mut visited = HashSet::new();
+            while let Some(pred) = predecessors.get(&current) {
+                if !visited.insert(*pred) {
+                    break;
+                }
+                match &self.nodes[pred].data {
+                    NodeData::Dialog(d) => {
+                        if let Some(content) = &d.content {
+                            res.insert(*id, content.as_str());
+                        }
+                        break;
+                    }
+                    NodeData::Choices(c) => {
+                        let choice = c.choices.iter().find(|ch| ch.next_node == Some(current));
+                        if let Some(choice) = choice {
+                            res.insert(*id, choice.content.as_str());
+                        }
+                        break;
+                    }
+                    NodeData::Phylum(_) => current = *pred,
+                    NodeData::Link(_) => break,
+                }
+            }
+        }
+        res
+    }
+
+    pub fn get_line_content(&self, node_id: &Uuid, choice_id: Option<&Uuid>) -> Result<Option<&str>> {
+        let node = self
+            .nodes
+            .get(node_id)
+            .ok_or(anyhow!("node {node_id} does not exist"))?;
+        match (&node.data, choice_id) {
+            (NodeData::Dialog(d), None) => Ok(d.content.as_deref()),
+            (NodeData::Choices(c), Some(choice_id)) => Ok(Some(&c.get_choice(choice_id)?.content)),
+            _ => bail!("node {node_id} does not hold the requested line"),
+        }
+    }
+
+    pub fn set_line_content(
+        &mut self,
+        node_id: &Uuid,
+        choice_id: Option<&Uuid>,
+        content: &str,
+    ) -> Result<()> {
+        let node = self
+            .nodes
+            .get_mut(node_id)
+            .ok_or(anyhow!("node {node_id} does not exist"))?;
+        match (&mut node.data, choice_id) {
+            (NodeData::Dialog(d), None) => d.content = Some(String::from(content)),
+            (NodeData::Choices(c), Some(choice_id)) => {
+                c.get_choice_mut(choice_id)?.content = String::from(content)
+            }
+            _ => bail!("node {node_id} does not hold the requested line"),
+        }
+        Ok(())
+    }
+
     pub fn get_phylums_map(&self) -> HashMap<Uuid, &Phylum> {
         let mut res = HashMap::new();
         for (_, v) in &self.nodes {
@@ -302,6 +904,32 @@ impl Dialog {
 
 }
 
+impl Choices {
+    pub fn get_choice(&self, choice_id: &Uuid) -> Result<&Choice> {
+        self.choices
+            .iter()
+            .find(|c| c.id == *choice_id)
+            .ok_or(anyhow!("choice {choice_id} does not exist"))
+    }
+
+    pub fn get_choice_mut(&mut self, choice_id: &Uuid) -> Result<&mut Choice> {
+        self.choices
+            .iter_mut()
+            .find(|c| c.id == *choice_id)
+            .ok_or(anyhow!("choice {choice_id} does not exist"))
+    }
+}
+
+impl DialogNode {
+    pub fn get_character_id(&self) -> Option<&Uuid> {
+        self.character_id.as_ref()
+    }
+
+    pub fn get_line_id(&self) -> Option<&String> {
+        self.line_id.as_ref()
+    }
+}
+
 impl Coherent for DialogNode {
     fn enforce_coherence(&self, dialog: &Dialog) -> Result<()> {
         if let Some(n) = self.next_node {
@@ -326,6 +954,17 @@ impl Coherent for Choices {
     }
 }
 
+impl Coherent for DialogLink {
+    fn enforce_coherence(&self, dialog: &Dialog) -> Result<()> {
+        if let Some(n) = self.next_node {
+            if !dialog.nodes.contains_key(&n) {
+                bail!("incoherent key found : {n}")
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Coherent for Phylum {
     fn enforce_coherence(&self, dialog: &Dialog) -> Result<()> {
         for condition in &self.branches {
@@ -378,6 +1017,31 @@ impl VariableContainer for Conditions {
     }
 }
 
+impl NecessityExpression {
+    /// Leaves of the expression, from left to right.
+    pub fn get_var_necessities(&self) -> Vec<&VarNecessity> {
+        match self {
+            NecessityExpression::Var(v) => vec![v],
+            NecessityExpression::Tree(t) => {
+                let mut res = t.left.get_var_necessities();
+                res.extend(t.right.get_var_necessities());
+                res
+            }
+        }
+    }
+
+    fn get_var_necessities_mut(&mut self) -> Vec<&mut VarNecessity> {
+        match self {
+            NecessityExpression::Var(v) => vec![v],
+            NecessityExpression::Tree(t) => {
+                let mut res = t.left.get_var_necessities_mut();
+                res.extend(t.right.get_var_necessities_mut());
+                res
+            }
+        }
+    }
+}
+
 impl VariableCoherent for TreeNecessity {
     fn enforce_variable_coherence(&self, vars: &HashSet<&Uuid>) -> Result<()> {
         self.left.enforce_variable_coherence(vars)?;
@@ -406,3 +1070,326 @@ impl VariableContainer for VarNecessity {
         list.push(self.var_id);
     }
 }
+
+impl LineDelimited for Dialog {
+    const COLLECTIONS: &'static [&'static str] = &["nodes"];
+}
+
+impl LineDelimited for DialogMetadata {
+    const COLLECTIONS: &'static [&'static str] = &["data", "groups"];
+}
+
+impl From<&Dialog> for proto::Dialog {
+    fn from(dialog: &Dialog) -> Self {
+        let mut nodes: Vec<&Node> = dialog.nodes.values().collect();
+        nodes.sort_by_key(|n| n.id);
+        Self {
+            id: dialog.id.to_string(),
+            name: dialog.name.clone(),
+            root_node: dialog.root_node.map(|n| n.to_string()),
+            characters_ids: dialog.characters_ids.iter().map(Uuid::to_string).collect(),
+            created_at: dialog.created_at.to_rfc3339(),
+            main_character: dialog.main_character.to_string(),
+            nodes: nodes.into_iter().map(proto::Node::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::Dialog> for Dialog {
+    type Error = anyhow::Error;
+
+    fn try_from(dialog: proto::Dialog) -> Result<Self> {
+        let mut nodes = HashMap::new();
+        for node in dialog.nodes {
+            let node = Node::try_from(node)?;
+            nodes.insert(node.id, node);
+        }
+        Ok(Self {
+            id: parse_id(&dialog.id)?,
+            name: dialog.name,
+            root_node: parse_opt_id(dialog.root_node.as_ref())?,
+            characters_ids: dialog
+                .characters_ids
+                .iter()
+                .map(|id| parse_id(id))
+                .collect::<Result<Vec<Uuid>>>()?,
+            created_at: DateTime::parse_from_rfc3339(&dialog.created_at)?.with_timezone(&Utc),
+            main_character: parse_id(&dialog.main_character)?,
+            nodes,
+        })
+    }
+}
+
+impl From<&Node> for proto::Node {
+    fn from(node: &Node) -> Self {
+        let kind = match &node.data {
+            NodeData::Dialog(d) => proto::node_data::Kind::Dialog(proto::DialogNode {
+                line_id: d.line_id.clone(),
+                next_node: d.next_node.map(|n| n.to_string()),
+                character_id: d.character_id.map(|c| c.to_string()),
+                content_link: d.content_link.map(|c| c.to_string()),
+                content: d.content.clone(),
+            }),
+            NodeData::Phylum(p) => proto::node_data::Kind::Phylum(proto::Phylum {
+                id: p.id.to_string(),
+                name: p.name.clone(),
+                branches: p.branches.iter().map(proto::Conditions::from).collect(),
+            }),
+            NodeData::Choices(c) => proto::node_data::Kind::Choices(proto::Choices {
+                choices: c
+                    .choices
+                    .iter()
+                    .map(|choice| proto::Choice {
+                        id: choice.id.to_string(),
+                        content: choice.content.clone(),
+                        next_node: choice.next_node.map(|n| n.to_string()),
+                    })
+                    .collect(),
+            }),
+            NodeData::Link(l) => proto::node_data::Kind::Link(proto::DialogLink {
+                dialog_id: l.dialog_id.to_string(),
+                node_id: l.node_id.map(|n| n.to_string()),
+                next_node: l.next_node.map(|n| n.to_string()),
+            }),
+        };
+        Self {
+            id: node.id.to_string(),
+            pos_x: node.pos_x,
+            pos_y: node.pos_y,
+            data: Some(proto::NodeData { kind: Some(kind) }),
+        }
+    }
+}
+
+impl TryFrom<proto::Node> for Node {
+    type Error = anyhow::Error;
+
+    fn try_from(node: proto::Node) -> Result<Self> {
+        let id = parse_id(&node.id)?;
+        let kind = node
+            .data
+            .and_then(|d| d.kind)
+            .ok_or(anyhow!("node {id} has no data"))?;
+        let data = match kind {
+            proto::node_data::Kind::Dialog(d) => NodeData::Dialog(DialogNode {
+                line_id: d.line_id,
+                next_node: parse_opt_id(d.next_node.as_ref())?,
+                character_id: parse_opt_id(d.character_id.as_ref())?,
+                content_link: parse_opt_id(d.content_link.as_ref())?,
+                content: d.content,
+            }),
+            proto::node_data::Kind::Phylum(p) => NodeData::Phylum(Phylum {
+                id: parse_id(&p.id)?,
+                name: p.name,
+                branches: p
+                    .branches
+                    .into_iter()
+                    .map(Conditions::try_from)
+                    .collect::<Result<Vec<Conditions>>>()?,
+            }),
+            proto::node_data::Kind::Choices(c) => NodeData::Choices(Choices {
+                choices: c
+                    .choices
+                    .into_iter()
+                    .map(|choice| {
+                        Ok(Choice {
+                            id: parse_id(&choice.id)?,
+                            content: choice.content,
+                            next_node: parse_opt_id(choice.next_node.as_ref())?,
+                        })
+                    })
+                    .collect::<Result<Vec<Choice>>>()?,
+            }),
+            proto::node_data::Kind::Link(l) => NodeData::Link(DialogLink {
+                dialog_id: parse_id(&l.dialog_id)?,
+                node_id: parse_opt_id(l.node_id.as_ref())?,
+                next_node: parse_opt_id(l.next_node.as_ref())?,
+            }),
+        };
+        Ok(Self {
+            id,
+            pos_x: node.pos_x,
+            pos_y: node.pos_y,
+            data,
+        })
+    }
+}
+
+impl From<&Conditions> for proto::Conditions {
+    fn from(conditions: &Conditions) -> Self {
+        Self {
+            id: conditions.id.to_string(),
+            priority: conditions.priority,
+            name: conditions.name.clone(),
+            necessities: conditions
+                .necessities
+                .as_ref()
+                .map(proto::NecessityExpression::from),
+            next_node: conditions.next_node.map(|n| n.to_string()),
+        }
+    }
+}
+
+impl TryFrom<proto::Conditions> for Conditions {
+    type Error = anyhow::Error;
+
+    fn try_from(conditions: proto::Conditions) -> Result<Self> {
+        Ok(Self {
+            id: parse_id(&conditions.id)?,
+            priority: conditions.priority,
+            name: conditions.name,
+            necessities: conditions
+                .necessities
+                .map(NecessityExpression::try_from)
+                .transpose()?,
+            next_node: parse_opt_id(conditions.next_node.as_ref())?,
+        })
+    }
+}
+
+impl From<&NecessityExpression> for proto::NecessityExpression {
+    fn from(expression: &NecessityExpression) -> Self {
+        let kind = match expression {
+            NecessityExpression::Tree(t) => {
+                proto::necessity_expression::Kind::Tree(proto::TreeNecessity {
+                    left: Some(Box::new(t.left.as_ref().into())),
+                    operator: t.operator.clone(),
+                    right: Some(Box::new(t.right.as_ref().into())),
+                })
+            }
+            NecessityExpression::Var(v) => {
+                proto::necessity_expression::Kind::Var(proto::VarNecessity {
+                    var_id: v.var_id.to_string(),
+                    necessary_state: v.necessary_state.clone(),
+                })
+            }
+        };
+        Self { kind: Some(kind) }
+    }
+}
+
+impl TryFrom<proto::NecessityExpression> for NecessityExpression {
+    type Error = anyhow::Error;
+
+    fn try_from(expression: proto::NecessityExpression) -> Result<Self> {
+        match expression.kind.ok_or(anyhow!("empty necessity expression"))? {
+            proto::necessity_expression::Kind::Tree(t) => {
+                let left = t.left.ok_or(anyhow!("necessity tree without left side"))?;
+                let right = t.right.ok_or(anyhow!("necessity tree without right side"))?;
+                Ok(NecessityExpression::Tree(TreeNecessity {
+                    left: Box::new(NecessityExpression::try_from(*left)?),
+                    operator: t.operator,
+                    right: Box::new(NecessityExpression::try_from(*right)?),
+                }))
+            }
+            proto::necessity_expression::Kind::Var(v) => {
+                Ok(NecessityExpression::Var(VarNecessity {
+                    var_id: parse_id(&v.var_id)?,
+                    necessary_state: v.necessary_state,
+                }))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dialog(group_id: Option<Uuid>, order: usize) -> SimpleDialog {
+        SimpleDialog {
+            id: Uuid::new_v4(),
+            name: String::from("Tavern"),
+            main_character: Uuid::new_v4(),
+            characters: vec![],
+            order,
+            group_id,
+        }
+    }
+
+    fn add_dialog(metadata: &mut DialogMetadata, group_id: Option<Uuid>, order: usize) -> Uuid {
+        let d = dialog(group_id, order);
+        let id = d.id;
+        metadata.data.insert(id, d);
+        id
+    }
+
+    #[test]
+    fn group_cannot_be_moved_into_its_descendant() {
+        let mut metadata = DialogMetadata::new();
+        let act = metadata.create_group("Act I", None).unwrap().id;
+        let scene = metadata.create_group("Scene 1", Some(act)).unwrap().id;
+        assert!(metadata.move_group(&act, Some(scene), 0).is_err());
+        assert!(metadata.move_group(&act, Some(act), 0).is_err());
+        assert_eq!(metadata.groups[&act].parent_id, None);
+    }
+
+    #[test]
+    fn moving_a_group_renumbers_both_parents() {
+        let mut metadata = DialogMetadata::new();
+        let first = metadata.create_group("Act I", None).unwrap().id;
+        let second = metadata.create_group("Act II", None).unwrap().id;
+        let third = metadata.create_group("Act III", None).unwrap().id;
+        metadata.move_group(&first, Some(third), 0).unwrap();
+        assert_eq!(metadata.groups[&second].order, 0);
+        assert_eq!(metadata.groups[&third].order, 1);
+        assert_eq!(metadata.groups[&first].order, 0);
+        assert_eq!(metadata.groups[&first].parent_id, Some(third));
+    }
+
+    #[test]
+    fn deleting_a_group_moves_its_content_to_its_parent() {
+        let mut metadata = DialogMetadata::new();
+        let act = metadata.create_group("Act I", None).unwrap().id;
+        let scene = metadata.create_group("Scene 1", Some(act)).unwrap().id;
+        let sub = metadata.create_group("Sub", Some(scene)).unwrap().id;
+        let in_act = add_dialog(&mut metadata, Some(act), 0);
+        let in_scene = add_dialog(&mut metadata, Some(scene), 0);
+        metadata.delete_group(&scene).unwrap();
+        assert_eq!(metadata.groups[&sub].parent_id, Some(act));
+        assert_eq!(metadata.data[&in_scene].group_id, Some(act));
+        let mut orders = vec![metadata.data[&in_act].order, metadata.data[&in_scene].order];
+        orders.sort();
+        assert_eq!(orders, vec![0, 1]);
+        assert!(metadata.delete_group(&scene).is_err());
+    }
+
+    #[test]
+    fn dialog_is_in_every_ancestor_group() {
+        let mut metadata = DialogMetadata::new();
+        let act = metadata.create_group("Act I", None).unwrap().id;
+        let scene = metadata.create_group("Scene 1", Some(act)).unwrap().id;
+        let other = metadata.create_group("Act II", None).unwrap().id;
+        let dialog_id = add_dialog(&mut metadata, Some(scene), 0);
+        assert!(metadata.is_in_group(&dialog_id, &scene));
+        assert!(metadata.is_in_group(&dialog_id, &act));
+        assert!(!metadata.is_in_group(&dialog_id, &other));
+    }
+
+    #[test]
+    fn nested_groups_forming_a_cycle_are_refused_and_do_not_loop() {
+        let mut metadata = DialogMetadata::new();
+        let act = metadata.create_group("Act I", None).unwrap().id;
+        let scene = metadata.create_group("Scene 1", Some(act)).unwrap().id;
+        let other = metadata.create_group("Act II", None).unwrap().id;
+        let dialog_id = add_dialog(&mut metadata, Some(scene), 0);
+        assert!(metadata.enforce_groups_coherence().is_ok());
+
+        metadata.groups.get_mut(&act).unwrap().parent_id = Some(scene);
+        assert!(metadata.enforce_groups_coherence().is_err());
+        assert!(!metadata.is_in_group(&dialog_id, &other));
+        assert!(metadata.is_in_group(&dialog_id, &act));
+    }
+
+    #[test]
+    fn references_to_unknown_groups_are_refused() {
+        let mut metadata = DialogMetadata::new();
+        let act = metadata.create_group("Act I", None).unwrap().id;
+        metadata.groups.get_mut(&act).unwrap().parent_id = Some(Uuid::new_v4());
+        assert!(metadata.enforce_groups_coherence().is_err());
+
+        let mut metadata = DialogMetadata::new();
+        add_dialog(&mut metadata, Some(Uuid::new_v4()), 0);
+        assert!(metadata.enforce_groups_coherence().is_err());
+    }
+}
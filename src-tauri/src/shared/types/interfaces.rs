@@ -33,6 +33,12 @@ pub trait Persisted<T> {
     fn save(&self) -> Result<()>;
 }
 
+/// Implemented by the structs persisted as line delimited json.
+/// Each element of the listed collections is written on its own line.
+pub trait LineDelimited {
+    const COLLECTIONS: &'static [&'static str];
+}
+
 pub struct FSUploader {}
 
 impl Uploader for FSUploader {}
@@ -0,0 +1,318 @@
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::shared::{hash::content_hash, types::interfaces::LineDelimited};
+
+pub const DEFAULT_SOURCE_LOCALE: &str = "en";
+
+/// A language the project can be shipped in.
+/// When a string is missing in this locale, the
+/// fallback locale is used, and ultimately the
+/// source locale of the project.
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct Locale {
+    code: String,
+    name: String,
+    fallback: Option<String>,
+}
+
+/// Project level list of locales.
+/// The source locale is the one the dialogs are written in:
+/// its text is the content of the dialogs and characters themselves
+/// and never lives in a string table.
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct LocaleSettings {
+    source_locale: String,
+    locales: Vec<Locale>,
+}
+
+/// A translated string, along with the hash of the source
+/// text it was translated from.
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct LocalizedString {
+    pub text: String,
+    pub source_hash: String,
+}
+
+/// Strings of one locale, keyed by node id for the content
+/// of dialog nodes, by choice id for choices and by character
+/// id for display names.
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct LocaleTable {
+    pub entries: BTreeMap<Uuid, LocalizedString>,
+}
+
+/// A line of a dialog as seen in a given locale.
+/// `text` is the translation stored for the locale, while
+/// `resolved_text` is what would be displayed once the fallback
+/// rules are applied, `resolved_locale` being the locale it was taken from.
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct LocalizedLine {
+    pub node_id: Uuid,
+    pub choice_id: Option<Uuid>,
+    pub source_text: Option<String>,
+    pub text: Option<String>,
+    pub resolved_text: Option<String>,
+    pub resolved_locale: Option<String>,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct LocalizedDialog {
+    pub dialog_id: Uuid,
+    pub locale: String,
+    pub lines: Vec<LocalizedLine>,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct LocalizedCharacterName {
+    pub character_id: Uuid,
+    pub source_text: String,
+    pub text: Option<String>,
+    pub resolved_text: String,
+    pub resolved_locale: String,
+}
+
+/// Number of strings of a locale in each translation state.
+/// A translation is stale when the source text changed
+/// after it was recorded.
+#[derive(TS, Serialize, Deserialize, Debug, Default, Clone, Copy)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct TranslationCounts {
+    pub total: usize,
+    pub translated: usize,
+    pub missing: usize,
+    pub stale: usize,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct DialogTranslationProgress {
+    pub dialog_id: Uuid,
+    pub dialog_name: String,
+    pub counts: TranslationCounts,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct LocaleProgress {
+    pub locale: String,
+    pub counts: TranslationCounts,
+    pub characters: TranslationCounts,
+    pub dialogs: Vec<DialogTranslationProgress>,
+}
+
+/// String tables of a locale and of its fallbacks, in resolution order.
+/// The source locale has no table since its text is the content itself.
+pub struct FallbackTables {
+    tables: Vec<(String, Option<LocaleTable>)>,
+}
+
+impl Locale {
+    pub fn new(code: &str, name: &str, fallback: Option<&str>) -> Self {
+        Self {
+            code: String::from(code),
+            name: String::from(name),
+            fallback: fallback.map(String::from),
+        }
+    }
+
+    pub fn get_code(&self) -> &String {
+        &self.code
+    }
+
+    pub fn validate_code(code: &str) -> Result<()> {
+        if code.is_empty() || code.len() > 35 {
+            bail!("locale code must be between 1 and 35 characters long")
+        }
+        if !code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!("locale code {code} can only contain ascii letters, digits, '-' and '_'")
+        }
+        Ok(())
+    }
+}
+
+impl LocaleSettings {
+    pub fn new() -> Self {
+        Self {
+            source_locale: String::from(DEFAULT_SOURCE_LOCALE),
+            locales: vec![Locale::new(DEFAULT_SOURCE_LOCALE, "English", None)],
+        }
+    }
+
+    pub fn get_source_locale(&self) -> &String {
+        &self.source_locale
+    }
+
+    pub fn get_locales(&self) -> &Vec<Locale> {
+        &self.locales
+    }
+
+    pub fn is_source(&self, locale: &str) -> bool {
+        self.source_locale == locale
+    }
+
+    pub fn get_locale(&self, code: &str) -> Result<&Locale> {
+        self.locales
+            .iter()
+            .find(|l| l.code == code)
+            .ok_or(anyhow!("locale {code} is not part of the project"))
+    }
+
+    /// Enforces that every code is valid and unique, that the source
+    /// locale is one of the locales and that fallbacks point to
+    /// existing locales without cycles.
+    pub fn enforce_coherence(&self) -> Result<()> {
+        let mut codes = HashSet::new();
+        for locale in &self.locales {
+            Locale::validate_code(&locale.code)?;
+            if !codes.insert(&locale.code) {
+                bail!("locale {} is defined twice", locale.code)
+            }
+        }
+        if !codes.contains(&self.source_locale) {
+            bail!(
+                "source locale {} is not part of the locales",
+                self.source_locale
+            )
+        }
+        for locale in &self.locales {
+            if let Some(fallback) = &locale.fallback {
+                if !codes.contains(fallback) {
+                    bail!(
+                        "fallback {fallback} of locale {} does not exist",
+                        locale.code
+                    )
+                }
+            }
+            self.get_fallback_chain(&locale.code)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the locales to look into, in order, to resolve a
+    /// string of the given locale. Always ends with the source locale.
+    pub fn get_fallback_chain(&self, code: &str) -> Result<Vec<&str>> {
+        let mut chain: Vec<&str> = vec![];
+        let mut current = Some(self.get_locale(code)?);
+        while let Some(locale) = current {
+            if chain.contains(&locale.code.as_str()) {
+                bail!("fallback cycle detected for locale {code}")
+            }
+            chain.push(&locale.code);
+            current = match &locale.fallback {
+                Some(f) => Some(self.get_locale(f)?),
+                None => None,
+            };
+        }
+        if !chain.contains(&self.source_locale.as_str()) {
+            chain.push(&self.source_locale);
+        }
+        Ok(chain)
+    }
+}
+
+impl LocaleTable {
+    pub fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+        }
+    }
+
+    pub fn get_text(&self, id: &Uuid) -> Option<&String> {
+        self.entries.get(id).map(|e| &e.text)
+    }
+
+    /// Counts the translation state of the given strings,
+    /// identified by their id and their current source text.
+    pub fn count<'a>(
+        &self,
+        strings: impl Iterator<Item = (&'a Uuid, &'a str)>,
+    ) -> TranslationCounts {
+        let mut counts = TranslationCounts::default();
+        for (id, source) in strings {
+            counts.total += 1;
+            match self.entries.get(id) {
+                None => counts.missing += 1,
+                Some(e) if e.source_hash != content_hash(source) => counts.stale += 1,
+                Some(_) => counts.translated += 1,
+            }
+        }
+        counts
+    }
+
+    /// Sets the translation of a string. An empty text removes it,
+    /// so that the fallback rules apply again.
+    pub fn set_text(&mut self, id: Uuid, text: &str, source_hash: String) {
+        if text.is_empty() {
+            self.entries.remove(&id);
+            return;
+        }
+        self.entries.insert(
+            id,
+            LocalizedString {
+                text: String::from(text),
+                source_hash,
+            },
+        );
+    }
+}
+
+impl TranslationCounts {
+    pub fn add(&mut self, other: &TranslationCounts) {
+        self.total += other.total;
+        self.translated += other.translated;
+        self.missing += other.missing;
+        self.stale += other.stale;
+    }
+}
+
+impl FallbackTables {
+    pub fn new(tables: Vec<(String, Option<LocaleTable>)>) -> Self {
+        Self { tables }
+    }
+
+    /// Text stored for the requested locale itself, without fallback.
+    pub fn get_own_text(&self, id: &Uuid, source_text: Option<&str>) -> Option<String> {
+        match self.tables.first() {
+            Some((_, Some(table))) => table.get_text(id).cloned(),
+            Some((_, None)) => source_text.map(String::from),
+            None => None,
+        }
+    }
+
+    /// Returns the text to display for the string and the locale it was taken from.
+    pub fn resolve(&self, id: &Uuid, source_text: Option<&str>) -> Option<(String, String)> {
+        for (locale, table) in &self.tables {
+            let text = match table {
+                Some(t) => t.get_text(id).cloned(),
+                None => source_text.map(String::from),
+            };
+            if let Some(text) = text {
+                return Some((text, locale.clone()));
+            }
+        }
+        None
+    }
+}
+
+impl LineDelimited for LocaleSettings {
+    const COLLECTIONS: &'static [&'static str] = &["locales"];
+}
+
+impl LineDelimited for LocaleTable {
+    const COLLECTIONS: &'static [&'static str] = &["entries"];
+}
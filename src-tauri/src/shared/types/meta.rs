@@ -1,10 +1,14 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use anyhow::{anyhow, bail, Result};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::shared::types::{dialog::PhylumDiff, interfaces::Identified, variables::VariableStore};
+use crate::shared::types::{
+    dialog::PhylumDiff,
+    interfaces::{Identified, LineDelimited},
+    variables::VariableStore,
+};
 
 type VarIdentifier = Uuid;
 type DialogNodeIdentifier = Uuid;
@@ -14,6 +18,61 @@ pub struct VarToPhylum {
     pub data: HashMap<VarIdentifier, Vec<DialogNodeIdentifier>>,
 }
 
+/// Keeps track of the last line id issued for each prefix,
+/// so that a line id is never given twice, even after the
+/// line it identified was deleted.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LineIdRegistry {
+    pub counters: BTreeMap<String, u32>,
+}
+
+impl LineIdRegistry {
+    pub fn new() -> Self {
+        Self {
+            counters: BTreeMap::new(),
+        }
+    }
+
+    /// Builds the prefix of the line ids of a dialog from its name,
+    /// e.g. `DLG_TAVERN` for a dialog named "Tavern".
+    pub fn prefix_for(dialog_name: &str) -> String {
+        let mut prefix = String::from("DLG");
+        let mut in_word = false;
+        for c in dialog_name.chars() {
+            if prefix.len() >= 24 {
+                break;
+            }
+            if c.is_ascii_alphanumeric() {
+                if !in_word {
+                    prefix.push('_');
+                    in_word = true;
+                }
+                prefix.push(c.to_ascii_uppercase());
+            } else {
+                in_word = false;
+            }
+        }
+        prefix
+    }
+
+    pub fn next_id(&mut self, prefix: &str) -> String {
+        let counter = self.counters.entry(String::from(prefix)).or_insert(0);
+        *counter += 1;
+        format!("{prefix}_{:04}", counter)
+    }
+
+    /// Whether the id was given by the registry, under any prefix.
+    pub fn has_issued(&self, line_id: &str) -> bool {
+        let Some((prefix, number)) = line_id.rsplit_once('_') else {
+            return false;
+        };
+        match (self.counters.get(prefix), number.parse::<u32>()) {
+            (Some(counter), Ok(n)) => n >= 1 && n <= *counter,
+            _ => false,
+        }
+    }
+}
+
 impl VarToPhylum {
     pub fn enforce_free_existing_var(&self, id: &VarIdentifier) -> Result<()> {
         let var = self.data.get(id).ok_or(anyhow!("{id} was not present"))?;
@@ -29,6 +88,11 @@ impl VarToPhylum {
         }
     }
 
+    /// Phylums using the variable in their conditions.
+    pub fn get_phylums(&self, id: &VarIdentifier) -> &[DialogNodeIdentifier] {
+        self.data.get(id).map(|p| p.as_slice()).unwrap_or(&[])
+    }
+
     pub fn mutate_to_match_diffs(&mut self, diffs: PhylumDiff) -> Result<()> {
         for deleted in diffs.deleted {
             let phylum_id = *deleted.get_id();
@@ -62,3 +126,11 @@ impl VarToPhylum {
     }
 
 }
+
+impl LineDelimited for VarToPhylum {
+    const COLLECTIONS: &'static [&'static str] = &["data"];
+}
+
+impl LineDelimited for LineIdRegistry {
+    const COLLECTIONS: &'static [&'static str] = &["counters"];
+}
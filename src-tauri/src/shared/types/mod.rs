@@ -6,3 +6,13 @@ pub mod dialog;
 pub mod variables;
 pub mod meta;
 pub mod gitter;
+pub mod spreadsheet;
+pub mod localization;
+pub mod translation;
+pub mod stats;
+pub mod voice;
+pub mod proto;
+pub mod comment;
+pub mod user;
+pub mod search;
+pub mod watcher;
@@ -8,6 +8,8 @@ use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 use uuid::Uuid;
 
+use crate::shared::types::interfaces::LineDelimited;
+
 #[derive(TS)]
 #[ts(export, export_to = "../../src/bindings/")]
 #[derive(Debug, Serialize, Deserialize)]
@@ -28,6 +30,41 @@ pub struct ProjectMetadata {
     branches: Vec<String>,
 }
 
+/// Version of the on-disk format of the files of a project.
+/// Projects created before versioning have no format file
+/// and are considered to be at version 1.
+pub const CURRENT_FORMAT_VERSION: u32 = 2;
+pub const UNVERSIONED_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectFormat {
+    pub version: u32,
+    #[serde(default)]
+    pub storage: StorageBackend,
+}
+
+/// Encoding of the dialogs, variables and characters of a project.
+#[derive(TS)]
+#[ts(export, export_to = "../../src/bindings/")]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StorageBackend {
+    #[default]
+    Json,
+    Protobuf,
+}
+
+/// Outcome of the opening of a project, listing the migrations
+/// that were applied to bring it to the current format.
+#[derive(TS)]
+#[ts(export, export_to = "../../src/bindings/")]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub applied: Vec<String>,
+    pub backup: Option<String>,
+}
+
 pub type AtomicProject = Arc<RwLock<Project>>;
 pub type AtomicProjects = Arc<RwLock<Vec<AtomicProject>>>;
 
@@ -55,6 +92,10 @@ impl Project {
         &self.id
     }
 
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
     pub fn set_last_commit(&mut self, date: DateTime<Utc>) {
         self.metadata.last_commit = Some(date);
     }
@@ -76,3 +117,23 @@ impl Project {
         return &self.path;
     }
 }
+
+impl ProjectFormat {
+    pub fn current() -> Self {
+        Self {
+            version: CURRENT_FORMAT_VERSION,
+            storage: StorageBackend::Json,
+        }
+    }
+
+    pub fn unversioned() -> Self {
+        Self {
+            version: UNVERSIONED_FORMAT_VERSION,
+            storage: StorageBackend::Json,
+        }
+    }
+}
+
+impl LineDelimited for ProjectFormat {
+    const COLLECTIONS: &'static [&'static str] = &[];
+}
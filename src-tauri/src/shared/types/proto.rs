@@ -0,0 +1,403 @@
+//! Protobuf messages of the storage backend, mirroring `proto/open_dialog.proto`.
+//! Conversions from and to the domain types live next to the domain types.
+
+use anyhow::{anyhow, Result};
+use uuid::Uuid;
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Dialog {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(string, optional, tag = "3")]
+    pub root_node: Option<String>,
+    #[prost(string, repeated, tag = "4")]
+    pub characters_ids: Vec<String>,
+    #[prost(string, tag = "5")]
+    pub created_at: String,
+    #[prost(string, tag = "6")]
+    pub main_character: String,
+    #[prost(message, repeated, tag = "7")]
+    pub nodes: Vec<Node>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Node {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(int32, tag = "2")]
+    pub pos_x: i32,
+    #[prost(int32, tag = "3")]
+    pub pos_y: i32,
+    #[prost(message, optional, tag = "4")]
+    pub data: Option<NodeData>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NodeData {
+    #[prost(oneof = "node_data::Kind", tags = "1, 2, 3, 4")]
+    pub kind: Option<node_data::Kind>,
+}
+
+pub mod node_data {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Kind {
+        #[prost(message, tag = "1")]
+        Dialog(super::DialogNode),
+        #[prost(message, tag = "2")]
+        Phylum(super::Phylum),
+        #[prost(message, tag = "3")]
+        Choices(super::Choices),
+        #[prost(message, tag = "4")]
+        Link(super::DialogLink),
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DialogNode {
+    #[prost(string, optional, tag = "1")]
+    pub line_id: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    pub next_node: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub character_id: Option<String>,
+    #[prost(string, optional, tag = "4")]
+    pub content_link: Option<String>,
+    #[prost(string, optional, tag = "5")]
+    pub content: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DialogLink {
+    #[prost(string, tag = "1")]
+    pub dialog_id: String,
+    #[prost(string, optional, tag = "2")]
+    pub node_id: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub next_node: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Choices {
+    #[prost(message, repeated, tag = "1")]
+    pub choices: Vec<Choice>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Choice {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub content: String,
+    #[prost(string, optional, tag = "3")]
+    pub next_node: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Phylum {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, optional, tag = "2")]
+    pub name: Option<String>,
+    #[prost(message, repeated, tag = "3")]
+    pub branches: Vec<Conditions>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Conditions {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(int32, tag = "2")]
+    pub priority: i32,
+    #[prost(string, tag = "3")]
+    pub name: String,
+    #[prost(message, optional, tag = "4")]
+    pub necessities: Option<NecessityExpression>,
+    #[prost(string, optional, tag = "5")]
+    pub next_node: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NecessityExpression {
+    #[prost(oneof = "necessity_expression::Kind", tags = "1, 2")]
+    pub kind: Option<necessity_expression::Kind>,
+}
+
+pub mod necessity_expression {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Kind {
+        #[prost(message, tag = "1")]
+        Tree(super::TreeNecessity),
+        #[prost(message, tag = "2")]
+        Var(super::VarNecessity),
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TreeNecessity {
+    #[prost(message, optional, boxed, tag = "1")]
+    pub left: Option<Box<NecessityExpression>>,
+    #[prost(string, tag = "2")]
+    pub operator: String,
+    #[prost(message, optional, boxed, tag = "3")]
+    pub right: Option<Box<NecessityExpression>>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VarNecessity {
+    #[prost(string, tag = "1")]
+    pub var_id: String,
+    #[prost(string, tag = "2")]
+    pub necessary_state: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VariableStore {
+    #[prost(message, repeated, tag = "1")]
+    pub data: Vec<Variable>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Variable {
+    #[prost(oneof = "variable::Kind", tags = "1, 2, 3, 4")]
+    pub kind: Option<variable::Kind>,
+}
+
+pub mod variable {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Kind {
+        #[prost(message, tag = "1")]
+        Global(super::GlobalVariable),
+        #[prost(message, tag = "2")]
+        GlobalChar(super::GlobalCharacterVariable),
+        #[prost(message, tag = "3")]
+        Char(super::CharacterVariable),
+        #[prost(message, tag = "4")]
+        Dialog(super::DialogVariable),
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GlobalVariable {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(string, tag = "3")]
+    pub current_state: String,
+    #[prost(string, repeated, tag = "4")]
+    pub potential_states: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GlobalCharacterVariable {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(string, repeated, tag = "3")]
+    pub potential_states: Vec<String>,
+    #[prost(message, repeated, tag = "4")]
+    pub characters: Vec<CharacterVariableState>,
+    #[prost(string, optional, tag = "5")]
+    pub group_id: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CharacterVariableState {
+    #[prost(string, tag = "1")]
+    pub current_state: String,
+    #[prost(string, tag = "2")]
+    pub character_id: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CharacterVariable {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(string, tag = "3")]
+    pub current_state: String,
+    #[prost(string, repeated, tag = "4")]
+    pub potential_states: Vec<String>,
+    #[prost(string, tag = "5")]
+    pub character_id: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DialogVariable {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(string, tag = "3")]
+    pub current_state: String,
+    #[prost(string, repeated, tag = "4")]
+    pub potential_states: Vec<String>,
+    #[prost(string, tag = "5")]
+    pub dialog_id: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Character {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub display_name: String,
+    #[prost(string, optional, tag = "3")]
+    pub first_name: Option<String>,
+    #[prost(string, optional, tag = "4")]
+    pub last_name: Option<String>,
+    #[prost(string, optional, tag = "5")]
+    pub description: Option<String>,
+    #[prost(string, optional, tag = "6")]
+    pub description_link: Option<String>,
+    #[prost(string, optional, tag = "7")]
+    pub portrait_link: Option<String>,
+    #[prost(string, optional, tag = "8")]
+    pub artwork_link: Option<String>,
+    #[prost(string, optional, tag = "9")]
+    pub background_link: Option<String>,
+    #[prost(string, repeated, tag = "10")]
+    pub tags: Vec<String>,
+    #[prost(string, repeated, tag = "11")]
+    pub group_ids: Vec<String>,
+    #[prost(message, repeated, tag = "12")]
+    pub attributes: Vec<AttributeValue>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AttributeValue {
+    #[prost(string, tag = "1")]
+    pub attribute_id: String,
+    #[prost(oneof = "attribute_value::Value", tags = "2, 3, 4, 5, 6")]
+    pub value: Option<attribute_value::Value>,
+}
+
+pub mod attribute_value {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Value {
+        #[prost(string, tag = "2")]
+        Text(String),
+        #[prost(double, tag = "3")]
+        Number(f64),
+        #[prost(string, tag = "4")]
+        EnumValue(String),
+        #[prost(string, tag = "5")]
+        Image(String),
+        #[prost(string, tag = "6")]
+        CharacterId(String),
+    }
+}
+
+pub fn parse_id(id: &str) -> Result<Uuid> {
+    Uuid::parse_str(id).map_err(|e| anyhow!("invalid id {id} in protobuf file: {e}"))
+}
+
+pub fn parse_opt_id(id: Option<&String>) -> Result<Option<Uuid>> {
+    id.map(|i| parse_id(i)).transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+    use serde::{de::DeserializeOwned, Serialize};
+    use serde_json::json;
+
+    use super::*;
+    use crate::shared::types::{character, dialog, variables};
+
+    /// Encodes the value, decodes it back, and compares both through json.
+    fn assert_round_trip<T, P>(value: serde_json::Value)
+    where
+        T: Serialize + DeserializeOwned + TryFrom<P, Error = anyhow::Error>,
+        P: Message + Default + for<'a> From<&'a T>,
+    {
+        let original: T = serde_json::from_value(value).unwrap();
+        let bytes = P::from(&original).encode_to_vec();
+        let decoded = T::try_from(P::decode(bytes.as_slice()).unwrap()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&original).unwrap(),
+            serde_json::to_value(&decoded).unwrap()
+        );
+    }
+
+    fn id() -> String {
+        Uuid::new_v4().to_string()
+    }
+
+    #[test]
+    fn dialog_round_trip() {
+        let (a, b, c, d) = (id(), id(), id(), id());
+        assert_round_trip::<dialog::Dialog, Dialog>(json!({
+            "id": id(),
+            "name": "Tavern",
+            "root_node": a,
+            "characters_ids": [id()],
+            "created_at": "2024-05-01T10:00:00Z",
+            "main_character": id(),
+            "nodes": {
+                a.clone(): {"id": a, "pos_x": 0, "pos_y": 0, "data": {"Dialog": {
+                    "line_id": "DLG_TAVERN_0001", "next_node": b, "character_id": id(),
+                    "content_link": id(), "content": null
+                }}},
+                b.clone(): {"id": b, "pos_x": 1, "pos_y": 2, "data": {"Phylum": {
+                    "id": id(), "name": null, "branches": [{
+                        "id": id(), "priority": 0, "name": "default",
+                        "necessities": {"Tree": {
+                            "left": {"Var": {"var_id": id(), "necessary_state": "on"}},
+                            "operator": "AND",
+                            "right": {"Var": {"var_id": id(), "necessary_state": "awake"}}
+                        }},
+                        "next_node": c
+                    }]
+                }}},
+                c.clone(): {"id": c, "pos_x": 3, "pos_y": 4, "data": {"Choices": {
+                    "choices": [{"id": id(), "content": "Leave", "next_node": d}]
+                }}},
+                d.clone(): {"id": d, "pos_x": 5, "pos_y": 6, "data": {"Link": {
+                    "dialog_id": id(), "node_id": id(), "next_node": a
+                }}}
+            }
+        }));
+    }
+
+    #[test]
+    fn variable_store_round_trip() {
+        assert_round_trip::<variables::VariableStore, VariableStore>(json!({"data": [
+            {"Global": {"id": id(), "name": "day", "current_state": "1", "potential_states": ["1", "2"]}},
+            {"GlobalChar": {"id": id(), "name": "mood", "potential_states": ["calm", "angry"],
+                "characters": [{"current_state": "calm", "character_id": id()}], "group_id": id()}},
+            {"Char": {"id": id(), "name": "hp", "current_state": "full",
+                "potential_states": ["full"], "character_id": id()}},
+            {"Dialog": {"id": id(), "name": "seen", "current_state": "no",
+                "potential_states": ["no", "yes"], "dialog_id": id()}}
+        ]}));
+    }
+
+    #[test]
+    fn character_round_trip() {
+        assert_round_trip::<character::Character, Character>(json!({
+            "id": id(),
+            "display_name": "Bob",
+            "first_name": "Robert",
+            "last_name": null,
+            "description": "A bartender",
+            "description_link": id(),
+            "portrait_link": "bob.png",
+            "artwork_link": null,
+            "background_link": null,
+            "tags": ["npc"],
+            "group_ids": [id()],
+            "attributes": {
+                id(): {"Text": "tenor"},
+                id(): {"Number": 42.5},
+                id(): {"Enum": "guild"},
+                id(): {"Image": "bob-young.png"},
+                id(): {"Character": id()}
+            }
+        }));
+    }
+}
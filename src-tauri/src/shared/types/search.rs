@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// Where the text of a search hit comes from.
+#[derive(TS, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub enum SearchHitKind {
+    NodeText,
+    ChoiceText,
+    DialogName,
+    CharacterName,
+    CharacterDescription,
+    VariableName,
+}
+
+/// Search form. When filtering by speaker, only the lines said
+/// by that character are searched : the choices of a dialog are
+/// said by its main character. When filtering by dialog group, only
+/// the lines and names of the dialogs of the group and of its sub groups
+/// are searched.
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct SearchQuery {
+    pub text: String,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    #[serde(default)]
+    pub whole_word: bool,
+    #[serde(default)]
+    pub regex: bool,
+    pub speaker_id: Option<Uuid>,
+    pub dialog_group_id: Option<Uuid>,
+}
+
+/// A match of the search. The snippet is split around the
+/// matched text so that the frontend can highlight it.
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct SearchHit {
+    pub kind: SearchHitKind,
+    pub dialog_id: Option<Uuid>,
+    pub node_id: Option<Uuid>,
+    pub choice_id: Option<Uuid>,
+    pub character_id: Option<Uuid>,
+    pub variable_id: Option<Uuid>,
+    pub before: String,
+    pub matched: String,
+    pub after: String,
+}
+
+/// A match that can be replaced, shown in the preview of a find and replace.
+/// `start` and `end` are byte offsets in the line and `line_checksum` the hash
+/// of the line when the preview was computed : they are only meaningful
+/// to the backend and must be sent back as is to apply the replacement.
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct ReplaceMatch {
+    pub dialog_id: Uuid,
+    pub node_id: Uuid,
+    pub choice_id: Option<Uuid>,
+    pub start: usize,
+    pub end: usize,
+    pub line_checksum: String,
+    pub before: String,
+    pub matched: String,
+    pub replacement: String,
+    pub after: String,
+}
+
+/// Outcome of a find and replace. Lines changed since the preview are
+/// left untouched and reported as conflicts. When lines were replaced, the
+/// backup is the snapshot to recover to undo them.
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct ReplaceReport {
+    pub replaced: usize,
+    pub dialogs: Vec<Uuid>,
+    pub conflicts: Vec<ReplaceMatch>,
+    pub backup: Option<String>,
+}
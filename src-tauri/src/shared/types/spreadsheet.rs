@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// One row of the spreadsheet used by editors to proofread
+/// the dialogs. Each row is either the content of a dialog
+/// node or the content of a choice (in which case `choice_id`
+/// is set).
+/// The checksum is the hash of the text at export time, and
+/// is used on import to detect lines that were changed in
+/// the application since the export.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DialogLineRow {
+    pub project: String,
+    pub dialog: String,
+    pub dialog_id: Uuid,
+    pub node_id: Uuid,
+    pub choice_id: Option<Uuid>,
+    #[serde(default)]
+    pub line_id: Option<String>,
+    pub speaker: String,
+    pub text: String,
+    pub checksum: String,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct LineReference {
+    pub dialog_id: Uuid,
+    pub node_id: Uuid,
+    pub choice_id: Option<Uuid>,
+}
+
+/// A line that was edited in the spreadsheet while it was
+/// also changed in the application after the export.
+/// Those lines are never applied.
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct LineConflict {
+    pub line: LineReference,
+    pub current_text: String,
+    pub proposed_text: String,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct LineImportReport {
+    pub updated: Vec<LineReference>,
+    pub unchanged: usize,
+    pub conflicts: Vec<LineConflict>,
+    pub missing: Vec<LineReference>,
+}
+
+impl DialogLineRow {
+    pub fn get_reference(&self) -> LineReference {
+        LineReference {
+            dialog_id: self.dialog_id,
+            node_id: self.node_id,
+            choice_id: self.choice_id,
+        }
+    }
+}
+
+impl LineImportReport {
+    pub fn new() -> Self {
+        Self {
+            updated: vec![],
+            unchanged: 0,
+            conflicts: vec![],
+            missing: vec![],
+        }
+    }
+}
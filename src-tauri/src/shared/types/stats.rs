@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::shared::types::localization::LocaleProgress;
+
+/// Figures about the content of a project,
+/// used by producers to follow its progress.
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct ProjectStats {
+    pub dialogs: usize,
+    pub characters: usize,
+    pub variables: usize,
+    pub nodes: usize,
+    pub lines: usize,
+    pub words: usize,
+    pub translations: Vec<LocaleProgress>,
+}
@@ -0,0 +1,141 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// File formats understood by CAT tools.
+#[derive(TS, Serialize, Deserialize, Debug, Clone, Copy)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub enum TranslationFormat {
+    Xliff,
+    Po,
+}
+
+/// Identifies a translatable string across export and import.
+/// Serialized as `line:<dialog>:<node>[:<choice>]` or `character:<id>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranslationKey {
+    Line {
+        dialog_id: Uuid,
+        node_id: Uuid,
+        choice_id: Option<Uuid>,
+    },
+    CharacterName {
+        character_id: Uuid,
+    },
+}
+
+/// A string to translate, with the context given to the translator.
+pub struct TranslationUnit {
+    pub key: TranslationKey,
+    pub group_id: String,
+    pub group_name: String,
+    pub source: String,
+    pub target: Option<String>,
+    pub speaker: Option<String>,
+    pub previous_line: Option<String>,
+    pub notes: Vec<String>,
+}
+
+/// A string read back from a translated file.
+pub struct ParsedUnit {
+    pub key: String,
+    pub source: String,
+    pub target: Option<String>,
+}
+
+pub struct ParsedTranslations {
+    pub target_locale: Option<String>,
+    pub units: Vec<ParsedUnit>,
+}
+
+/// A translation whose source text changed between the export
+/// and the import. It is still written, but remains stale until
+/// it is reviewed.
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct StaleTranslation {
+    pub key: String,
+    pub translated_source: String,
+    pub current_source: String,
+    pub text: String,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct TranslationImportReport {
+    pub updated: Vec<String>,
+    pub unchanged: usize,
+    pub untranslated: usize,
+    pub stale: Vec<StaleTranslation>,
+    pub missing: Vec<String>,
+}
+
+impl TranslationKey {
+    /// Id of the string in the locale tables.
+    pub fn get_table_id(&self) -> &Uuid {
+        match self {
+            TranslationKey::Line {
+                node_id, choice_id, ..
+            } => choice_id.as_ref().unwrap_or(node_id),
+            TranslationKey::CharacterName { character_id } => character_id,
+        }
+    }
+}
+
+impl Display for TranslationKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TranslationKey::Line {
+                dialog_id,
+                node_id,
+                choice_id: Some(choice_id),
+            } => write!(f, "line:{dialog_id}:{node_id}:{choice_id}"),
+            TranslationKey::Line {
+                dialog_id, node_id, ..
+            } => write!(f, "line:{dialog_id}:{node_id}"),
+            TranslationKey::CharacterName { character_id } => write!(f, "character:{character_id}"),
+        }
+    }
+}
+
+impl FromStr for TranslationKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let parse = |i: usize| -> Result<Uuid> {
+            Uuid::from_str(parts[i]).map_err(|_| anyhow!("invalid translation key {s}"))
+        };
+        match (parts.first(), parts.len()) {
+            (Some(&"line"), 3) => Ok(TranslationKey::Line {
+                dialog_id: parse(1)?,
+                node_id: parse(2)?,
+                choice_id: None,
+            }),
+            (Some(&"line"), 4) => Ok(TranslationKey::Line {
+                dialog_id: parse(1)?,
+                node_id: parse(2)?,
+                choice_id: Some(parse(3)?),
+            }),
+            (Some(&"character"), 2) => Ok(TranslationKey::CharacterName {
+                character_id: parse(1)?,
+            }),
+            _ => bail!("invalid translation key {s}"),
+        }
+    }
+}
+
+impl TranslationImportReport {
+    pub fn new() -> Self {
+        Self {
+            updated: vec![],
+            unchanged: 0,
+            untranslated: 0,
+            stale: vec![],
+            missing: vec![],
+        }
+    }
+}
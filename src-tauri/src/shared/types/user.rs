@@ -0,0 +1,21 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// Someone who used the application on this machine.
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct UserProfile {
+    pub name: String,
+    pub email: Option<String>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct RecentProject {
+    pub project_id: Uuid,
+    pub name: String,
+    pub opened_at: DateTime<Utc>,
+}
@@ -1,11 +1,17 @@
-use std::{char, collections::HashSet};
+use std::{
+    char,
+    collections::{HashMap, HashSet},
+};
 
-use anyhow::{Result, bail};
+use anyhow::{Result, anyhow, bail};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 use uuid::Uuid;
 
-use crate::shared::types::interfaces::Identified;
+use crate::shared::types::{
+    interfaces::{Identified, LineDelimited},
+    proto::{self, parse_id, parse_opt_id},
+};
 
 #[derive(TS)]
 #[ts(export, export_to = "../../src/bindings/")]
@@ -25,6 +31,9 @@ pub struct GlobalCharacterVariable {
     name: String,
     potential_states: Vec<String>,
     characters: Vec<CharacterVariableState>,
+    /// When set, the variable holds a state for each character of the group.
+    #[serde(default)]
+    group_id: Option<Uuid>,
 }
 
 #[derive(TS)]
@@ -75,6 +84,43 @@ pub struct VariableStore {
     pub data: Vec<Variable>,
 }
 
+/// Condition of a dialog reading a variable.
+#[derive(TS)]
+#[ts(export, export_to = "../../src/bindings/")]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VariableUsage {
+    pub variable_id: Uuid,
+    pub dialog_id: Uuid,
+    pub dialog_name: String,
+    pub node_id: Uuid,
+    pub phylum_id: Uuid,
+    pub phylum_name: Option<String>,
+    pub condition_id: Uuid,
+    pub condition_name: String,
+    pub necessary_state: String,
+}
+
+/// Renaming of a state of a variable, applied to the conditions
+/// and current states using it when the variables are saved.
+#[derive(TS)]
+#[ts(export, export_to = "../../src/bindings/")]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StateRename {
+    pub variable_id: Uuid,
+    pub from: String,
+    pub to: String,
+}
+
+/// Every place of the project where a variable is used.
+#[derive(TS)]
+#[ts(export, export_to = "../../src/bindings/")]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VariableUsages {
+    pub variable_id: Uuid,
+    pub variable_name: String,
+    pub usages: Vec<VariableUsage>,
+}
+
 #[enum_dispatch::enum_dispatch]
 trait CoherentVar {
     fn enforce_coherence(
@@ -172,6 +218,41 @@ impl Identified for DialogVariable {
     }
 }
 
+impl Variable {
+    pub fn get_name(&self) -> &String {
+        match self {
+            Variable::Global(v) => &v.name,
+            Variable::GlobalChar(v) => &v.name,
+            Variable::Char(v) => &v.name,
+            Variable::Dialog(v) => &v.name,
+        }
+    }
+
+    pub fn get_potential_states(&self) -> &Vec<String> {
+        match self {
+            Variable::Global(v) => &v.potential_states,
+            Variable::GlobalChar(v) => &v.potential_states,
+            Variable::Char(v) => &v.potential_states,
+            Variable::Dialog(v) => &v.potential_states,
+        }
+    }
+
+    /// Renames the current states of the variable. Potential states are left as is.
+    fn rename_current_state(&mut self, from: &str, to: &str) {
+        let states = match self {
+            Variable::Global(v) => vec![&mut v.current_state],
+            Variable::GlobalChar(v) => v.characters.iter_mut().map(|c| &mut c.current_state).collect(),
+            Variable::Char(v) => vec![&mut v.current_state],
+            Variable::Dialog(v) => vec![&mut v.current_state],
+        };
+        for state in states {
+            if state == from {
+                *state = String::from(to);
+            }
+        }
+    }
+}
+
 impl VariableStore {
     pub fn new() -> Self {
         Self { data: vec![] }
@@ -180,5 +261,494 @@ impl VariableStore {
     pub fn delete_var_with_id(&mut self, char_id: &Uuid) {
         self.data.retain(|x| *x.get_id() != *char_id);
     }
+
+    /// States of the variables of this store which are not potential states anymore
+    /// in the new one. Variables absent from the new store are ignored.
+    pub fn get_removed_states(&self, new: &VariableStore) -> HashMap<Uuid, HashSet<String>> {
+        let mut res = HashMap::new();
+        for var in &new.data {
+            let old = match self.data.iter().find(|v| v.get_id() == var.get_id()) {
+                Some(v) => v,
+                None => continue,
+            };
+            let removed: HashSet<String> = old
+                .get_potential_states()
+                .iter()
+                .filter(|s| !var.get_potential_states().contains(s))
+                .cloned()
+                .collect();
+            if !removed.is_empty() {
+                res.insert(*var.get_id(), removed);
+            }
+        }
+        res
+    }
+
+    /// Checks that each rename goes from a removed state to a potential
+    /// state of the variable, then applies it to the current states.
+    pub fn apply_state_renames(
+        &mut self,
+        renames: &[StateRename],
+        removed: &HashMap<Uuid, HashSet<String>>,
+    ) -> Result<()> {
+        for rename in renames {
+            let var = self
+                .data
+                .iter_mut()
+                .find(|v| *v.get_id() == rename.variable_id)
+                .ok_or(anyhow!("variable {} does not exist", rename.variable_id))?;
+            if !removed
+                .get(&rename.variable_id)
+                .is_some_and(|r| r.contains(&rename.from))
+            {
+                bail!("state {} of variable {} was not removed", rename.from, var.get_name())
+            }
+            if !var.get_potential_states().contains(&rename.to) {
+                bail!("state {} of variable {} does not exist", rename.to, var.get_name())
+            }
+            var.rename_current_state(&rename.from, &rename.to);
+        }
+        Ok(())
+    }
+
+    /// Checks that the conditions only need potential states of their variables.
+    pub fn enforce_necessary_states(&self, usages: &[VariableUsage]) -> Result<()> {
+        for usage in usages {
+            let var = self
+                .data
+                .iter()
+                .find(|v| *v.get_id() == usage.variable_id)
+                .ok_or(anyhow!("variable {} does not exist", usage.variable_id))?;
+            if !var.get_potential_states().contains(&usage.necessary_state) {
+                bail!(
+                    "condition {} of dialog {} needs state {} which variable {} does not have",
+                    usage.condition_name,
+                    usage.dialog_name,
+                    usage.necessary_state,
+                    var.get_name()
+                )
+            }
+        }
+        Ok(())
+    }
+
+    /// Gives each global character variable targeting a group a state for
+    /// every member of the group, starting at its first potential state,
+    /// and removes the states of the characters which left it.
+    pub fn sync_group_targets(&mut self, members: &HashMap<Uuid, Vec<Uuid>>) -> Result<()> {
+        for var in self.data.iter_mut() {
+            let var = match var {
+                Variable::GlobalChar(g) => g,
+                _ => continue,
+            };
+            let group_id = match var.group_id {
+                Some(id) => id,
+                None => continue,
+            };
+            let group_members = members
+                .get(&group_id)
+                .ok_or(anyhow!("group {group_id} of variable {} does not exist", var.name))?;
+            var.characters
+                .retain(|c| group_members.contains(&c.character_id));
+            for member in group_members {
+                if !var.characters.iter().any(|c| c.character_id == *member) {
+                    var.characters.push(CharacterVariableState {
+                        current_state: var.potential_states.first().cloned().unwrap_or_default(),
+                        character_id: *member,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Ids of the global character variables targeting a group.
+    pub fn get_group_targets(&self, group_id: &Uuid) -> Vec<Uuid> {
+        self.data
+            .iter()
+            .filter_map(|v| match v {
+                Variable::GlobalChar(g) if g.group_id == Some(*group_id) => Some(g.id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn is_global_character_variable(&self, variable_id: &Uuid) -> bool {
+        self.data
+            .iter()
+            .any(|v| matches!(v, Variable::GlobalChar(g) if g.id == *variable_id))
+    }
+
+    /// Current state of a character in a global character variable.
+    pub fn get_character_state(&self, variable_id: &Uuid, character_id: &Uuid) -> Result<&str> {
+        let var = self
+            .data
+            .iter()
+            .find_map(|v| match v {
+                Variable::GlobalChar(g) if g.id == *variable_id => Some(g),
+                _ => None,
+            })
+            .ok_or(anyhow!("global character variable {variable_id} does not exist"))?;
+        var.characters
+            .iter()
+            .find(|c| c.character_id == *character_id)
+            .map(|c| c.current_state.as_str())
+            .ok_or(anyhow!(
+                "variable {} holds no state for character {character_id}",
+                var.name
+            ))
+    }
+
+    /// Ids of the variables scoped to a dialog.
+    pub fn get_dialog_variables(&self, dialog_id: &Uuid) -> Vec<Uuid> {
+        self.data
+            .iter()
+            .filter_map(|v| match v {
+                Variable::Dialog(d) if d.dialog_id == *dialog_id => Some(d.id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Ids of the character variables of a character.
+    pub fn get_character_variables(&self, character_id: &Uuid) -> Vec<Uuid> {
+        self.data
+            .iter()
+            .filter_map(|v| match v {
+                Variable::Char(c) if c.character_id == *character_id => Some(c.id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Ids of the global character variables holding a state for a character.
+    pub fn get_global_character_variables(&self, character_id: &Uuid) -> Vec<Uuid> {
+        self.data
+            .iter()
+            .filter_map(|v| match v {
+                Variable::GlobalChar(g)
+                    if g.characters.iter().any(|c| c.character_id == *character_id) =>
+                {
+                    Some(g.id)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Moves the variables of a character to another one. A global character
+    /// variable already holding a state for the other character keeps it.
+    pub fn reassign_character(&mut self, character_id: &Uuid, to: &Uuid) {
+        for var in self.data.iter_mut() {
+            match var {
+                Variable::Char(c) if c.character_id == *character_id => c.character_id = *to,
+                Variable::GlobalChar(g) => {
+                    if g.characters.iter().any(|c| c.character_id == *to) {
+                        g.characters.retain(|c| c.character_id != *character_id);
+                    } else {
+                        for state in g.characters.iter_mut() {
+                            if state.character_id == *character_id {
+                                state.character_id = *to;
+                            }
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Deletes the character variables of a character,
+    /// and its states in global character variables.
+    pub fn remove_character(&mut self, character_id: &Uuid) {
+        self.data.retain(|v| match v {
+            Variable::Char(c) => c.character_id != *character_id,
+            _ => true,
+        });
+        for var in self.data.iter_mut() {
+            if let Variable::GlobalChar(g) = var {
+                g.characters.retain(|c| c.character_id != *character_id);
+            }
+        }
+    }
+}
+
+impl LineDelimited for VariableStore {
+    const COLLECTIONS: &'static [&'static str] = &["data"];
+}
+
+impl From<&VariableStore> for proto::VariableStore {
+    fn from(store: &VariableStore) -> Self {
+        Self {
+            data: store.data.iter().map(proto::Variable::from).collect(),
+        }
+    }
 }
 
+impl TryFrom<proto::VariableStore> for VariableStore {
+    type Error = anyhow::Error;
+
+    fn try_from(store: proto::VariableStore) -> Result<Self> {
+        Ok(Self {
+            data: store
+                .data
+                .into_iter()
+                .map(Variable::try_from)
+                .collect::<Result<Vec<Variable>>>()?,
+        })
+    }
+}
+
+impl From<&Variable> for proto::Variable {
+    fn from(var: &Variable) -> Self {
+        let kind = match var {
+            Variable::Global(v) => proto::variable::Kind::Global(proto::GlobalVariable {
+                id: v.id.to_string(),
+                name: v.name.clone(),
+                current_state: v.current_state.clone(),
+                potential_states: v.potential_states.clone(),
+            }),
+            Variable::GlobalChar(v) => {
+                proto::variable::Kind::GlobalChar(proto::GlobalCharacterVariable {
+                    id: v.id.to_string(),
+                    name: v.name.clone(),
+                    potential_states: v.potential_states.clone(),
+                    characters: v
+                        .characters
+                        .iter()
+                        .map(|c| proto::CharacterVariableState {
+                            current_state: c.current_state.clone(),
+                            character_id: c.character_id.to_string(),
+                        })
+                        .collect(),
+                    group_id: v.group_id.map(|g| g.to_string()),
+                })
+            }
+            Variable::Char(v) => proto::variable::Kind::Char(proto::CharacterVariable {
+                id: v.id.to_string(),
+                name: v.name.clone(),
+                current_state: v.current_state.clone(),
+                potential_states: v.potential_states.clone(),
+                character_id: v.character_id.to_string(),
+            }),
+            Variable::Dialog(v) => proto::variable::Kind::Dialog(proto::DialogVariable {
+                id: v.id.to_string(),
+                name: v.name.clone(),
+                current_state: v.current_state.clone(),
+                potential_states: v.potential_states.clone(),
+                dialog_id: v.dialog_id.to_string(),
+            }),
+        };
+        Self { kind: Some(kind) }
+    }
+}
+
+impl TryFrom<proto::Variable> for Variable {
+    type Error = anyhow::Error;
+
+    fn try_from(var: proto::Variable) -> Result<Self> {
+        let var = match var.kind.ok_or(anyhow!("empty variable"))? {
+            proto::variable::Kind::Global(v) => Variable::Global(GlobalVariable {
+                id: parse_id(&v.id)?,
+                name: v.name,
+                current_state: v.current_state,
+                potential_states: v.potential_states,
+            }),
+            proto::variable::Kind::GlobalChar(v) => {
+                Variable::GlobalChar(GlobalCharacterVariable {
+                    id: parse_id(&v.id)?,
+                    name: v.name,
+                    potential_states: v.potential_states,
+                    characters: v
+                        .characters
+                        .into_iter()
+                        .map(|c| {
+                            Ok(CharacterVariableState {
+                                current_state: c.current_state,
+                                character_id: parse_id(&c.character_id)?,
+                            })
+                        })
+                        .collect::<Result<Vec<CharacterVariableState>>>()?,
+                    group_id: parse_opt_id(v.group_id.as_ref())?,
+                })
+            }
+            proto::variable::Kind::Char(v) => Variable::Char(CharacterVariable {
+                id: parse_id(&v.id)?,
+                name: v.name,
+                current_state: v.current_state,
+                potential_states: v.potential_states,
+                character_id: parse_id(&v.character_id)?,
+            }),
+            proto::variable::Kind::Dialog(v) => Variable::Dialog(DialogVariable {
+                id: parse_id(&v.id)?,
+                name: v.name,
+                current_state: v.current_state,
+                potential_states: v.potential_states,
+                dialog_id: parse_id(&v.dialog_id)?,
+            }),
+        };
+        Ok(var)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn global(potential: &[&str]) -> Variable {
+        Variable::Global(GlobalVariable {
+            id: Uuid::new_v4(),
+            name: String::from("day"),
+            current_state: String::from(potential[0]),
+            potential_states: states(potential),
+        })
+    }
+
+    fn usage(variable_id: Uuid, necessary_state: &str) -> VariableUsage {
+        VariableUsage {
+            variable_id,
+            dialog_id: Uuid::new_v4(),
+            dialog_name: String::from("Tavern"),
+            node_id: Uuid::new_v4(),
+            phylum_id: Uuid::new_v4(),
+            phylum_name: None,
+            condition_id: Uuid::new_v4(),
+            condition_name: String::from("default"),
+            necessary_state: String::from(necessary_state),
+        }
+    }
+
+    fn states(states: &[&str]) -> Vec<String> {
+        states.iter().map(|s| String::from(*s)).collect()
+    }
+
+    fn set_states(var: &mut Variable, new_states: &[&str]) {
+        if let Variable::Global(g) = var {
+            g.potential_states = states(new_states);
+        }
+    }
+
+    fn current_state(var: &Variable) -> &str {
+        match var {
+            Variable::Global(g) => &g.current_state,
+            _ => unreachable!(),
+        }
+    }
+
+    fn rename(variable_id: Uuid, from: &str, to: &str) -> StateRename {
+        StateRename {
+            variable_id,
+            from: String::from(from),
+            to: String::from(to),
+        }
+    }
+
+    #[test]
+    fn removed_states_are_listed_per_variable() {
+        let old = VariableStore {
+            data: vec![global(&["morning", "noon", "evening"]), global(&["yes", "no"])],
+        };
+        let mut new = VariableStore {
+            data: vec![global(&["yes"])],
+        };
+        let first_id = *old.data[0].get_id();
+        let mut first = global(&["morning"]);
+        if let Variable::Global(g) = &mut first {
+            g.id = first_id;
+        }
+        new.data.push(first);
+
+        let removed = old.get_removed_states(&new);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(
+            removed[&first_id],
+            HashSet::from([String::from("noon"), String::from("evening")])
+        );
+    }
+
+    #[test]
+    fn renames_apply_to_current_states() {
+        let old = VariableStore {
+            data: vec![global(&["morning", "evening"])],
+        };
+        let mut new = VariableStore {
+            data: vec![global(&["evening", "dawn"])],
+        };
+        let var_id = *old.data[0].get_id();
+        if let Variable::Global(g) = &mut new.data[0] {
+            g.id = var_id;
+            g.current_state = String::from("morning");
+        }
+        let removed = old.get_removed_states(&new);
+        new.apply_state_renames(&[rename(var_id, "morning", "dawn")], &removed)
+            .unwrap();
+        assert_eq!(current_state(&new.data[0]), "dawn");
+    }
+
+    #[test]
+    fn renames_must_go_from_a_removed_state_to_a_potential_one() {
+        let mut store = VariableStore {
+            data: vec![global(&["morning", "evening"])],
+        };
+        let var_id = *store.data[0].get_id();
+        let removed = HashMap::from([(var_id, HashSet::from([String::from("noon")]))]);
+        assert!(store
+            .apply_state_renames(&[rename(var_id, "morning", "evening")], &removed)
+            .is_err());
+        assert!(store
+            .apply_state_renames(&[rename(var_id, "noon", "night")], &removed)
+            .is_err());
+        assert!(store
+            .apply_state_renames(&[rename(Uuid::new_v4(), "noon", "evening")], &removed)
+            .is_err());
+        set_states(&mut store.data[0], &["morning", "evening", "night"]);
+        assert!(store
+            .apply_state_renames(&[rename(var_id, "noon", "night")], &removed)
+            .is_ok());
+    }
+
+    #[test]
+    fn group_targets_follow_the_members_of_the_group() {
+        let group_id = Uuid::new_v4();
+        let (stays, leaves, joins) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut store = VariableStore {
+            data: vec![Variable::GlobalChar(GlobalCharacterVariable {
+                id: Uuid::new_v4(),
+                name: String::from("mood"),
+                potential_states: states(&["calm", "angry"]),
+                characters: vec![
+                    CharacterVariableState {
+                        current_state: String::from("angry"),
+                        character_id: stays,
+                    },
+                    CharacterVariableState {
+                        current_state: String::from("calm"),
+                        character_id: leaves,
+                    },
+                ],
+                group_id: Some(group_id),
+            })],
+        };
+        let var_id = *store.data[0].get_id();
+        store
+            .sync_group_targets(&HashMap::from([(group_id, vec![stays, joins])]))
+            .unwrap();
+        assert_eq!(store.get_character_state(&var_id, &stays).unwrap(), "angry");
+        assert_eq!(store.get_character_state(&var_id, &joins).unwrap(), "calm");
+        assert!(store.get_character_state(&var_id, &leaves).is_err());
+        assert!(store.sync_group_targets(&HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn conditions_must_need_potential_states() {
+        let store = VariableStore {
+            data: vec![global(&["morning", "evening"])],
+        };
+        let var_id = *store.data[0].get_id();
+        assert!(store.enforce_necessary_states(&[usage(var_id, "evening")]).is_ok());
+        assert!(store.enforce_necessary_states(&[usage(var_id, "night")]).is_err());
+        assert!(store
+            .enforce_necessary_states(&[usage(Uuid::new_v4(), "evening")])
+            .is_err());
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::shared::types::interfaces::LineDelimited;
+
+/// Audio files of the lines of a dialog, keyed by node id
+/// then by locale. Files are stored in the assets of the project.
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct VoiceOverTable {
+    pub entries: BTreeMap<Uuid, BTreeMap<String, String>>,
+}
+
+/// A voiced line that has no audio file yet for a locale.
+#[derive(TS, Serialize, Deserialize, Debug)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct MissingAudio {
+    pub dialog_id: Uuid,
+    pub dialog_name: String,
+    pub node_id: Uuid,
+    pub line_id: Option<String>,
+    pub locale: String,
+    pub text: String,
+}
+
+impl VoiceOverTable {
+    pub fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+        }
+    }
+
+    pub fn get_audio(&self, node_id: &Uuid, locale: &str) -> Option<&String> {
+        self.entries.get(node_id).and_then(|l| l.get(locale))
+    }
+
+    /// Links the audio file to the line, returning the file it replaced.
+    pub fn set_audio(&mut self, node_id: Uuid, locale: &str, file: String) -> Option<String> {
+        self.entries
+            .entry(node_id)
+            .or_default()
+            .insert(String::from(locale), file)
+    }
+
+    pub fn remove_audio(&mut self, node_id: &Uuid, locale: &str) -> Option<String> {
+        let locales = self.entries.get_mut(node_id)?;
+        let res = locales.remove(locale);
+        if locales.is_empty() {
+            self.entries.remove(node_id);
+        }
+        res
+    }
+}
+
+impl LineDelimited for VoiceOverTable {
+    const COLLECTIONS: &'static [&'static str] = &["entries"];
+}
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// Kind of project data changed outside of the application.
+#[derive(TS, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub enum ChangeTarget {
+    Dialog,
+    DialogMetadata,
+    Character,
+    CharacterMetadata,
+    Variables,
+    Meta,
+}
+
+/// Payload of the event emitted when files of a project are modified
+/// outside of the application, after the data has been reloaded.
+/// `error` is set when the reloaded data is invalid, and `removed`
+/// when the dialog or character does not exist anymore.
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+#[ts(export, export_to = "../../src/bindings/")]
+pub struct ProjectFileChange {
+    pub project_id: String,
+    pub target: ChangeTarget,
+    pub target_id: Option<Uuid>,
+    pub removed: bool,
+    pub error: Option<String>,
+}
@@ -1,10 +1,12 @@
 import { createContext, useContext, useState, useCallback, PropsWithChildren } from "react"
+import { invoke } from "@tauri-apps/api/core"
 import { Project } from "../bindings/Project"
 import { useVariables, VariableContext } from "../hooks/useVariables"
 
 type GlobalStateContextType = {
   project: Project | undefined
   openProject: (project: Project) => void
+  openProjectError: string | undefined
   closeProject: () => void
 } & VariableContext
 
@@ -12,10 +14,14 @@ const GlobalStateContext = createContext<GlobalStateContextType | undefined>(und
 
 export const GlobalStateProvider = ({ children }: PropsWithChildren) => {
   const [project, setProject] = useState<Project | undefined>(undefined)
+  const [openProjectError, setOpenProjectError] = useState<string | undefined>(undefined)
   const variables = useVariables(project?.id)
 
   const openProject = useCallback((project: Project) => {
-    setProject(project)
+    setOpenProjectError(undefined)
+    invoke("open_project", { projectId: project.id })
+      .then(() => setProject(project))
+      .catch((e) => setOpenProjectError(`Could not open ${project.name}: ${e}`))
   }, [])
 
   const closeProject = useCallback(() => {
@@ -23,7 +29,7 @@ export const GlobalStateProvider = ({ children }: PropsWithChildren) => {
   }, [])
 
   return (
-    <GlobalStateContext.Provider value={{ project, openProject, closeProject,  ...variables}}>
+    <GlobalStateContext.Provider value={{ project, openProject, openProjectError, closeProject,  ...variables}}>
       {children}
     </GlobalStateContext.Provider>
   )
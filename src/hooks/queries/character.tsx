@@ -76,7 +76,7 @@ export function useDeleteCharacter(): UseMutationResult<void, Error, DeleteChara
   return useMutation({
     mutationKey: ['characters', 'delete'],
     mutationFn: async ({ projectId, characterId }: DeleteCharacterParams) =>
-      invoke<void>("delete_character", { projectId, characterId }),
+      invoke<void>("delete_character", { projectId, characterId, mode: "Block" }),
     onSuccess: (_, { projectId }) => {
       queryClient.invalidateQueries({ queryKey: ['characters', 'all', projectId] });
     }
@@ -10,7 +10,7 @@ export const HomePage: React.FC = () => {
   const { data: projets, isPending, error } = useGetProjects();
   const [showOverlay, setShowOverlay] = useState(false);
   const [showSpinner, setShowSpinner] = useState(false);
-  const { openProject } = useGlobalState();
+  const { openProject, openProjectError } = useGlobalState();
 
   useEffect(() => {
     if (!isPending) {
@@ -51,6 +51,12 @@ export const HomePage: React.FC = () => {
             </div>
           )}
 
+          {openProjectError && (
+            <div className="p-4 mb-6 rounded-lg bg-red-900/20 border border-red-400/30 text-red-400 text-center">
+              {openProjectError}
+            </div>
+          )}
+
           {hasProjects && (
             <div className="bg-base-surface rounded-lg p-6 mb-6">
               <div className="flex items-center justify-between mb-4">